@@ -1,29 +1,35 @@
 use axum::{
+    extract::State,
     routing::{get, post},
     Form, Router,
 };
-use lettre::{
-    message::header::ContentType, transport::smtp::authentication::Credentials, Message,
-    SmtpTransport, Transport,
-};
+use lettre::message::Mailbox;
 use maud::{html, Markup};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     links::{EMAIL, PHONE, WHATSAPP_LINK},
-    under_construction, ClientState,
+    outbox::{self, QueuedEmail},
+    page::error_fragment,
+    seo::Meta,
+    store::Record,
+    ClientState,
 };
 
 pub fn about_router() -> Router<ClientState> {
-    return Router::new()
+    Router::new()
         .route("/about", get(about_page))
         .route("/bylaw", get(bylaw_page))
         .route("/team", get(team_page))
         .route("/contact", get(contact_page))
-        .route("/contact_response", post(contact_response));
+        .route("/contact_response", post(contact_response))
 }
-pub async fn about_page() -> Markup {
-    html! {
+pub async fn about_page() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "About Us",
+        "Learn about NJ Thiruvalluvar Tamil Sangam, a non-profit that brings the Tamil community of New Jersey together.",
+    );
+    let content = html! {
         div class="bg-vertical-to-pink"{
 
         div class="bg-white bg-opacity-90 px-4 mx-auto max-w-screen-md relative z-5" {
@@ -39,7 +45,7 @@ pub async fn about_page() -> Markup {
                 "At the heart of our mission lies a dedication to preserving and promoting Tamil culture, language, and identity. Through a diverse array of programs, events, and initiatives, we seek to celebrate our heritage, instill pride in our traditions, and pass down our cultural legacy to future generations."
             }
                   p class="text-sm md:text-base lg:text-lg xl:text-xl 2xl:text-2xl font-semibold cursor-pointer"{
-                                    a href="assets/img/TTS-Donation-Receipt.pdf" class="text-blue-500 underline"{"TTS Donation Receipt"}                                 }
+                                    a href="/assets/img/TTS-Donation-Receipt.pdf" class="text-blue-500 underline"{"TTS Donation Receipt"}                                 }
             p class="mb-4 lg:mb-8 font-light sm:text-xl" {
                 "But our work extends far beyond cultural preservation – it's about empowerment, advocacy, and creating opportunities for growth and advancement. From educational initiatives and skill-building workshops to social welfare projects and community outreach efforts, we are committed to addressing the needs of our community and empowering individuals to realize their full potential."
             }
@@ -65,10 +71,15 @@ pub async fn about_page() -> Markup {
             }
         }
     }
-    }
+    };
+    (meta, content)
 }
-pub async fn team_page() -> Markup {
-    html! {
+pub async fn team_page() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Our Team",
+        "Meet the board of directors and volunteers who run NJ Thiruvalluvar Tamil Sangam.",
+    );
+    let content = html! {
         div class="bg-vertical-to-pink"{
 
         div class="bg-pink-500 bg-opacity-90 px-4 mx-auto max-w-screen-md relative z-5" {
@@ -112,24 +123,30 @@ pub async fn team_page() -> Markup {
             }
         }
     }
-    }
+    };
+    (meta, content)
 }
 
-pub async fn bylaw_page() -> Markup {
-    html! {
+pub async fn bylaw_page() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Bylaw",
+        "The bylaws that govern NJ Thiruvalluvar Tamil Sangam.",
+    );
+    let content = html! {
         div class="container mx-auto px-4 py-8 text-center" {
                     h1 class="text-3xl font-bold mb-4" { "Bylaws" }
 
                     // Embedding PDF using iframe
                     div class="w-full h-screen overflow-y-auto border border-gray-300 shadow-lg rounded-lg" {
                         iframe
-                            src="assets/img/under_construction.jpg"
+                            src="/assets/img/under_construction.jpg"
                             class="w-full h-full"
                             title="Embedded PDF Viewer"
                             { "Your browser does not support PDF viewing. You can download the PDF file <a href=\"{pdf_url}\">here</a> instead." }
                     }
                 }
-    }
+    };
+    (meta, content)
 }
 
 /*pub async fn team_page() -> Markup {
@@ -142,8 +159,12 @@ pub async fn bylaw_page() -> Markup {
 
     }
 }*/
-pub async fn contact_page() -> Markup {
-    html! {
+pub async fn contact_page() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Contact Us",
+        "Send NJ Thiruvalluvar Tamil Sangam a message or reach us by phone and email.",
+    );
+    let content = html! {
         div class="bg-vertical-to-pink"{
         div class="max-w-7xl mx-auto p-8" {
             h1 class="text-3xl font-bold mb-6 text-center" { "Contact Us" }
@@ -202,9 +223,10 @@ pub async fn contact_page() -> Markup {
             }
         }
         }
-    }
+    };
+    (meta, content)
 }
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContactFormData {
     first_name: String,
     last_name: String,
@@ -212,6 +234,9 @@ pub struct ContactFormData {
     subject: String,
     message: String,
 }
+impl Record for ContactFormData {
+    const COLLECTION: &'static str = "contact_messages";
+}
 impl std::fmt::Display for ContactFormData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -222,42 +247,33 @@ impl std::fmt::Display for ContactFormData {
     }
 }
 
-pub async fn contact_response(Form(data): Form<ContactFormData>) -> Markup {
-    let smtp_username = std::env::var("SMTP_USERNAME").expect("SMTP_USERNAME must be set");
-    let smtp_password = std::env::var("SMTP_PASSWORD").expect("SMTP_PASSWORD must be set");
-    let smtp_server = std::env::var("SMTP_SERVER").expect("SMTP_SERVER must be set");
-    let self_email = Message::builder()
-        .from(smtp_username.parse().unwrap())
-        .to(smtp_username.parse().unwrap())
-        .subject(format!("Contact from {}", data.email))
-        .header(ContentType::TEXT_PLAIN)
-        .body(data.to_string())
-        .unwrap();
-    let email = Message::builder()
-        .from(smtp_username.parse().unwrap())
-        .to(data.email.parse().unwrap())
-        .subject(format!("Contact from {}", data.email))
-        .header(ContentType::TEXT_PLAIN)
-        .body(data.to_string())
-        .unwrap();
-
-    let creds = Credentials::new(smtp_username.to_owned(), smtp_password.to_owned());
-
-    // Open a remote connection to gmail
-    let mailer = SmtpTransport::relay(smtp_server.as_str())
-        .unwrap()
-        .credentials(creds)
-        .build();
-
-    // Send the email
-    match mailer.send(&self_email) {
-        Ok(_) => println!("Email sent successfully!"),
-        Err(e) => panic!("Could not send email: {e:?}"),
-    }
-    match mailer.send(&email) {
-        Ok(_) => println!("Email sent successfully!"),
-        Err(e) => panic!("Could not send email: {e:?}"),
+pub async fn contact_response(
+    State(s): State<ClientState>,
+    Form(data): Form<ContactFormData>,
+) -> Markup {
+    let Ok(sender) = data.email.parse::<Mailbox>() else {
+        tracing::info!("contact form rejected: invalid email address");
+        return error_fragment(
+            "That email address does not look right. Please check it and try again.",
+        );
+    };
+    let subject = format!("Contact from {}", data.email);
+    let emails = [
+        QueuedEmail::new(&s.config.mail.from, &subject, data.to_string()).reply_to(&sender),
+        QueuedEmail::new(&sender, &subject, data.to_string()),
+    ];
+    for email in emails {
+        if let Err(e) = outbox::enqueue(&s, email).await {
+            tracing::error!(error = %e, "could not queue contact email");
+            return error_fragment(&format!(
+                "Sorry, we could not send your message right now. Please try again later or call us at {PHONE}."
+            ));
+        }
     }
+    match s.repo::<ContactFormData>().insert(&data).await {
+        Ok(_) => tracing::info!("contact message saved"),
+        Err(e) => tracing::error!(error = %e, "could not save contact message"),
+    };
     html! {
             div {
                 h2 { "Thank you for contacting us, " (data.first_name) "!" }
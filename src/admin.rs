@@ -0,0 +1,221 @@
+//! Organizer pages. Everything under `/admin` requires logging in with the
+//! configured admin password; the session is a signed cookie.
+
+use axum::{
+    async_trait,
+    extract::{DefaultBodyLimit, FromRef, FromRequestParts, Path, State},
+    http::request::Parts,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Router,
+};
+use axum_extra::extract::{
+    cookie::{Cookie, Key, SameSite},
+    SignedCookieJar,
+};
+use maud::{html, Markup};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+use crate::{
+    consent, hours, outbox, page, photos, redirects, registration, schedule, seo::Meta, tickets,
+    volunteers, ClientState,
+};
+
+const SESSION_COOKIE: &str = "organizer";
+
+impl FromRef<ClientState> for Key {
+    fn from_ref(s: &ClientState) -> Key {
+        s.cookie_key.clone()
+    }
+}
+
+/// Extractor that only succeeds for logged-in organizers; everyone else is
+/// sent to the login page.
+pub struct Organizer;
+
+#[async_trait]
+impl FromRequestParts<ClientState> for Organizer {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, s: &ClientState) -> Result<Self, Response> {
+        let jar = SignedCookieJar::<Key>::from_request_parts(parts, s)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        if is_organizer(&jar, s) {
+            Ok(Organizer)
+        } else {
+            Err(Redirect::to("/admin/login").into_response())
+        }
+    }
+}
+
+/// Whether the request carries a valid organizer session.
+pub fn is_organizer(jar: &SignedCookieJar, s: &ClientState) -> bool {
+    s.config.admin_password.is_some() && jar.get(SESSION_COOKIE).is_some()
+}
+
+pub fn admin_router() -> Router<ClientState> {
+    Router::new()
+        .route("/login", get(login_page).post(login))
+        .route("/logout", post(logout))
+        .route("/outbox", get(outbox_page))
+        .route("/outbox/:id/resend", post(resend))
+        .route("/registrations", get(registration::overview))
+        .route("/registrations/:slug", get(registration::list))
+        .route(
+            "/checkin/:slug",
+            get(tickets::checkin_page).post(tickets::checkin),
+        )
+        .route("/checkin/:slug/counts", get(tickets::checkin_counts))
+        .route("/volunteers", get(volunteers::overview))
+        .route("/volunteers/:slug", get(volunteers::list))
+        .route("/hours", get(hours::review))
+        .route("/hours/:id/:action", post(hours::decide))
+        .route("/links", get(redirects::links_page))
+        .route(
+            "/photos",
+            get(photos::upload_page)
+                .post(photos::upload)
+                .layer(DefaultBodyLimit::max(photos::MAX_UPLOAD_BYTES)),
+        )
+        .route("/photos/:year/:slug", get(consent::tagging_page))
+        .route("/photos/:year/:slug/tag", post(consent::tag))
+        .route("/photos/:year/:slug/untag", post(consent::untag))
+        .route("/schedule", get(schedule::overview))
+        .route(
+            "/schedule/:slug",
+            get(schedule::editor_page).post(schedule::add),
+        )
+        .route("/schedule/:slug/clear", post(schedule::clear))
+        .route("/schedule/:slug/:id", post(schedule::save))
+        .route("/schedule/:slug/:id/delete", post(schedule::delete))
+        .route("/schedule/:slug/:id/now", post(schedule::now_performing))
+}
+
+/// Compares without short-circuiting so the response time does not reveal
+/// how much of the password was right.
+fn same_secret(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+fn login_form(error: Option<&str>) -> Markup {
+    html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-md mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-6 text-center" { "Organizer Login" }
+                form method="post" action="/admin/login" class="bg-white p-8 rounded-lg shadow-lg space-y-6" {
+                    @if let Some(error) = error {
+                        (page::error_fragment(error))
+                    }
+                    div {
+                        label for="password" class="block text-sm font-medium text-gray-700" { "Password" }
+                        input type="password" id="password" name="password" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required autofocus {}
+                    }
+                    div class="text-center" {
+                        button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Log in" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn login_meta() -> Meta {
+    Meta::new("Organizer Login", "Sign in to the organizer pages.")
+}
+
+async fn login_page(State(s): State<ClientState>) -> (Meta, Markup) {
+    if s.config.admin_password.is_none() {
+        return (
+            login_meta(),
+            login_form(Some("Organizer pages are disabled on this server.")),
+        );
+    }
+    (login_meta(), login_form(None))
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    password: String,
+}
+
+async fn login(
+    State(s): State<ClientState>,
+    jar: SignedCookieJar,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    match &s.config.admin_password {
+        Some(password) if same_secret(password, &form.password) => {
+            let cookie = Cookie::build((SESSION_COOKIE, "1"))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax);
+            (jar.add(cookie), Redirect::to("/admin/outbox")).into_response()
+        }
+        _ => (login_meta(), login_form(Some("Wrong password."))).into_response(),
+    }
+}
+
+async fn logout(jar: SignedCookieJar) -> impl IntoResponse {
+    (
+        jar.remove(Cookie::build(SESSION_COOKIE).path("/")),
+        Redirect::to("/"),
+    )
+}
+
+async fn outbox_page(_: Organizer, State(s): State<ClientState>) -> (Meta, Markup) {
+    let content = match outbox::failed(&s).await {
+        Ok(emails) => html! {
+            @if emails.is_empty() {
+                p class="text-center" { "No failed emails." }
+            }
+            @for email in &emails {
+                div id=(format!("email-{}", email.id)) class="bg-white p-6 rounded-lg shadow-lg mb-4" {
+                    p class="mb-2" { strong { "To: " } (email.to) }
+                    p class="mb-2" { strong { "Subject: " } (email.subject) }
+                    p class="mb-2" { strong { "Queued: " } (email.created_at.try_to_rfc3339_string().unwrap_or_default()) }
+                    p class="mb-2" { strong { "Attempts: " } (email.attempts) }
+                    @if let Some(error) = &email.last_error {
+                        p class="mb-2 text-red-700" { (error) }
+                    }
+                    button class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600"
+                        hx-post=(format!("/admin/outbox/{}/resend", email.id))
+                        hx-target=(format!("#email-{}", email.id))
+                        hx-swap="outerHTML" { "Resend" }
+                }
+            }
+        },
+        Err(e) => {
+            tracing::error!(error = %e, "could not load outbox");
+            page::error_fragment("Could not load the outbox.")
+        }
+    };
+    let meta = Meta::new("Failed Emails", "Emails that could not be delivered.");
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-3xl mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-6 text-center" { "Failed Emails" }
+                (content)
+            }
+        }
+    };
+    (meta, content)
+}
+
+async fn resend(_: Organizer, State(s): State<ClientState>, Path(id): Path<ObjectId>) -> Markup {
+    match outbox::resend(&s, id).await {
+        Ok(true) => html! {
+            div class="bg-white p-6 rounded-lg shadow-lg mb-4" { p { "Queued for delivery again." } }
+        },
+        Ok(false) => page::error_fragment("That email is no longer in the failed list."),
+        Err(e) => {
+            tracing::error!(email_id = %id, error = %e, "could not requeue email");
+            page::error_fragment("Could not requeue the email.")
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! Home-page announcements. They live in `announcements.toml` in the
+//! content directory, each with the moment it goes live and the moment it
+//! comes down, so the hero text and the notification banner change on
+//! schedule without anyone editing code.
+
+use axum::extract::{Query, State};
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use maud::{html, Markup};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    content::{Content, ContentFile},
+    events, ClientState,
+};
+
+pub const FILE_NAME: &str = "announcements.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Announcement {
+    pub title: String,
+    /// A second line, such as the date of the event.
+    pub subtitle: Option<String>,
+    /// A path on this site or a full address.
+    pub link: Option<String>,
+    pub link_text: Option<String>,
+    #[serde(deserialize_with = "local_time")]
+    pub publish: NaiveDateTime,
+    #[serde(deserialize_with = "local_time")]
+    pub unpublish: NaiveDateTime,
+    /// Shown over the picture at the top of the home page instead of in
+    /// the banner below it.
+    #[serde(default)]
+    pub hero: bool,
+}
+
+/// Reads "YYYY-MM-DD HH:MM", or "YYYY-MM-DD" for midnight at the start of
+/// that day, in New Jersey time.
+fn local_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+                .map(|date| date.and_time(Default::default()))
+        })
+        .map_err(|_| {
+            serde::de::Error::custom(format!(
+                "{text:?} is not a time like \"2025-03-29 15:00\" or a date like \"2025-03-29\""
+            ))
+        })
+}
+
+impl Announcement {
+    pub fn is_live(&self, now: NaiveDateTime) -> bool {
+        self.publish <= now && now < self.unpublish
+    }
+
+    /// The link, followed through htmx when it stays on this site.
+    fn link_markup(&self, class: &str) -> Markup {
+        let Some(link) = &self.link else {
+            return html! {};
+        };
+        let text = self.link_text.as_deref().unwrap_or("Learn more");
+        html! {
+            @if link.starts_with('/') {
+                a href=(link) hx-get=(link) hx-target="#page" hx-push-url="true" class=(class) { (text) }
+            } @else {
+                a href=(link) class=(class) { (text) }
+            }
+        }
+    }
+}
+
+/// Layout of `announcements.toml`: a list of `[[announcement]]` tables.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Announcements {
+    #[serde(rename = "announcement", default)]
+    pub announcements: Vec<Announcement>,
+}
+
+impl Content for Announcements {
+    fn validate(&self) -> Result<(), String> {
+        for announcement in &self.announcements {
+            let title = &announcement.title;
+            if announcement.unpublish <= announcement.publish {
+                return Err(format!(
+                    "announcement {title:?} comes down before it goes live"
+                ));
+            }
+            if announcement.link_text.is_some() && announcement.link.is_none() {
+                return Err(format!("announcement {title:?} has link_text but no link"));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub type AnnouncementsFile = ContentFile<Announcements>;
+
+impl Announcements {
+    /// Announcements live at `now`, in the order of the file.
+    pub fn live(&self, now: NaiveDateTime) -> impl Iterator<Item = &Announcement> {
+        self.announcements
+            .iter()
+            .filter(move |announcement| announcement.is_live(now))
+    }
+
+    /// The hero announcement: the live one that went up last.
+    pub fn hero(&self, now: NaiveDateTime) -> Option<&Announcement> {
+        self.live(now)
+            .filter(|announcement| announcement.hero)
+            .max_by_key(|announcement| announcement.publish)
+    }
+
+    /// Live announcements for the banner.
+    pub fn banner(&self, now: NaiveDateTime) -> Vec<&Announcement> {
+        self.live(now)
+            .filter(|announcement| !announcement.hero)
+            .collect()
+    }
+}
+
+/// The current time in New Jersey.
+pub fn now() -> NaiveDateTime {
+    Utc::now().with_timezone(&events::TIME_ZONE).naive_local()
+}
+
+/// Text over the picture at the top of the home page.
+pub fn hero_markup(announcement: &Announcement) -> Markup {
+    html! {
+        p class="text-lg md:text-2xl lg:text-3xl xl:text-4xl 2xl:text-5xl font-bold animate-blink-color" {
+            (announcement.title)
+        }
+        @if let Some(subtitle) = &announcement.subtitle {
+            p class="text-lg md:text-2xl lg:text-3xl xl:text-4xl 2xl:text-5xl font-bold animate-blink-color" {
+                (subtitle)
+            }
+        }
+        p class="text-sm md:text-base lg:text-lg xl:text-xl 2xl:text-2xl font-semibold cursor-pointer" {
+            (announcement.link_markup("text-blue-500 underline"))
+        }
+    }
+}
+
+/// Seconds each banner announcement stays up.
+const ROTATE_SECS: u32 = 5;
+
+/// One banner line.
+fn banner_item(announcement: &Announcement) -> Markup {
+    html! {
+        div class="font-roboto sm:text-xl md:text-2xl lg:text-3xl" {
+            (announcement.title)
+            @if let Some(subtitle) = &announcement.subtitle {
+                ": " (subtitle)
+            }
+            @if announcement.link.is_some() {
+                " "
+                (announcement.link_markup("text-blue-600 underline"))
+            }
+        }
+    }
+}
+
+/// The banner showing the `i`th live announcement. With more than one live,
+/// htmx swaps in the next after a few seconds; without JavaScript the first
+/// simply stays up. Empty when nothing is live, which also ends the rotation.
+pub fn banner_markup(banner: &[&Announcement], i: usize) -> Markup {
+    let Some(announcement) = banner.get(i % banner.len().max(1)) else {
+        return html! {};
+    };
+    let class = "text-center px-4 py-6";
+    if banner.len() == 1 {
+        return html! {
+            div id="notificationBanner" class=(class) { (banner_item(announcement)) }
+        };
+    }
+    let next = format!("/announcements/next?i={}", (i + 1) % banner.len());
+    html! {
+        div id="notificationBanner" class=(class)
+            hx-get=(next) hx-trigger={ "every " (ROTATE_SECS) "s" } hx-swap="outerHTML swap:500ms" {
+            (banner_item(announcement))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NextQuery {
+    #[serde(default)]
+    i: usize,
+}
+
+pub async fn next(State(s): State<ClientState>, Query(query): Query<NextQuery>) -> Markup {
+    let announcements = s.announcements.get();
+    banner_markup(&announcements.banner(now()), query.i)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const ANNOUNCEMENTS: &str = r#"
+[[announcement]]
+title = "Tamil New Year"
+publish = "2025-03-01"
+unpublish = "2025-04-13 18:00"
+hero = true
+
+[[announcement]]
+title = "Volunteers needed"
+publish = "2025-04-01 09:00"
+unpublish = "2025-04-13"
+hero = true
+
+[[announcement]]
+title = "Tamil school registration is open"
+publish = "2025-03-15"
+unpublish = "2025-05-01"
+"#;
+
+    fn announcements() -> Announcements {
+        let announcements: Announcements = toml::from_str(ANNOUNCEMENTS).unwrap();
+        announcements.validate().unwrap();
+        announcements
+    }
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn titles<'a>(live: impl IntoIterator<Item = &'a Announcement>) -> Vec<&'a str> {
+        live.into_iter()
+            .map(|announcement| announcement.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn an_announcement_is_live_from_publish_until_unpublish() {
+        let announcements = announcements();
+        let new_year = &announcements.announcements[0];
+        assert!(!new_year.is_live(time("2025-02-28 23:59")));
+        assert!(new_year.is_live(time("2025-03-01 00:00")));
+        assert!(new_year.is_live(time("2025-04-13 17:59")));
+        assert!(!new_year.is_live(time("2025-04-13 18:00")));
+    }
+
+    #[test]
+    fn the_latest_live_hero_wins_and_the_rest_go_in_the_banner() {
+        let announcements = announcements();
+        let hero = |now| announcements.hero(time(now)).map(|a| a.title.as_str());
+        assert_eq!(hero("2025-02-01 12:00"), None);
+        assert_eq!(hero("2025-03-20 12:00"), Some("Tamil New Year"));
+        assert_eq!(hero("2025-04-05 12:00"), Some("Volunteers needed"));
+        assert_eq!(hero("2025-04-13 12:00"), Some("Tamil New Year"));
+        assert_eq!(hero("2025-04-14 12:00"), None);
+        assert_eq!(
+            titles(announcements.banner(time("2025-04-14 12:00"))),
+            ["Tamil school registration is open"]
+        );
+    }
+
+    #[test]
+    fn times_are_read_as_new_jersey_time() {
+        let announcements = announcements();
+        // 5:30 PM in New Jersey, when the hero is still up, is already
+        // 9:30 PM in UTC.
+        let evening = Utc.with_ymd_and_hms(2025, 4, 13, 21, 30, 0).unwrap();
+        let now = evening.with_timezone(&events::TIME_ZONE).naive_local();
+        assert_eq!(now, time("2025-04-13 17:30"));
+        assert_eq!(
+            announcements.hero(now).map(|a| a.title.as_str()),
+            Some("Tamil New Year")
+        );
+    }
+
+    #[test]
+    fn announcements_have_to_come_down_after_they_go_live() {
+        let toml = "[[announcement]]\ntitle = \"A\"\npublish = \"2025-04-13\"\nunpublish = \"2025-04-13\"\n";
+        let announcements: Announcements = toml::from_str(toml).unwrap();
+        assert!(announcements
+            .validate()
+            .unwrap_err()
+            .contains("comes down before it goes live"));
+    }
+}
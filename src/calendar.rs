@@ -0,0 +1,395 @@
+//! iCalendar (RFC 5545) feeds: `/events.ics` with every event, one `.ics`
+//! per event and a recurring feed per meetup under `/calendar`. They are
+//! built from the same content files as the pages, so a calendar never
+//! disagrees with the website.
+
+use axum::{
+    extract::{Path, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{Days, NaiveDate, NaiveTime, Utc, Weekday};
+
+use crate::{events::Event, meetups::Meetup, ClientState};
+
+const PRODUCT_ID: &str = "-//NJ Thiruvalluvar Tamil Sangam//Website//EN";
+/// All times are New Jersey local time, the site's
+/// [`TIME_ZONE`](crate::events::TIME_ZONE).
+const TZID: &str = "America/New_York";
+const VTIMEZONE: &[&str] = &[
+    "BEGIN:VTIMEZONE",
+    "TZID:America/New_York",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:-0500",
+    "TZOFFSETTO:-0400",
+    "TZNAME:EDT",
+    "DTSTART:19700308T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:-0400",
+    "TZOFFSETTO:-0500",
+    "TZNAME:EST",
+    "DTSTART:19701101T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+pub fn calendar_router() -> Router<ClientState> {
+    Router::new()
+        .route("/events.ics", get(events_feed))
+        .route("/calendar/:file", get(meetup_feed))
+}
+
+/// Escapes a TEXT value.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn local(date: NaiveDate, time: NaiveTime) -> String {
+    date.and_time(time).format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn day(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+struct Calendar {
+    text: String,
+    /// Host part of event UIDs, e.g. `njtts.org`.
+    domain: String,
+    site_url: String,
+    stamp: String,
+}
+
+impl Calendar {
+    fn new(site_url: &str, name: &str) -> Calendar {
+        let mut calendar = Calendar {
+            text: String::new(),
+            domain: site_url
+                .split_once("://")
+                .map_or(site_url, |(_, host)| host)
+                .to_string(),
+            site_url: site_url.to_string(),
+            stamp: Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+        };
+        calendar.line("BEGIN:VCALENDAR");
+        calendar.line("VERSION:2.0");
+        calendar.line(&format!("PRODID:{PRODUCT_ID}"));
+        calendar.line("CALSCALE:GREGORIAN");
+        calendar.line("METHOD:PUBLISH");
+        calendar.line(&format!("X-WR-CALNAME:{}", escape(name)));
+        calendar.line(&format!("X-WR-TIMEZONE:{TZID}"));
+        for line in VTIMEZONE {
+            calendar.line(line);
+        }
+        calendar
+    }
+
+    /// Appends one content line, folded at 75 octets.
+    fn line(&mut self, line: &str) {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > 75 {
+                self.text.push_str("\r\n ");
+                width = 1;
+            }
+            self.text.push(c);
+            width += c.len_utf8();
+        }
+        self.text.push_str("\r\n");
+    }
+
+    /// DTSTART and DTEND of something running from `start` to `end`, all day
+    /// when no start time is known.
+    fn span(
+        &mut self,
+        start: NaiveDate,
+        end: NaiveDate,
+        start_time: Option<NaiveTime>,
+        end_time: Option<NaiveTime>,
+    ) {
+        match start_time {
+            Some(start_time) => {
+                self.line(&format!("DTSTART;TZID={TZID}:{}", local(start, start_time)));
+                if let Some(end_time) = end_time {
+                    self.line(&format!("DTEND;TZID={TZID}:{}", local(end, end_time)));
+                }
+            }
+            None => {
+                self.line(&format!("DTSTART;VALUE=DATE:{}", day(start)));
+                // The end date of an all-day event is exclusive.
+                let after = end.checked_add_days(Days::new(1)).unwrap_or(end);
+                self.line(&format!("DTEND;VALUE=DATE:{}", day(after)));
+            }
+        }
+    }
+
+    fn details(&mut self, url: &str, location: Option<&str>, description: &str) {
+        if let Some(location) = location {
+            self.line(&format!("LOCATION:{}", escape(location)));
+        }
+        let url = format!("{}{url}", self.site_url);
+        let description = if description.is_empty() {
+            url.clone()
+        } else {
+            format!("{description}\n\n{url}")
+        };
+        self.line(&format!("DESCRIPTION:{}", escape(&description)));
+        self.line(&format!("URL:{url}"));
+    }
+
+    fn event(&mut self, event: &Event) {
+        self.line("BEGIN:VEVENT");
+        self.line(&format!("UID:event-{}@{}", event.slug, self.domain));
+        self.line(&format!("DTSTAMP:{}", self.stamp));
+        self.line(&format!("SUMMARY:{}", escape(&event.title)));
+        self.span(
+            event.date,
+            event.last_day(),
+            event.start_time,
+            event.end_time,
+        );
+        self.details(
+            &event.url(),
+            event.location().as_deref(),
+            &event.description,
+        );
+        self.line("END:VEVENT");
+    }
+
+    fn meetup(&mut self, meetup: &Meetup) {
+        let first = meetup.first_session();
+        self.line("BEGIN:VEVENT");
+        self.line(&format!("UID:meetup-{}@{}", meetup.slug, self.domain));
+        self.line(&format!("DTSTAMP:{}", self.stamp));
+        self.line(&format!("SUMMARY:{}", escape(&meetup.title)));
+        self.span(first, first, meetup.start_time, meetup.end_time);
+        let by_day = match meetup.weekday {
+            Weekday::Mon => "MO",
+            Weekday::Tue => "TU",
+            Weekday::Wed => "WE",
+            Weekday::Thu => "TH",
+            Weekday::Fri => "FR",
+            Weekday::Sat => "SA",
+            Weekday::Sun => "SU",
+        };
+        let until = meetup.until.map(|until| match meetup.start_time {
+            // UNTIL has to be in UTC when DTSTART has a time zone. The end
+            // of the next day in UTC is still before the following session.
+            Some(_) => format!(
+                ";UNTIL={}T235959Z",
+                day(until.checked_add_days(Days::new(1)).unwrap_or(until))
+            ),
+            None => format!(";UNTIL={}", day(until)),
+        });
+        self.line(&format!(
+            "RRULE:FREQ=WEEKLY;BYDAY={by_day}{}",
+            until.unwrap_or_default()
+        ));
+        self.details(
+            &meetup.page,
+            meetup.location.as_deref(),
+            &meetup.description,
+        );
+        self.line("END:VEVENT");
+    }
+
+    fn finish(mut self) -> String {
+        self.line("END:VCALENDAR");
+        self.text
+    }
+}
+
+/// A calendar response. With `download` set browsers save it as
+/// `file_name` instead of handing it to a calendar app to subscribe to.
+fn ics(calendar: Calendar, file_name: &str, download: bool) -> Response {
+    let disposition = if download { "attachment" } else { "inline" };
+    (
+        [
+            (CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("{disposition}; filename=\"{file_name}\""),
+            ),
+        ],
+        calendar.finish(),
+    )
+        .into_response()
+}
+
+async fn events_feed(State(s): State<ClientState>) -> Response {
+    let events = s.events.get();
+    let mut calendar = Calendar::new(&s.config.site_url, "NJ Tamil Sangam Events");
+    for event in &events.events {
+        calendar.event(event);
+    }
+    ics(calendar, "events.ics", false)
+}
+
+/// A single event, for `/events/{slug}.ics`.
+pub fn event_ics(s: &ClientState, event: &Event) -> Response {
+    let mut calendar = Calendar::new(&s.config.site_url, &event.title);
+    calendar.event(event);
+    ics(calendar, &format!("{}.ics", event.slug), true)
+}
+
+async fn meetup_feed(State(s): State<ClientState>, Path(file): Path<String>) -> Response {
+    let meetups = s.meetups.get();
+    let Some(meetup) = file
+        .strip_suffix(".ics")
+        .and_then(|slug| meetups.find(slug))
+    else {
+        return crate::not_found().await.into_response();
+    };
+    let mut calendar = Calendar::new(&s.config.site_url, &meetup.title);
+    calendar.meetup(meetup);
+    ics(calendar, &file, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::{
+        events::{Events, TIME_ZONE},
+        meetups::{self, MeetupsFile},
+        tests,
+    };
+
+    /// The content lines of `text`, unfolded.
+    fn unfold(text: &str) -> Vec<String> {
+        text.replace("\r\n ", "")
+            .split("\r\n")
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        let mut calendar = Calendar::new("https://njtts.org", "Test");
+        let long = format!("DESCRIPTION:{}", "தமிழ் சங்கம் ".repeat(20));
+        calendar.line(&long);
+        let text = calendar.finish();
+
+        assert!(text.ends_with("END:VCALENDAR\r\n"));
+        assert!(!text.replace("\r\n", "").contains(['\r', '\n']));
+        let physical: Vec<&str> = text.trim_end_matches("\r\n").split("\r\n").collect();
+        assert!(
+            physical.iter().all(|line| line.len() <= 75),
+            "{physical:#?}"
+        );
+        assert!(physical.iter().filter(|line| line.starts_with(' ')).count() > 1);
+        assert!(unfold(&text).contains(&long));
+    }
+
+    #[test]
+    fn text_values_are_escaped() {
+        assert_eq!(
+            escape("Music, dance; art\\craft\nand more"),
+            "Music\\, dance\\; art\\\\craft\\nand more"
+        );
+
+        let events: Events = toml::from_str(
+            r#"
+            [[event]]
+            title = "Songs, Dance; and\\More"
+            slug = "show"
+            date = "2099-04-14"
+            description = "Doors open at 5.\nBring a friend, or two."
+            "#,
+        )
+        .unwrap();
+        let mut calendar = Calendar::new("https://njtts.org", "Test");
+        calendar.event(&events.events[0]);
+        let lines = unfold(&calendar.finish());
+        assert!(lines.contains(&"SUMMARY:Songs\\, Dance\\; and\\\\More".to_string()));
+        assert!(lines.contains(
+            &"DESCRIPTION:Doors open at 5.\\nBring a friend\\, or two.\\n\\nhttps://njtts.org/events/show"
+                .to_string()
+        ));
+    }
+
+    /// Meetups on the reading circle's page and a club's page.
+    const MEETUPS: &str = r#"
+[[meetup]]
+title = "Reading Circle"
+slug = "reading-circle"
+page = "/vattam"
+weekday = "Wednesday"
+since = "2024-01-21"
+location = "Online"
+
+[[meetup]]
+title = "Test Walk"
+slug = "test-walk"
+page = "/walking_club"
+weekday = "Sunday"
+since = "2025-01-01"
+start_time = "08:00:00"
+end_time = "09:00:00"
+"#;
+
+    fn with_meetups(dir: &std::path::Path) -> ClientState {
+        let path = dir.join(meetups::FILE_NAME);
+        std::fs::write(&path, MEETUPS).unwrap();
+        ClientState {
+            meetups: Arc::new(MeetupsFile::load(path).expect("test meetups are valid")),
+            ..tests::test_state()
+        }
+    }
+
+    #[tokio::test]
+    async fn every_meetup_has_a_weekly_feed() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = with_meetups(dir.path());
+        for (page, title) in [
+            ("/vattam", "Reading Circle"),
+            ("/walking_club", "Test Walk"),
+        ] {
+            let meetups = s.meetups.get();
+            let meetup = meetups
+                .on_page(page)
+                .next()
+                .unwrap_or_else(|| panic!("{page} has no meetup"));
+            let response = tests::get(&s, &meetup.calendar_url(), None).await;
+            assert_eq!(response.status(), StatusCode::OK, "{page}");
+            let lines = unfold(&tests::text(response).await);
+            assert!(lines.contains(&format!("SUMMARY:{title}")), "{lines:#?}");
+            assert!(lines
+                .iter()
+                .any(|line| line.starts_with("RRULE:FREQ=WEEKLY;")));
+        }
+        let walk = tests::text(tests::get(&s, "/calendar/test-walk.ics", None).await).await;
+        assert!(
+            unfold(&walk).contains(&"DTSTART;TZID=America/New_York:20250105T080000".to_string())
+        );
+
+        let missing = tests::get(&s, "/calendar/no-such-meetup.ics", None).await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn the_shipped_meetups_all_have_feeds() {
+        let s = tests::test_state();
+        for meetup in &s.meetups.get().meetups {
+            let response = tests::get(&s, &meetup.calendar_url(), None).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", meetup.slug);
+        }
+    }
+
+    #[test]
+    fn feeds_use_the_site_time_zone() {
+        assert_eq!(TZID, TIME_ZONE.name());
+        assert!(VTIMEZONE.contains(&format!("TZID:{TZID}").as_str()));
+    }
+}
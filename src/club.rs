@@ -1,12 +1,21 @@
+use axum::extract::State;
 use maud::{html, Markup};
 
-pub async fn walking_page() -> Markup {
-    html! {
+use crate::{meetups, seo::Meta, ClientState};
+
+pub async fn walking_page(State(s): State<ClientState>) -> (Meta, Markup) {
+    let meetups = s.meetups.get();
+    let meta = Meta::new(
+        "Walking Club",
+        "Walk with friends from the Tamil community at the TTS Walking Club.",
+    );
+    let content = html! {
         section class="bg-white py-8 lg:py-16 px-4 mx-auto max-w-screen-md" {
             div class="text-center mb-8" {
-                img src="assets/img/codes/walking.jpeg" alt="QR Code" class="mx-auto mb-4" {}
+                img src="/assets/img/codes/walking.jpeg" alt="QR Code" class="mx-auto mb-4" {}
                 a href="your_whatsapp_link_here" target="_blank" rel="noopener noreferrer" class="text-green-500 hover:underline" { "Join Our Whatsapp Group!" }
             }
+            (meetups::schedule_markup(&meetups, "/walking_club"))
             h2 class="mb-4 text-center text-4xl tracking-tight font-extrabold text-gray-900" {"Step into Serenity: Introducing Our TTSWalking Club"}
             p class="mb-8 lg:mb-16 font-light text-center text-gray-500 sm:text-xl" {
                 "Nestled amidst the breathtaking landscapes of our community lies an invitation to embark on a journey unlike any other – welcome to our TTS Hiking Club! With a passion for adventure and a love for the great outdoors, our club is a vibrant community of individuals united by the desire to explore the wonders of nature, one trail at a time. Whether you're a seasoned hiker or just beginning to discover the joys of trekking, there's a place for you here."
@@ -21,19 +30,26 @@ pub async fn walking_page() -> Markup {
                 "Whether you seek adventure, camaraderie, or simply a chance to unplug and recharge, our Hiking Club is here to guide you on an unforgettable journey of discovery. Lace up your boots, pack your sense of adventure, and let's hit the trails together!"
             }
         }
-    }
+    };
+    (meta, content)
 }
 
-pub async fn hiking_page() -> Markup {
-    html! {
-        section class="bg-cover bg-center bg-no-repeat bg-fixed" style="background-image: url('assets/img/hiking.jpeg');" {
+pub async fn hiking_page(State(s): State<ClientState>) -> (Meta, Markup) {
+    let meetups = s.meetups.get();
+    let meta = Meta::new(
+        "Hiking Club",
+        "Explore New Jersey trails with the TTS Hiking Club.",
+    );
+    let content = html! {
+        section class="bg-cover bg-center bg-no-repeat bg-fixed" style="background-image: url('/assets/img/hiking.jpeg');" {
             div class="bg-white bg-opacity-90 py-8 lg:py-16 px-4 mx-auto max-w-screen-md relative z-5" {
                 h2 class="mb-4 text-center text-4xl tracking-tight font-extrabold text-gray-900" { "Explore, Connect, and Conquer Nature: Welcome to Our TTS Hiking Club" }
 
                 div class="text-center mb-8" {
-                    img src="assets/img/hiking_code.jpeg" alt="QR Code" class="mx-auto mb-4" {}
+                    img src="/assets/img/hiking_code.jpeg" alt="QR Code" class="mx-auto mb-4" {}
                     a href="https://chat.whatsapp.com/FjyUCpSVjIQDv04xSnBAZc" target="_blank" rel="noopener noreferrer" class="text-green-500 hover:underline" { "Join Our Whatsapp Group!" }
                 }
+                (meetups::schedule_markup(&meetups, "/hiking_club"))
                 p class="mb-8 lg:mb-16 font-light text-center sm:text-xl" {
                    "In the gentle rhythm of each step lies a pathway to serenity, and our TTS Walking Club is your guide to this tranquil journey. Nestled within the heart of our vibrant community, our Walking Club invites you to embrace the simple joys of walking and the profound connections it fosters – with nature, with others, and with oneself. Whether you're seeking a leisurely stroll or a brisk stride, our club offers a sanctuary for walkers of all paces and preferences."
                 }
@@ -48,16 +64,23 @@ pub async fn hiking_page() -> Markup {
                 }
             }
         }
-    }
+    };
+    (meta, content)
 }
 
-pub async fn running_page() -> Markup {
-    html! {
+pub async fn running_page(State(s): State<ClientState>) -> (Meta, Markup) {
+    let meetups = s.meetups.get();
+    let meta = Meta::new(
+        "Running Club",
+        "Train and race together with the TTS Running Club.",
+    );
+    let content = html! {
         section class="bg-white py-8 lg:py-16 px-4 mx-auto max-w-screen-md" {
             div class="text-center mb-8" {
-                img src="assets/img/codes/running.jpeg" alt="QR Code" class="mx-auto mb-4" {}
+                img src="/assets/img/codes/running.jpeg" alt="QR Code" class="mx-auto mb-4" {}
                 a href="your_whatsapp_link_here" target="_blank" rel="noopener noreferrer" class="text-green-500 hover:underline" { "Join Our Whatsapp Group!" }
             }
+            (meetups::schedule_markup(&meetups, "/running_club"))
             h2 class="mb-4 text-center text-4xl tracking-tight font-extrabold text-gray-900" { "Explore, Connect, and Conquer Nature: Welcome to Our TTS Hiking Club" }
             p class="mb-8 lg:mb-16 font-light text-center text-gray-500 sm:text-xl" {
                "In the gentle rhythm of each step lies a pathway to serenity, and our TTS Walking Club is your guide to this tranquil journey. Nestled within the heart of our vibrant community, our Walking Club invites you to embrace the simple joys of walking and the profound connections it fosters – with nature, with others, and with oneself. Whether you're seeking a leisurely stroll or a brisk stride, our club offers a sanctuary for walkers of all paces and preferences."
@@ -72,5 +95,6 @@ pub async fn running_page() -> Markup {
                 "So lace up your shoes, leave your worries behind, and let's wander together as we explore the beauty that surrounds us, one step at a time."
             }
         }
-    }
+    };
+    (meta, content)
 }
@@ -0,0 +1,674 @@
+use std::{fmt, net::SocketAddr, path::PathBuf, time::Duration};
+
+use clap::Parser;
+use lettre::message::Mailbox;
+use serde::Deserialize;
+
+/// Command line flags. Every flag can also be set through the environment
+/// variable named next to it, and falls back to the TOML file given with
+/// `--config`.
+#[derive(Parser, Debug)]
+#[command(name = "website", about = "NJ Thiruvalluvar Tamil Sangam website")]
+pub struct Args {
+    /// Path to a TOML configuration file
+    #[arg(long, short, env = "WEBSITE_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Address the HTTP server listens on
+    #[arg(long, env = "WEBSITE_BIND")]
+    pub bind: Option<String>,
+    /// Directory served under /assets
+    #[arg(long, env = "WEBSITE_STATIC_DIR")]
+    pub static_dir: Option<PathBuf>,
+    /// Directory holding the editable site content, such as events.toml [default: content]
+    #[arg(long, env = "WEBSITE_CONTENT_DIR")]
+    pub content_dir: Option<PathBuf>,
+    /// Directory for generated files such as resized photos [default: cache]
+    #[arg(long, env = "WEBSITE_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Where records are kept: "mongodb" or "memory" [default: mongodb]
+    #[arg(long, env = "WEBSITE_STORAGE")]
+    pub storage: Option<String>,
+    /// MongoDB connection string
+    #[arg(long, env = "MONGODB_URI", hide_env_values = true)]
+    pub mongodb_uri: Option<String>,
+    /// MongoDB database name [default: tts]
+    #[arg(long, env = "MONGODB_DATABASE")]
+    pub mongodb_database: Option<String>,
+    /// File that holds submissions while MongoDB is unreachable [default: journal.jsonl]
+    #[arg(long, env = "MONGODB_JOURNAL")]
+    pub mongodb_journal: Option<PathBuf>,
+    /// How email is delivered: "smtp", "file" or "memory" [default: smtp]
+    #[arg(long, env = "MAIL_TRANSPORT")]
+    pub mail_transport: Option<String>,
+    /// Sender address [default: the SMTP username]
+    #[arg(long, env = "MAIL_FROM")]
+    pub mail_from: Option<String>,
+    /// Directory the "file" transport writes .eml files into [default: mail]
+    #[arg(long, env = "MAIL_DIR")]
+    pub mail_dir: Option<PathBuf>,
+    /// SMTP relay host
+    #[arg(long, env = "SMTP_SERVER")]
+    pub smtp_server: Option<String>,
+    /// SMTP login, also used as the sender address
+    #[arg(long, env = "SMTP_USERNAME")]
+    pub smtp_username: Option<String>,
+    /// SMTP password
+    #[arg(long, env = "SMTP_PASSWORD", hide_env_values = true)]
+    pub smtp_password: Option<String>,
+    /// Delivery attempts before a queued email is marked failed [default: 6]
+    #[arg(long, env = "OUTBOX_MAX_ATTEMPTS")]
+    pub outbox_max_attempts: Option<u32>,
+    /// Seconds before the first retry; doubles after every attempt [default: 30]
+    #[arg(long, env = "OUTBOX_RETRY_SECS")]
+    pub outbox_retry_secs: Option<u64>,
+    /// Password for the organizer pages under /admin; unset disables them
+    #[arg(long, env = "ADMIN_PASSWORD", hide_env_values = true)]
+    pub admin_password: Option<String>,
+    /// At least 32 bytes used to sign cookies and tickets; random per run when unset
+    #[arg(long, env = "WEBSITE_SECRET_KEY", hide_env_values = true)]
+    pub secret_key: Option<String>,
+    /// Public address of the site, used in canonical links and the sitemap [default: https://njtts.org]
+    #[arg(long, env = "WEBSITE_URL")]
+    pub site_url: Option<String>,
+    /// Whether robots.txt lets search engines index the site [default: true]
+    #[arg(long, env = "ROBOTS_ALLOW_INDEXING")]
+    pub robots_allow_indexing: Option<bool>,
+    /// Comma-separated paths robots.txt asks crawlers to skip [default: /admin/]
+    #[arg(long, env = "ROBOTS_DISALLOW", value_delimiter = ',')]
+    pub robots_disallow: Option<Vec<String>>,
+    /// Log output: "pretty" or "json" [default: pretty]
+    #[arg(long, env = "WEBSITE_LOG_FORMAT")]
+    pub log_format: Option<String>,
+    /// Which logs to keep, in tracing's filter syntax [default: info]
+    #[arg(long, env = "RUST_LOG")]
+    pub log_filter: Option<String>,
+}
+
+/// Layout of the optional TOML configuration file.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    bind: Option<String>,
+    static_dir: Option<PathBuf>,
+    content_dir: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    storage: Option<String>,
+    #[serde(default)]
+    mongodb: FileMongoConfig,
+    #[serde(default)]
+    mail: FileMailConfig,
+    #[serde(default)]
+    smtp: FileSmtpConfig,
+    #[serde(default)]
+    outbox: FileOutboxConfig,
+    admin_password: Option<String>,
+    secret_key: Option<String>,
+    site_url: Option<String>,
+    #[serde(default)]
+    robots: FileRobotsConfig,
+    #[serde(default)]
+    log: FileLogConfig,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileMongoConfig {
+    uri: Option<String>,
+    database: Option<String>,
+    journal: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileMailConfig {
+    transport: Option<String>,
+    from: Option<String>,
+    dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileSmtpConfig {
+    server: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileOutboxConfig {
+    max_attempts: Option<u32>,
+    retry_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileRobotsConfig {
+    allow_indexing: Option<bool>,
+    disallow: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileLogConfig {
+    format: Option<String>,
+    filter: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind: SocketAddr,
+    pub static_dir: PathBuf,
+    /// Files organizers edit to change the site without a redeploy.
+    pub content_dir: PathBuf,
+    /// Files the site generates for itself and can make again, so the
+    /// directory may be emptied at any time.
+    pub cache_dir: PathBuf,
+    pub storage: StorageConfig,
+    pub mail: MailConfig,
+    pub outbox: OutboxConfig,
+    pub admin_password: Option<String>,
+    pub secret_key: Option<String>,
+    /// Scheme and host without a trailing slash, e.g. `https://njtts.org`.
+    pub site_url: String,
+    pub robots: RobotsConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Clone)]
+pub enum StorageConfig {
+    Mongo(MongoConfig),
+    /// Keep everything in process memory; nothing survives a restart.
+    Memory,
+}
+
+#[derive(Debug, Clone)]
+pub struct MongoConfig {
+    pub uri: String,
+    pub database: String,
+    /// Append-only file inserts are written to while the database is down.
+    pub journal: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    /// Sender of every email; the organisation's own inbox.
+    pub from: Mailbox,
+    pub transport: MailTransport,
+}
+
+#[derive(Debug, Clone)]
+pub enum MailTransport {
+    Smtp(SmtpConfig),
+    /// Write each message as an .eml file into this directory.
+    File(PathBuf),
+    /// Keep messages in process memory.
+    Memory,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub server: String,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutboxConfig {
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled after each failed attempt.
+    pub retry_base: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct RobotsConfig {
+    /// When false every crawler is turned away, e.g. on a staging server.
+    pub allow_indexing: bool,
+    pub disallow: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Filter directives such as `info,website=debug`.
+    pub filter: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable, one event per line.
+    Pretty,
+    /// One JSON object per event, for log collectors.
+    Json,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "could not read {}: {e}", path.display()),
+            ConfigError::Parse(path, e) => write!(f, "could not parse {}: {e}", path.display()),
+            ConfigError::Invalid(problems) => {
+                writeln!(f, "invalid configuration:")?;
+                for problem in problems {
+                    writeln!(f, "  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Returns the value of a setting that has no default, recording a problem
+/// when it is missing.
+fn required(
+    problems: &mut Vec<String>,
+    value: Option<String>,
+    key: &str,
+    flag: &str,
+    env: &str,
+) -> String {
+    value.filter(|v| !v.is_empty()).unwrap_or_else(|| {
+        problems.push(format!(
+            "{key} is not set (use --{flag}, {env} or `{key}` in the config file)"
+        ));
+        String::new()
+    })
+}
+
+impl Config {
+    /// Loads the configuration from the command line, the environment and
+    /// the optional TOML file, in that order of precedence.
+    pub fn load() -> Result<Config, ConfigError> {
+        Config::from_args(Args::parse())
+    }
+
+    pub fn from_args(args: Args) -> Result<Config, ConfigError> {
+        let file = match &args.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| ConfigError::Read(path.clone(), e))?;
+                toml::from_str(&text).map_err(|e| ConfigError::Parse(path.clone(), e))?
+            }
+            None => FileConfig::default(),
+        };
+
+        let mut problems = Vec::new();
+
+        let storage = match args.storage.or(file.storage).as_deref() {
+            None | Some("mongodb") => StorageConfig::Mongo(MongoConfig {
+                uri: required(
+                    &mut problems,
+                    args.mongodb_uri.or(file.mongodb.uri),
+                    "mongodb.uri",
+                    "mongodb-uri",
+                    "MONGODB_URI",
+                ),
+                database: args
+                    .mongodb_database
+                    .or(file.mongodb.database)
+                    .unwrap_or_else(|| "tts".to_string()),
+                journal: args
+                    .mongodb_journal
+                    .or(file.mongodb.journal)
+                    .unwrap_or_else(|| PathBuf::from("journal.jsonl")),
+            }),
+            Some("memory") => StorageConfig::Memory,
+            Some(other) => {
+                problems.push(format!(
+                    "storage {other:?} is unknown (expected \"mongodb\" or \"memory\")"
+                ));
+                StorageConfig::Memory
+            }
+        };
+        let smtp_username = args.smtp_username.or(file.smtp.username);
+        let transport = match args.mail_transport.or(file.mail.transport).as_deref() {
+            None | Some("smtp") => MailTransport::Smtp(SmtpConfig {
+                server: required(
+                    &mut problems,
+                    args.smtp_server.or(file.smtp.server),
+                    "smtp.server",
+                    "smtp-server",
+                    "SMTP_SERVER",
+                ),
+                username: required(
+                    &mut problems,
+                    smtp_username.clone(),
+                    "smtp.username",
+                    "smtp-username",
+                    "SMTP_USERNAME",
+                ),
+                password: required(
+                    &mut problems,
+                    args.smtp_password.or(file.smtp.password),
+                    "smtp.password",
+                    "smtp-password",
+                    "SMTP_PASSWORD",
+                ),
+            }),
+            Some("file") => MailTransport::File(
+                args.mail_dir
+                    .or(file.mail.dir)
+                    .unwrap_or_else(|| PathBuf::from("mail")),
+            ),
+            Some("memory") => MailTransport::Memory,
+            Some(other) => {
+                problems.push(format!(
+                    "mail.transport {other:?} is unknown (expected \"smtp\", \"file\" or \"memory\")"
+                ));
+                MailTransport::Memory
+            }
+        };
+        let from = required(
+            &mut problems,
+            args.mail_from.or(file.mail.from).or(smtp_username),
+            "mail.from",
+            "mail-from",
+            "MAIL_FROM",
+        );
+        let from = match from.parse::<Mailbox>() {
+            Ok(mailbox) => Some(mailbox),
+            Err(e) if !from.is_empty() => {
+                problems.push(format!("mail.from {from:?} is not an email address: {e}"));
+                None
+            }
+            Err(_) => None,
+        };
+
+        let bind = args
+            .bind
+            .or(file.bind)
+            .unwrap_or_else(|| "0.0.0.0:3300".to_string());
+        let bind = match bind.parse() {
+            Ok(addr) => Some(addr),
+            Err(e) => {
+                problems.push(format!("bind address {bind:?} is invalid: {e}"));
+                None
+            }
+        };
+
+        let static_dir = args
+            .static_dir
+            .or(file.static_dir)
+            .unwrap_or_else(|| PathBuf::from("src/static"));
+        if !static_dir.is_dir() {
+            problems.push(format!(
+                "static_dir {} is not a directory",
+                static_dir.display()
+            ));
+        }
+
+        let content_dir = args
+            .content_dir
+            .or(file.content_dir)
+            .unwrap_or_else(|| PathBuf::from("content"));
+        if !content_dir.is_dir() {
+            problems.push(format!(
+                "content_dir {} is not a directory",
+                content_dir.display()
+            ));
+        }
+
+        let cache_dir = args
+            .cache_dir
+            .or(file.cache_dir)
+            .unwrap_or_else(|| PathBuf::from("cache"));
+        if cache_dir.exists() && !cache_dir.is_dir() {
+            problems.push(format!(
+                "cache_dir {} is not a directory",
+                cache_dir.display()
+            ));
+        }
+
+        let outbox = OutboxConfig {
+            max_attempts: args
+                .outbox_max_attempts
+                .or(file.outbox.max_attempts)
+                .unwrap_or(6),
+            retry_base: Duration::from_secs(
+                args.outbox_retry_secs
+                    .or(file.outbox.retry_secs)
+                    .unwrap_or(30),
+            ),
+        };
+        if outbox.max_attempts == 0 {
+            problems.push("outbox.max_attempts must be at least 1".to_string());
+        }
+
+        let admin_password = args
+            .admin_password
+            .or(file.admin_password)
+            .filter(|p| !p.is_empty());
+        let secret_key = args.secret_key.or(file.secret_key);
+        if secret_key.as_ref().is_some_and(|key| key.len() < 32) {
+            problems.push("secret_key must be at least 32 bytes long".to_string());
+        }
+
+        let site_url = args
+            .site_url
+            .or(file.site_url)
+            .unwrap_or_else(|| "https://njtts.org".to_string())
+            .trim_end_matches('/')
+            .to_string();
+        if !(site_url.starts_with("https://") || site_url.starts_with("http://")) {
+            problems.push(format!(
+                "site_url {site_url:?} must start with http:// or https://"
+            ));
+        }
+        let robots = RobotsConfig {
+            allow_indexing: args
+                .robots_allow_indexing
+                .or(file.robots.allow_indexing)
+                .unwrap_or(true),
+            disallow: args
+                .robots_disallow
+                .or(file.robots.disallow)
+                .unwrap_or_else(|| vec!["/admin/".to_string()]),
+        };
+
+        let format = match args.log_format.or(file.log.format).as_deref() {
+            None | Some("pretty") => LogFormat::Pretty,
+            Some("json") => LogFormat::Json,
+            Some(other) => {
+                problems.push(format!(
+                    "log.format {other:?} is unknown (expected \"pretty\" or \"json\")"
+                ));
+                LogFormat::Pretty
+            }
+        };
+        let filter = args
+            .log_filter
+            .or(file.log.filter)
+            .unwrap_or_else(|| "info".to_string());
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&filter) {
+            problems.push(format!("log.filter {filter:?} is invalid: {e}"));
+        }
+
+        match (bind, from) {
+            (Some(bind), Some(from)) if problems.is_empty() => Ok(Config {
+                bind,
+                static_dir,
+                content_dir,
+                cache_dir,
+                storage,
+                mail: MailConfig { from, transport },
+                outbox,
+                admin_password,
+                secret_key,
+                site_url,
+                robots,
+                log: LogConfig { format, filter },
+            }),
+            _ => Err(ConfigError::Invalid(problems)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(args: &[&str]) -> Result<Config, ConfigError> {
+        Config::from_args(Args::parse_from(["website"].iter().chain(args)))
+    }
+
+    fn problems(args: &[&str]) -> Vec<String> {
+        match load(args) {
+            Err(ConfigError::Invalid(problems)) => problems,
+            other => panic!("expected problems, got {other:?}"),
+        }
+    }
+
+    fn has(problems: &[String], start: &str) -> bool {
+        problems.iter().any(|problem| problem.starts_with(start))
+    }
+
+    fn database(config: &Config) -> &str {
+        match &config.storage {
+            StorageConfig::Mongo(mongo) => &mongo.database,
+            StorageConfig::Memory => panic!("expected MongoDB storage"),
+        }
+    }
+
+    const FILE: &str = r#"
+bind = "127.0.0.1:1001"
+
+[mongodb]
+uri = "mongodb://file"
+database = "from-file"
+
+[smtp]
+server = "smtp.file"
+username = "file@njtts.org"
+password = "file-password"
+"#;
+
+    #[test]
+    fn flags_win_over_the_environment_which_wins_over_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, FILE).unwrap();
+        let config_flag = format!("--config={}", path.display());
+
+        let config = load(&[&config_flag]).unwrap();
+        assert_eq!(config.bind.port(), 1001);
+        assert_eq!(database(&config), "from-file");
+        assert_eq!(config.mail.from.email.to_string(), "file@njtts.org");
+
+        // No other test reads this variable.
+        std::env::set_var("WEBSITE_BIND", "127.0.0.1:1002");
+        let from_env = load(&[&config_flag]);
+        let from_flag = load(&[
+            &config_flag,
+            "--bind=127.0.0.1:1003",
+            "--mongodb-database=flag",
+        ]);
+        std::env::remove_var("WEBSITE_BIND");
+        assert_eq!(from_env.unwrap().bind.port(), 1002);
+        let from_flag = from_flag.unwrap();
+        assert_eq!(from_flag.bind.port(), 1003);
+        assert_eq!(database(&from_flag), "flag");
+    }
+
+    #[test]
+    fn defaults_fill_what_is_optional() {
+        let config = load(&[
+            "--mongodb-uri=mongodb://flag",
+            "--smtp-server=smtp.flag",
+            "--smtp-username=flag@njtts.org",
+            "--smtp-password=secret",
+        ])
+        .unwrap();
+        assert_eq!(database(&config), "tts");
+        assert_eq!(config.static_dir, PathBuf::from("src/static"));
+    }
+
+    #[test]
+    fn every_missing_value_is_reported_at_once() {
+        let problems = problems(&["--static-dir=no/such/dir"]);
+        for key in [
+            "mongodb.uri",
+            "smtp.server",
+            "smtp.username",
+            "smtp.password",
+        ] {
+            assert!(
+                problems
+                    .iter()
+                    .any(|p| p.starts_with(&format!("{key} is not set"))),
+                "{key} missing from {problems:?}"
+            );
+        }
+        assert!(problems.contains(&"static_dir no/such/dir is not a directory".to_string()));
+        assert_eq!(problems.len(), 6, "{problems:?}");
+    }
+
+    #[test]
+    fn memory_storage_needs_no_database() {
+        let memory = problems(&["--storage=memory"]);
+        assert!(!has(&memory, "mongodb.uri"));
+        let unknown = problems(&["--storage=postgres"]);
+        assert!(unknown.contains(
+            &"storage \"postgres\" is unknown (expected \"mongodb\" or \"memory\")".to_string()
+        ));
+    }
+
+    #[test]
+    fn the_sender_falls_back_to_the_smtp_login() {
+        let config = load(&[
+            "--storage=memory",
+            "--smtp-server=smtp.flag",
+            "--smtp-username=login@njtts.org",
+            "--smtp-password=secret",
+        ])
+        .unwrap();
+        assert_eq!(config.mail.from.email.to_string(), "login@njtts.org");
+
+        let problems = problems(&["--storage=memory", "--mail-transport=memory"]);
+        assert!(!has(&problems, "smtp."), "{problems:?}");
+        assert!(has(&problems, "mail.from is not set"));
+    }
+
+    #[test]
+    fn malformed_values_are_reported() {
+        let problems = problems(&[
+            "--bind=nowhere",
+            "--mongodb-uri=mongodb://flag",
+            "--smtp-server=smtp.flag",
+            "--smtp-username=not an address",
+            "--smtp-password=secret",
+            "--outbox-max-attempts=0",
+            "--secret-key=too short",
+            "--log-format=xml",
+        ]);
+        assert_eq!(problems.len(), 5, "{problems:?}");
+        assert!(has(&problems, "bind address \"nowhere\" is invalid"));
+        assert!(has(
+            &problems,
+            "mail.from \"not an address\" is not an email address"
+        ));
+    }
+
+    #[test]
+    fn an_unreadable_file_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert!(matches!(
+            load(&[&format!("--config={}", path.display())]),
+            Err(ConfigError::Read(..))
+        ));
+        std::fs::write(&path, "colour = \"blue\"").unwrap();
+        assert!(matches!(
+            load(&[&format!("--config={}", path.display())]),
+            Err(ConfigError::Parse(..))
+        ));
+    }
+}
@@ -0,0 +1,952 @@
+//! Photo consent. Families ask for a private link by email at
+//! `/photo-consent`, list their members there and tick who may appear in
+//! photos on the website. Organizers tag gallery photos with the people in
+//! them; a photo showing anyone without consent is withheld from the
+//! public gallery and its files, while organizers still see it marked.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Form,
+};
+use axum_extra::extract::SignedCookieJar;
+use lettre::message::Mailbox;
+use maud::{html, Markup};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::{self, Organizer},
+    gallery::{self, Album},
+    images,
+    links::PHONE,
+    outbox::{self, QueuedEmail},
+    page::error_fragment,
+    seo::Meta,
+    store::{Record, StoreError},
+    tickets::{self, Purpose},
+    ClientState,
+};
+
+/// Most members one family may list.
+const MAX_MEMBERS: usize = 20;
+/// Shown for a tag whose person the family has since removed. Their photos
+/// stay withheld until an organizer removes the tag.
+const REMOVED: &str = "a removed family member";
+const INPUT: &str = "mt-1 block w-full p-2 border border-gray-300 rounded-md";
+
+/// One family, found by email address.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Family {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    /// Lowercased.
+    pub email: String,
+    pub name: String,
+    pub created_at: DateTime,
+}
+
+impl Record for Family {
+    const COLLECTION: &'static str = "families";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Member {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub family: ObjectId,
+    pub name: String,
+    /// Whether photos showing this person may be public.
+    pub consent: bool,
+    pub consent_changed_at: DateTime,
+}
+
+impl Record for Member {
+    const COLLECTION: &'static str = "family_members";
+}
+
+/// A person in a gallery photo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    /// Relative to the gallery folder, as in [`gallery::Photo::path`].
+    pub photo: String,
+    pub member: ObjectId,
+    pub tagged_at: DateTime,
+}
+
+impl Record for Tag {
+    const COLLECTION: &'static str = "photo_tags";
+}
+
+/// Photos that must stay out of public view, each with the people in it
+/// who have no consent.
+pub async fn withheld(s: &ClientState) -> Result<HashMap<String, Vec<String>>, StoreError> {
+    let tags = s.repo::<Tag>().find(doc! {}).await?;
+    if tags.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let members: HashMap<ObjectId, Member> = s
+        .repo::<Member>()
+        .find(doc! {})
+        .await?
+        .into_iter()
+        .map(|member| (member.id, member))
+        .collect();
+    let mut withheld: HashMap<String, Vec<String>> = HashMap::new();
+    for tag in tags {
+        let name = match members.get(&tag.member) {
+            Some(member) if member.consent => continue,
+            Some(member) => member.name.clone(),
+            None => REMOVED.to_string(),
+        };
+        withheld.entry(tag.photo).or_default().push(name);
+    }
+    Ok(withheld)
+}
+
+/// Whether one photo must stay out of public view. When the records cannot
+/// be read the photo is withheld, since it may show a child.
+pub async fn is_withheld(s: &ClientState, photo: &str) -> bool {
+    let tags = match s.repo::<Tag>().find(doc! { "photo": photo }).await {
+        Ok(tags) => tags,
+        Err(e) => {
+            tracing::error!(photo = %photo, error = %e, "could not load photo tags");
+            return true;
+        }
+    };
+    for tag in tags {
+        match s
+            .repo::<Member>()
+            .find_one(doc! { "_id": tag.member })
+            .await
+        {
+            Ok(Some(member)) if member.consent => {}
+            Ok(_) => return true,
+            Err(e) => {
+                tracing::error!(photo = %photo, error = %e, "could not load tagged member");
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Keeps withheld photos from being fetched straight from `/assets`, except
+/// by organizers, and any gallery photo from being cached. Runs inside the
+/// `/assets` service, so paths start at the static directory.
+pub async fn guard_assets(
+    State(s): State<ClientState>,
+    jar: SignedCookieJar,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(photo) = gallery_photo(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    if !admin::is_organizer(&jar, &s) && is_withheld(&s, &photo).await {
+        return crate::not_found().await.into_response();
+    }
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(images::GALLERY_CACHE_CONTROL),
+    );
+    response
+}
+
+/// The gallery photo a request path under the static directory is for,
+/// relative to the gallery folder. The path is decoded and tidied the way
+/// the file server does it, so no spelling of a photo's address slips by.
+fn gallery_photo(path: &str) -> Option<String> {
+    let path = images::decode_path(path)?;
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            // Refused by the file server anyway.
+            ".." => return None,
+            segment => segments.push(segment),
+        }
+    }
+    segments
+        .join("/")
+        .strip_prefix(&format!("{}/", gallery::DIR))
+        .map(str::to_string)
+}
+
+fn family_url(s: &ClientState, family: &Family) -> String {
+    format!(
+        "{}/photo-consent/{}",
+        s.config.site_url,
+        tickets::code(s, Purpose::Family, family.id)
+    )
+}
+
+async fn find_family(s: &ClientState, code: &str) -> Option<Family> {
+    let id = tickets::verify(s, Purpose::Family, code)?;
+    match s.repo::<Family>().find_one(doc! { "_id": id }).await {
+        Ok(family) => family,
+        Err(e) => {
+            tracing::error!(family_id = %id, error = %e, "could not load family");
+            None
+        }
+    }
+}
+
+async fn members(s: &ClientState, family: ObjectId) -> Result<Vec<Member>, StoreError> {
+    let mut members = s.repo::<Member>().find(doc! { "family": family }).await?;
+    members.sort_by_key(|member| member.name.to_lowercase());
+    Ok(members)
+}
+
+pub async fn start_page() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Photo Consent",
+        "Choose whether your family may appear in photos on our website.",
+    );
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen py-10 px-4" {
+            div class="max-w-md mx-auto bg-white p-8 rounded-lg shadow-lg flex flex-col gap-4" {
+                h1 class="text-2xl font-bold text-center" { "Photo Consent" }
+                p class="text-gray-700" {
+                    "We share photos of our events in the gallery, and many of them show children. "
+                    "You decide, person by person, whether your family may appear there. Photos "
+                    "showing anyone without consent are kept off the website. We will email you a "
+                    "private link to your family's choices."
+                }
+                (link_form(None))
+            }
+        }
+    };
+    (meta, content)
+}
+
+fn link_form(error: Option<&str>) -> Markup {
+    html! {
+        div id="consent-link" {
+            @if let Some(error) = error {
+                (error_fragment(error))
+            }
+            form method="post" action="/photo-consent" hx-post="/photo-consent" hx-target="#consent-link" hx-swap="outerHTML" class="space-y-4" {
+                div {
+                    label for="name" class="block text-sm font-medium text-gray-700" { "Your name" }
+                    input type="text" id="name" name="name" class=(INPUT) required {}
+                }
+                div {
+                    label for="email" class="block text-sm font-medium text-gray-700" { "Email" }
+                    input type="email" id="email" name="email" class=(INPUT) required {}
+                }
+                div class="text-center" {
+                    button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Email me my link" }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkForm {
+    name: String,
+    email: String,
+}
+
+/// Emails the family the link to their choices, opening a record on first
+/// use.
+pub async fn send_link(State(s): State<ClientState>, Form(form): Form<LinkForm>) -> Markup {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return link_form(Some("Please tell us your name."));
+    }
+    let Ok(to) = form.email.trim().parse::<Mailbox>() else {
+        return link_form(Some(
+            "That email address does not look right. Please check it and try again.",
+        ));
+    };
+    let email = to.email.to_string().to_lowercase();
+    let sorry = format!("Sorry, we could not send your link right now. Please try again later or call us at {PHONE}.");
+    let repo = s.repo::<Family>();
+    let family = match repo.find_one(doc! { "email": &email }).await {
+        Ok(Some(family)) => family,
+        Ok(None) => {
+            let family = Family {
+                id: ObjectId::new(),
+                email,
+                name: name.to_string(),
+                created_at: DateTime::now(),
+            };
+            if let Err(e) = repo.insert(&family).await {
+                tracing::error!(error = %e, "could not open family record");
+                return link_form(Some(&sorry));
+            }
+            tracing::info!(family_id = %family.id, "family record opened");
+            family
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "could not load family");
+            return link_form(Some(&sorry));
+        }
+    };
+    let body = format!(
+        "Dear {},\n\nHere is your private link to choose who in your family may appear in \
+         photos on our website:\n\n{}\n\nYou can change your choices there at any time. \
+         Please keep the link to yourself.\n\nNJ Thiruvalluvar Tamil Sangam\n",
+        family.name,
+        family_url(&s, &family)
+    );
+    let email = QueuedEmail::new(&to, "Your photo consent choices", body);
+    if let Err(e) = outbox::enqueue(&s, email).await {
+        tracing::error!(family_id = %family.id, error = %e, "could not queue consent link");
+        return link_form(Some(&sorry));
+    }
+    html! {
+        div id="consent-link" class="p-4 rounded-lg bg-green-50 border border-green-200" {
+            p { "We have emailed your link to " (to.email) ". Please check your inbox." }
+        }
+    }
+}
+
+/// The family's members with their consent, and the form to add one.
+async fn family_markup(
+    s: &ClientState,
+    family: &Family,
+    code: &str,
+    message: Option<Result<&str, &str>>,
+) -> Markup {
+    let members = match members(s, family.id).await {
+        Ok(members) => members,
+        Err(e) => {
+            tracing::error!(family_id = %family.id, error = %e, "could not load family members");
+            return html! {
+                div id="family" { (error_fragment("Your choices are unavailable right now. Please try again later.")) }
+            };
+        }
+    };
+    let action = format!("/photo-consent/{code}/members");
+    html! {
+        div id="family" class="flex flex-col gap-6" {
+            @match message {
+                Some(Ok(message)) => {
+                    p class="p-4 rounded-lg bg-green-50 border border-green-200" { (message) }
+                }
+                Some(Err(message)) => (error_fragment(message)),
+                None => {}
+            }
+            section class="bg-white p-6 rounded-lg shadow flex flex-col gap-4" {
+                h2 class="text-xl font-semibold" { "Your family" }
+                p class="text-gray-700" {
+                    "Tick everyone who may appear in photos on our website. Photos showing anyone "
+                    "without a tick are kept off the public gallery."
+                }
+                @if members.is_empty() {
+                    p class="text-gray-600" { "Add the members of your family below, children included." }
+                }
+                ul class="divide-y" {
+                    @for member in &members {
+                        @let member_action = format!("{action}/{}", member.id);
+                        li class="flex items-center justify-between gap-4 py-2" {
+                            form method="post" action=(member_action) hx-post=(member_action) hx-trigger="change" hx-target="#family" hx-swap="outerHTML" class="flex items-center gap-2" {
+                                input type="checkbox" id={ "consent-" (member.id) } name="consent" checked[member.consent] class="h-4 w-4" {}
+                                label for={ "consent-" (member.id) } { (member.name) }
+                                noscript {
+                                    button type="submit" class="text-sm text-blue-600 hover:underline" { "Save" }
+                                }
+                            }
+                            form method="post" action={ (member_action) "/delete" } hx-post={ (member_action) "/delete" } hx-target="#family" hx-swap="outerHTML"
+                                hx-confirm={ "Remove " (member.name) "?" } {
+                                button type="submit" class="text-sm text-red-600 hover:underline" { "Remove" }
+                            }
+                        }
+                    }
+                }
+            }
+            @if members.len() < MAX_MEMBERS {
+                section class="bg-white p-6 rounded-lg shadow flex flex-col gap-4" {
+                    h2 class="text-xl font-semibold" { "Add a family member" }
+                    form method="post" action=(action) hx-post=(action) hx-target="#family" hx-swap="outerHTML" class="space-y-4" {
+                        div {
+                            label for="name" class="block text-sm font-medium text-gray-700" { "Name" }
+                            input type="text" id="name" name="name" class=(INPUT) required {}
+                        }
+                        div class="flex items-center gap-2" {
+                            input type="checkbox" id="consent" name="consent" class="h-4 w-4" {}
+                            label for="consent" { "May appear in photos on the website" }
+                        }
+                        div class="text-center" {
+                            button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Add" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn family_page(family: &Family, markup: Markup) -> (Meta, Markup) {
+    let meta = Meta::new("Your Photo Consent", "Your family's photo consent.");
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen py-10 px-4" {
+            div class="max-w-2xl mx-auto flex flex-col gap-4" {
+                h1 class="text-3xl font-bold text-center" { "Photo Consent" }
+                p class="text-center text-gray-700" { (family.name) " · " (family.email) }
+                (markup)
+            }
+        }
+    };
+    (meta, content)
+}
+
+pub async fn family(State(s): State<ClientState>, Path(code): Path<String>) -> Response {
+    let Some(family) = find_family(&s, &code).await else {
+        return crate::not_found().await.into_response();
+    };
+    let markup = family_markup(&s, &family, &code, None).await;
+    family_page(&family, markup).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MemberForm {
+    name: String,
+    /// Present when the box is ticked.
+    consent: Option<String>,
+}
+
+pub async fn add_member(
+    State(s): State<ClientState>,
+    Path(code): Path<String>,
+    Form(form): Form<MemberForm>,
+) -> Response {
+    let Some(family) = find_family(&s, &code).await else {
+        return crate::not_found().await.into_response();
+    };
+    let name = form.name.trim();
+    let result = async {
+        if name.is_empty() {
+            return Err("Please enter a name.".to_string());
+        }
+        let existing = members(&s, family.id).await.map_err(|e| {
+            tracing::error!(family_id = %family.id, error = %e, "could not load family members");
+            "Sorry, we could not save that. Please try again.".to_string()
+        })?;
+        if existing.len() >= MAX_MEMBERS {
+            return Err(format!("A family can list up to {MAX_MEMBERS} people."));
+        }
+        if existing
+            .iter()
+            .any(|member| member.name.eq_ignore_ascii_case(name))
+        {
+            return Err(format!("{name} is already listed."));
+        }
+        let member = Member {
+            id: ObjectId::new(),
+            family: family.id,
+            name: name.to_string(),
+            consent: form.consent.is_some(),
+            consent_changed_at: DateTime::now(),
+        };
+        s.repo::<Member>().insert(&member).await.map_err(|e| {
+            tracing::error!(family_id = %family.id, error = %e, "could not add family member");
+            "Sorry, we could not save that. Please try again.".to_string()
+        })?;
+        tracing::info!(family_id = %family.id, member_id = %member.id, consent = member.consent, "family member added");
+        Ok(format!("{name} added."))
+    }
+    .await;
+    let message = result.as_deref().map_err(String::as_str);
+    family_markup(&s, &family, &code, Some(message))
+        .await
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConsentForm {
+    /// Present when the box is ticked.
+    consent: Option<String>,
+}
+
+pub async fn set_consent(
+    State(s): State<ClientState>,
+    Path((code, id)): Path<(String, ObjectId)>,
+    Form(form): Form<ConsentForm>,
+) -> Response {
+    let Some(family) = find_family(&s, &code).await else {
+        return crate::not_found().await.into_response();
+    };
+    let consent = form.consent.is_some();
+    let set = doc! { "consent": consent, "consent_changed_at": DateTime::now() };
+    let message = match s
+        .repo::<Member>()
+        .update(doc! { "_id": id, "family": family.id }, set)
+        .await
+    {
+        Ok(0) => Err("That person is no longer listed."),
+        Ok(_) => {
+            tracing::info!(family_id = %family.id, member_id = %id, consent, "photo consent changed");
+            Ok("Your choice is saved.")
+        }
+        Err(e) => {
+            tracing::error!(family_id = %family.id, member_id = %id, error = %e, "could not change photo consent");
+            Err("Sorry, we could not save that. Please try again.")
+        }
+    };
+    family_markup(&s, &family, &code, Some(message))
+        .await
+        .into_response()
+}
+
+pub async fn remove_member(
+    State(s): State<ClientState>,
+    Path((code, id)): Path<(String, ObjectId)>,
+) -> Response {
+    let Some(family) = find_family(&s, &code).await else {
+        return crate::not_found().await.into_response();
+    };
+    let message = match s
+        .repo::<Member>()
+        .delete(doc! { "_id": id, "family": family.id })
+        .await
+    {
+        Ok(_) => {
+            tracing::info!(family_id = %family.id, member_id = %id, "family member removed");
+            Ok("Removed.")
+        }
+        Err(e) => {
+            tracing::error!(family_id = %family.id, member_id = %id, error = %e, "could not remove family member");
+            Err("Sorry, we could not remove that person. Please try again.")
+        }
+    };
+    family_markup(&s, &family, &code, Some(message))
+        .await
+        .into_response()
+}
+
+/// Every listed person, labelled with their family, for the tag picker.
+async fn people(s: &ClientState) -> Result<Vec<(Member, String)>, StoreError> {
+    let families: HashMap<ObjectId, Family> = s
+        .repo::<Family>()
+        .find(doc! {})
+        .await?
+        .into_iter()
+        .map(|family| (family.id, family))
+        .collect();
+    let mut people: Vec<(Member, String)> = s
+        .repo::<Member>()
+        .find(doc! {})
+        .await?
+        .into_iter()
+        .map(|member| {
+            let family = families
+                .get(&member.family)
+                .map_or_else(String::new, |family| family.name.clone());
+            (member, family)
+        })
+        .collect();
+    people.sort_by_key(|(member, family)| (member.name.to_lowercase(), family.to_lowercase()));
+    Ok(people)
+}
+
+fn album_path(year: i32, slug: &str) -> String {
+    format!("/admin/photos/{year}/{slug}")
+}
+
+/// Each photo of the album with the people tagged in it.
+async fn tagging_markup(
+    s: &ClientState,
+    year: i32,
+    album: &Album,
+    message: Option<Result<&str, &str>>,
+) -> Markup {
+    let loaded = async {
+        let people = people(s).await?;
+        let tags = s.repo::<Tag>().find(doc! {}).await?;
+        Ok::<_, StoreError>((people, tags))
+    };
+    let (people, tags) = match loaded.await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            tracing::error!(error = %e, "could not load photo tags");
+            return html! {
+                div id="tagging" { (error_fragment("Tags are unavailable right now. Please try again later.")) }
+            };
+        }
+    };
+    let names: HashMap<ObjectId, &Member> = people
+        .iter()
+        .map(|(member, _)| (member.id, member))
+        .collect();
+    let base = album_path(year, &album.slug);
+    html! {
+        div id="tagging" class="flex flex-col gap-4" {
+            @match message {
+                Some(Ok(message)) => {
+                    p class="p-4 rounded-lg bg-green-50 border border-green-200" { (message) }
+                }
+                Some(Err(message)) => (error_fragment(message)),
+                None => {}
+            }
+            @if people.is_empty() {
+                p class="text-center" { "No family has listed anyone for photo consent yet." }
+            }
+            @for photo in &album.photos {
+                @let photo_tags: Vec<&Tag> = tags.iter().filter(|tag| tag.photo == photo.path).collect();
+                @let hidden = photo_tags.iter().any(|tag| !names.get(&tag.member).is_some_and(|member| member.consent));
+                div class="bg-white p-4 rounded-lg shadow flex flex-col sm:flex-row gap-4" {
+                    div class="sm:w-48 shrink-0" {
+                        (images::picture(&photo.asset(), &photo.alt, "12rem", "rounded-lg w-full", true))
+                    }
+                    div class="flex flex-col gap-2 flex-grow" {
+                        p class="font-semibold" { (photo.path.rsplit('/').next().unwrap_or_default()) }
+                        @if hidden {
+                            p class="text-sm text-red-700" { "Hidden from the public gallery" }
+                        } @else {
+                            p class="text-sm text-green-700" { "Public" }
+                        }
+                        ul class="text-sm" {
+                            @for tag in &photo_tags {
+                                li class="flex items-center gap-2" {
+                                    @match names.get(&tag.member) {
+                                        Some(member) => {
+                                            (member.name)
+                                            @if member.consent {
+                                                span class="text-green-700" { "consented" }
+                                            } @else {
+                                                span class="text-red-700" { "no consent" }
+                                            }
+                                        }
+                                        None => span class="text-red-700" { (REMOVED) },
+                                    }
+                                    form hx-post={ (base) "/untag" } hx-target="#tagging" hx-swap="outerHTML" method="post" action={ (base) "/untag" } {
+                                        input type="hidden" name="photo" value=(photo.path) {}
+                                        input type="hidden" name="member" value=(tag.member) {}
+                                        button type="submit" class="text-red-600 hover:underline" { "Remove" }
+                                    }
+                                }
+                            }
+                        }
+                        @if !people.is_empty() {
+                            form hx-post={ (base) "/tag" } hx-target="#tagging" hx-swap="outerHTML" method="post" action={ (base) "/tag" } class="flex gap-2 items-center" {
+                                input type="hidden" name="photo" value=(photo.path) {}
+                                select name="member" class="p-1 border border-gray-300 rounded-md text-sm" required {
+                                    option value="" { "Choose a person…" }
+                                    @for (member, family) in &people {
+                                        option value=(member.id) { (member.name) " (" (family) ")" }
+                                    }
+                                }
+                                button type="submit" class="bg-orange-500 text-white px-3 py-1 rounded-md text-sm hover:bg-orange-600" { "Tag" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn find_album(s: &ClientState, year: i32, slug: &str) -> Option<Album> {
+    gallery::find_album(&s.config.static_dir, year, slug)
+}
+
+/// Organizer view for tagging the people in one album's photos.
+pub async fn tagging_page(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path((year, slug)): Path<(i32, String)>,
+) -> Response {
+    let Some(album) = find_album(&s, year, &slug) else {
+        return crate::not_found().await.into_response();
+    };
+    let meta = Meta::new(
+        format!("Tag People: {}", album.title),
+        "Tag people in photos.",
+    );
+    let markup = tagging_markup(&s, year, &album, None).await;
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-4xl mx-auto p-8 flex flex-col gap-4" {
+                h1 class="text-3xl font-bold text-center" { (album.title) " " (year) }
+                p class="text-center text-gray-700" {
+                    "Tag the people in each photo. A photo showing anyone without photo consent "
+                    "is hidden from the public gallery."
+                }
+                (markup)
+            }
+        }
+    };
+    (meta, content).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagForm {
+    photo: String,
+    member: String,
+}
+
+pub async fn tag(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path((year, slug)): Path<(i32, String)>,
+    Form(form): Form<TagForm>,
+) -> Response {
+    let Some(album) = find_album(&s, year, &slug) else {
+        return crate::not_found().await.into_response();
+    };
+    let result = async {
+        if !album.photos.iter().any(|photo| photo.path == form.photo) {
+            return Err("That photo is not in this album.");
+        }
+        let Ok(member) = form.member.parse::<ObjectId>() else {
+            return Err("Please choose a person.");
+        };
+        let repo = s.repo::<Tag>();
+        let failed = |e: StoreError| {
+            tracing::error!(photo = %form.photo, error = %e, "could not tag photo");
+            "Could not save the tag. Please try again."
+        };
+        if s.repo::<Member>()
+            .find_one(doc! { "_id": member })
+            .await
+            .map_err(failed)?
+            .is_none()
+        {
+            return Err("That person is no longer listed.");
+        }
+        if repo
+            .find_one(doc! { "photo": &form.photo, "member": member })
+            .await
+            .map_err(failed)?
+            .is_some()
+        {
+            return Err("That person is already tagged in the photo.");
+        }
+        let tag = Tag {
+            id: ObjectId::new(),
+            photo: form.photo.clone(),
+            member,
+            tagged_at: DateTime::now(),
+        };
+        repo.insert(&tag).await.map_err(failed)?;
+        tracing::info!(photo = %form.photo, member_id = %member, "photo tagged");
+        Ok("Tagged.")
+    }
+    .await;
+    tagging_markup(&s, year, &album, Some(result))
+        .await
+        .into_response()
+}
+
+pub async fn untag(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path((year, slug)): Path<(i32, String)>,
+    Form(form): Form<TagForm>,
+) -> Response {
+    let Some(album) = find_album(&s, year, &slug) else {
+        return crate::not_found().await.into_response();
+    };
+    let result = match form.member.parse::<ObjectId>() {
+        Err(_) => Err("Please choose a person."),
+        Ok(member) => match s
+            .repo::<Tag>()
+            .delete(doc! { "photo": &form.photo, "member": member })
+            .await
+        {
+            Ok(_) => {
+                tracing::info!(photo = %form.photo, member_id = %member, "photo tag removed");
+                Ok("Tag removed.")
+            }
+            Err(e) => {
+                tracing::error!(photo = %form.photo, error = %e, "could not remove photo tag");
+                Err("Could not remove the tag. Please try again.")
+            }
+        },
+    };
+    tagging_markup(&s, year, &album, Some(result))
+        .await
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::tests;
+
+    const PHOTO: &str = "2026/fest/x.jpg";
+
+    #[test]
+    fn finds_the_photo_however_its_path_is_spelled() {
+        for path in [
+            "/img/gallery/2026/fest/x.jpg",
+            "//img//gallery/2026//fest/x.jpg",
+            "/./img/gallery/./2026/fest/x.jpg",
+            "/img%2fgallery%2f2026%2ffest%2fx.jpg",
+            "/%69mg/gallery/2026/fest/%78.jpg",
+        ] {
+            assert_eq!(gallery_photo(path).as_deref(), Some(PHOTO), "{path:?}");
+        }
+    }
+
+    #[test]
+    fn refuses_paths_that_climb() {
+        for path in [
+            "/img/other/../gallery/2026/fest/x.jpg",
+            "/img/other/%2e%2e/gallery/2026/fest/x.jpg",
+            "/img/other/%2E%2E%2Fgallery/2026/fest/x.jpg",
+            "/img/gallery/2026/fest/%FF.jpg",
+        ] {
+            assert_eq!(gallery_photo(path), None, "{path:?}");
+        }
+    }
+
+    #[test]
+    fn backslashes_do_not_separate() {
+        // The file server does not split on them either, so this names a
+        // file called `2026\fest\x.jpg`, not the photo.
+        assert_eq!(
+            gallery_photo("/img/gallery/2026%5Cfest%5Cx.jpg").as_deref(),
+            Some("2026\\fest\\x.jpg")
+        );
+        assert_eq!(gallery_photo("/img\\gallery\\2026\\fest\\x.jpg"), None);
+    }
+
+    #[test]
+    fn other_assets_are_not_gallery_photos() {
+        assert_eq!(gallery_photo("/img/home_bg.jpeg"), None);
+        assert_eq!(gallery_photo("/img/gallery"), None);
+        assert_eq!(gallery_photo("/img/gallery.jpg"), None);
+    }
+
+    /// A site with one gallery photo tagged with a child whose family has
+    /// not given consent.
+    async fn withheld_photo(
+        static_dir: &std::path::Path,
+        cache_dir: &std::path::Path,
+    ) -> ClientState {
+        let album = static_dir.join(gallery::DIR).join("2026/fest");
+        std::fs::create_dir_all(&album).unwrap();
+        image::RgbImage::new(8, 8)
+            .save(album.join("x.jpg"))
+            .unwrap();
+        let s = tests::test_state_with(&[
+            &format!("--static-dir={}", static_dir.display()),
+            &format!("--cache-dir={}", cache_dir.display()),
+        ]);
+        let family = Family {
+            id: ObjectId::new(),
+            email: "parent@example.com".to_string(),
+            name: "Parent".to_string(),
+            created_at: DateTime::now(),
+        };
+        s.repo::<Family>().insert(&family).await.unwrap();
+        let member = Member {
+            id: ObjectId::new(),
+            family: family.id,
+            name: "Child".to_string(),
+            consent: false,
+            consent_changed_at: DateTime::now(),
+        };
+        s.repo::<Member>().insert(&member).await.unwrap();
+        let tag = Tag {
+            id: ObjectId::new(),
+            photo: PHOTO.to_string(),
+            member: member.id,
+            tagged_at: DateTime::now(),
+        };
+        s.repo::<Tag>().insert(&tag).await.unwrap();
+        s
+    }
+
+    /// Addresses that reach the photo's file or a copy of it.
+    const REACHING: &[&str] = &[
+        "/assets/img/gallery/2026/fest/x.jpg",
+        "/assets//img//gallery/2026//fest/x.jpg",
+        "/assets/./img/gallery/./2026/fest/x.jpg",
+        "/assets/img%2fgallery%2f2026%2ffest%2fx.jpg",
+        "/assets/%69mg/gallery/2026/fest/%78.jpg",
+        "/resized/400/jpeg/img/gallery/2026/fest/x.jpg",
+        "/resized/400/webp/img%2fgallery%2f2026%2ffest%2fx.jpg",
+        "/resized/400/jpeg/%69mg/gallery/2026/fest/%78.jpg",
+    ];
+
+    /// Other spellings, which are refused or name no file at all.
+    const REFUSED: &[&str] = &[
+        "/assets/img/other/../gallery/2026/fest/x.jpg",
+        "/assets/img/other/%2e%2e/gallery/2026/fest/x.jpg",
+        "/assets/img/gallery/2026%5Cfest%5Cx.jpg",
+        "/resized/400/jpeg/img//gallery/2026/fest/x.jpg",
+        "/resized/400/jpeg/img/gallery/./2026/fest/x.jpg",
+        "/resized/400/jpeg/img/other/../gallery/2026/fest/x.jpg",
+        "/resized/400/jpeg/img/other/%2e%2e/gallery/2026/fest/x.jpg",
+        "/resized/400/jpeg/img/gallery/2026%5Cfest%5Cx.jpg",
+    ];
+
+    #[tokio::test]
+    async fn withheld_photo_is_hidden_from_visitors_at_every_address() {
+        let static_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let s = withheld_photo(static_dir.path(), cache_dir.path()).await;
+        for uri in REACHING.iter().chain(REFUSED) {
+            let status = tests::get(&s, uri, None).await.status();
+            assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn organizers_still_see_a_withheld_photo() {
+        let static_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let s = withheld_photo(static_dir.path(), cache_dir.path()).await;
+        let cookie = tests::organizer_cookie(&s).await;
+        for uri in REACHING {
+            let status = tests::get(&s, uri, Some(&cookie)).await.status();
+            assert_eq!(status, StatusCode::OK, "{uri}");
+        }
+        for uri in REFUSED {
+            let status = tests::get(&s, uri, Some(&cookie)).await.status();
+            assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn consent_makes_the_photo_public() {
+        let static_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let s = withheld_photo(static_dir.path(), cache_dir.path()).await;
+        s.repo::<Member>()
+            .update(doc! {}, doc! { "consent": true })
+            .await
+            .unwrap();
+        for uri in REACHING {
+            let status = tests::get(&s, uri, None).await.status();
+            assert_eq!(status, StatusCode::OK, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn gallery_photos_are_not_kept_in_caches() {
+        let static_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let s = withheld_photo(static_dir.path(), cache_dir.path()).await;
+        image::RgbImage::new(8, 8)
+            .save(static_dir.path().join("img/poster.jpg"))
+            .unwrap();
+        let cookie = tests::organizer_cookie(&s).await;
+        for uri in REACHING {
+            let response = tests::get(&s, uri, Some(&cookie)).await;
+            let cache_control = &response.headers()[header::CACHE_CONTROL];
+            assert_eq!(cache_control, "private, no-cache", "{uri}");
+        }
+        let response = tests::get(&s, "/resized/400/jpeg/img/poster.jpg", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "public, max-age=86400"
+        );
+    }
+}
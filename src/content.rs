@@ -0,0 +1,105 @@
+//! Site content that organizers edit as TOML files under `content_dir`
+//! instead of in Rust. A [`ContentFile`] re-reads its file whenever the
+//! modification time changes; an edit that does not parse is logged and the
+//! last good version keeps being served.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use serde::de::DeserializeOwned;
+
+/// A document that can be loaded from a content file.
+pub trait Content: DeserializeOwned + Send + Sync + 'static {
+    /// Checks what deserializing alone cannot, such as unique slugs.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ContentError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentError::Read(path, e) => write!(f, "could not read {}: {e}", path.display()),
+            ContentError::Parse(path, e) => write!(f, "could not parse {}: {e}", path.display()),
+            ContentError::Invalid(path, e) => write!(f, "{}: {e}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ContentError {}
+
+struct Loaded<T> {
+    modified: Option<SystemTime>,
+    value: Arc<T>,
+}
+
+pub struct ContentFile<T> {
+    path: PathBuf,
+    loaded: RwLock<Loaded<T>>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read<T: Content>(path: &Path) -> Result<T, ContentError> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| ContentError::Read(path.to_path_buf(), e))?;
+    let value: T = toml::from_str(&text).map_err(|e| ContentError::Parse(path.to_path_buf(), e))?;
+    value
+        .validate()
+        .map_err(|e| ContentError::Invalid(path.to_path_buf(), e))?;
+    Ok(value)
+}
+
+impl<T: Content> ContentFile<T> {
+    /// Reads `path`, failing when it is missing or invalid so that a broken
+    /// file is noticed at startup rather than by visitors.
+    pub fn load(path: impl Into<PathBuf>) -> Result<ContentFile<T>, ContentError> {
+        let path = path.into();
+        let modified = modified(&path);
+        let value = Arc::new(read(&path)?);
+        Ok(ContentFile {
+            path,
+            loaded: RwLock::new(Loaded { modified, value }),
+        })
+    }
+
+    /// The current contents, re-read first if the file changed on disk.
+    pub fn get(&self) -> Arc<T> {
+        let modified = modified(&self.path);
+        {
+            let loaded = self.loaded.read().unwrap();
+            if loaded.modified == modified {
+                return loaded.value.clone();
+            }
+        }
+        let mut loaded = self.loaded.write().unwrap();
+        if loaded.modified != modified {
+            // Remember the timestamp even on failure so a broken file is
+            // reported once per edit, not on every request.
+            loaded.modified = modified;
+            match read(&self.path) {
+                Ok(value) => {
+                    tracing::info!(path = %self.path.display(), "reloaded content");
+                    loaded.value = Arc::new(value);
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "keeping the previous content");
+                }
+            }
+        }
+        loaded.value.clone()
+    }
+}
@@ -0,0 +1,615 @@
+//! The events catalog. Events live in `events.toml` in the content
+//! directory; the events page splits them into upcoming and past by today's
+//! date, so an event moves to the archive on its own once it is over. Each
+//! event also has a page of its own at `/events/{slug}` to share directly,
+//! and a calendar entry at `/events/{slug}.ics`.
+
+use std::collections::{BTreeMap, HashSet};
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use chrono::{Datelike, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use maud::{html, Markup};
+use serde::Deserialize;
+
+use crate::{
+    calendar,
+    content::{Content, ContentFile},
+    images, registration, schedule,
+    seo::Meta,
+    volunteers, ClientState,
+};
+
+pub const FILE_NAME: &str = "events.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Event {
+    pub title: String,
+    /// Lowercase letters, digits and dashes; unique across all events.
+    pub slug: String,
+    pub date: NaiveDate,
+    /// Last day of an event that spans several days.
+    pub end_date: Option<NaiveDate>,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub venue: Option<String>,
+    pub address: Option<String>,
+    /// Site-relative path of the poster image.
+    pub poster: Option<String>,
+    pub registration: Option<Registration>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, rename = "sponsor")]
+    pub sponsors: Vec<Sponsor>,
+    #[serde(default, rename = "volunteer")]
+    pub shifts: Vec<Shift>,
+}
+
+/// How people sign up for an event.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Registration {
+    /// Link to a form hosted elsewhere.
+    Link(String),
+    /// Registration on this site.
+    Native(NativeRegistration),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NativeRegistration {
+    /// Most attendees, adults and children together; unlimited when unset.
+    pub capacity: Option<u32>,
+    /// Last day to register [default: the day of the event].
+    pub closes: Option<NaiveDate>,
+    /// Whether registrations past capacity join a waitlist instead of being
+    /// turned away.
+    #[serde(default = "waitlist_default")]
+    pub waitlist: bool,
+}
+
+fn waitlist_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sponsor {
+    pub name: String,
+    /// e.g. "Gold Sponsor" or "Food Partner".
+    pub tier: Option<String>,
+    /// Site-relative path of the logo or banner.
+    pub logo: Option<String>,
+}
+
+/// A volunteer role for one stretch of time, e.g. setup from 1 to 3 PM.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Shift {
+    /// Identifies the shift within its event, e.g. "setup". Sign-ups are
+    /// kept under it, so the role and times can be edited without losing
+    /// them.
+    pub id: String,
+    /// e.g. "Setup", "Food", "Stage" or "Cleanup".
+    pub role: String,
+    /// Day of the shift [default: the first day of the event].
+    pub date: Option<NaiveDate>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Volunteers needed.
+    pub needed: u32,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Shift {
+    pub fn day(&self, event: &Event) -> NaiveDate {
+        self.date.unwrap_or(event.date)
+    }
+
+    /// e.g. "Sat, Mar 29, 1:00 PM – 3:00 PM".
+    pub fn when(&self, event: &Event) -> String {
+        format!(
+            "{}, {} – {}",
+            self.day(event).format("%a, %b %-d"),
+            self.start.format("%-I:%M %p"),
+            self.end.format("%-I:%M %p")
+        )
+    }
+}
+
+impl Event {
+    pub fn url(&self) -> String {
+        format!("/events/{}", self.slug)
+    }
+
+    pub fn last_day(&self) -> NaiveDate {
+        self.end_date.unwrap_or(self.date)
+    }
+
+    /// The on-site registration settings, if people register here.
+    pub fn native_registration(&self) -> Option<&NativeRegistration> {
+        match &self.registration {
+            Some(Registration::Native(native)) => Some(native),
+            _ => None,
+        }
+    }
+
+    /// Whether on-site registration accepts new entries on `today`.
+    pub fn registration_open(&self, today: NaiveDate) -> bool {
+        self.native_registration()
+            .is_some_and(|native| today <= native.closes.unwrap_or(self.date))
+    }
+
+    pub fn find_shift(&self, id: &str) -> Option<&Shift> {
+        self.shifts.iter().find(|shift| shift.id == id)
+    }
+
+    /// Whether the event has not finished by `today`.
+    pub fn is_upcoming(&self, today: NaiveDate) -> bool {
+        self.last_day() >= today
+    }
+
+    /// The date or date range, e.g. "Saturday, March 29, 2025".
+    pub fn dates(&self) -> String {
+        match self.end_date {
+            Some(end) if end != self.date => {
+                let start = if end.year() == self.date.year() {
+                    self.date.format("%B %-d")
+                } else {
+                    self.date.format("%B %-d, %Y")
+                };
+                format!("{start} – {}", end.format("%B %-d, %Y"))
+            }
+            _ => self.date.format("%A, %B %-d, %Y").to_string(),
+        }
+    }
+
+    /// The time of day, e.g. "2:00 PM – 7:00 PM", if one is set.
+    pub fn times(&self) -> Option<String> {
+        let start = self.start_time?.format("%-I:%M %p");
+        Some(match self.end_time {
+            Some(end) => format!("{start} – {}", end.format("%-I:%M %p")),
+            None => format!("from {start}"),
+        })
+    }
+
+    /// Venue and address joined for display.
+    pub fn location(&self) -> Option<String> {
+        match (&self.venue, &self.address) {
+            (Some(venue), Some(address)) => Some(format!("{venue}, {address}")),
+            (Some(place), None) | (None, Some(place)) => Some(place.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Layout of `events.toml`: a list of `[[event]]` tables.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Events {
+    #[serde(rename = "event", default)]
+    pub events: Vec<Event>,
+}
+
+/// Whether `name` is made of lowercase letters, digits and dashes only.
+fn is_slug(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+impl Content for Events {
+    fn validate(&self) -> Result<(), String> {
+        let mut slugs = HashSet::new();
+        for event in &self.events {
+            let slug = &event.slug;
+            if !is_slug(slug) {
+                return Err(format!(
+                    "slug {slug:?} may only contain lowercase letters, digits and dashes"
+                ));
+            }
+            if !slugs.insert(slug) {
+                return Err(format!("slug {slug:?} is used by more than one event"));
+            }
+            if event.last_day() < event.date {
+                return Err(format!("event {slug:?} ends before it starts"));
+            }
+            if event
+                .native_registration()
+                .is_some_and(|native| native.capacity == Some(0))
+            {
+                return Err(format!("event {slug:?} has a capacity of 0"));
+            }
+            let mut shifts = HashSet::new();
+            for shift in &event.shifts {
+                let role = &shift.role;
+                if role.trim().is_empty() {
+                    return Err(format!("a volunteer shift of event {slug:?} has no role"));
+                }
+                let id = &shift.id;
+                if !is_slug(id) {
+                    return Err(format!(
+                        "shift id {id:?} of event {slug:?} may only contain lowercase letters, digits and dashes"
+                    ));
+                }
+                if !shifts.insert(id) {
+                    return Err(format!("event {slug:?} has two shifts with id {id:?}"));
+                }
+                if shift.end <= shift.start {
+                    return Err(format!(
+                        "the {role:?} shift of event {slug:?} ends before it starts"
+                    ));
+                }
+                if shift.needed == 0 {
+                    return Err(format!(
+                        "the {role:?} shift of event {slug:?} needs no volunteers"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub type EventsFile = ContentFile<Events>;
+
+impl Events {
+    pub fn find(&self, slug: &str) -> Option<&Event> {
+        self.events.iter().find(|event| event.slug == slug)
+    }
+
+    /// Events that have not finished by `today`, soonest first.
+    pub fn upcoming(&self, today: NaiveDate) -> Vec<&Event> {
+        let mut upcoming: Vec<&Event> = self
+            .events
+            .iter()
+            .filter(|event| event.is_upcoming(today))
+            .collect();
+        upcoming.sort_by_key(|event| (event.date, event.start_time));
+        upcoming
+    }
+
+    /// Finished events grouped by year, most recent year and event first.
+    pub fn past_by_year(&self, today: NaiveDate) -> Vec<(i32, Vec<&Event>)> {
+        let mut years: BTreeMap<i32, Vec<&Event>> = BTreeMap::new();
+        for event in self.events.iter().filter(|event| !event.is_upcoming(today)) {
+            years.entry(event.date.year()).or_default().push(event);
+        }
+        years
+            .into_iter()
+            .rev()
+            .map(|(year, mut events)| {
+                events.sort_by_key(|event| std::cmp::Reverse((event.date, event.start_time)));
+                (year, events)
+            })
+            .collect()
+    }
+}
+
+/// The site's time zone. Event dates and times are New Jersey time
+/// wherever the server runs.
+pub const TIME_ZONE: Tz = chrono_tz::America::New_York;
+
+/// Today in New Jersey.
+pub fn today() -> NaiveDate {
+    Utc::now().with_timezone(&TIME_ZONE).date_naive()
+}
+
+fn event_card(event: &Event, upcoming: bool) -> Markup {
+    html! {
+        article class="bg-white rounded-lg shadow-lg overflow-hidden flex flex-col text-left" {
+            @if let Some(poster) = &event.poster {
+                (images::picture(
+                    poster,
+                    &format!("{} poster", event.title),
+                    "(min-width: 1024px) 22rem, (min-width: 640px) 50vw, 100vw",
+                    "w-full object-cover",
+                    true,
+                ))
+            }
+            div class="p-4 flex flex-col gap-2 flex-grow" {
+                h3 class="text-xl font-semibold text-gray-900" {
+                    a href=(event.url()) hx-get=(event.url()) hx-target="#page" hx-push-url="true" class="hover:text-blue-700 hover:underline" {
+                        (event.title)
+                    }
+                }
+                p class="text-base text-gray-700" {
+                    (event.dates())
+                    @if let Some(times) = event.times() { ", " (times) }
+                }
+                @if let Some(location) = event.location() {
+                    p class="text-base text-gray-600" { (location) }
+                }
+                @if !event.description.is_empty() {
+                    p class="text-base text-gray-600" { (event.description) }
+                }
+                @if upcoming {
+                    @match &event.registration {
+                        Some(Registration::Link(link)) => {
+                            a href=(link) target="_blank" rel="noopener noreferrer"
+                                class="mt-auto self-start text-white bg-orange-600 hover:bg-red-600 px-4 py-2 rounded-lg text-base font-medium" {
+                                "Register"
+                            }
+                        }
+                        Some(Registration::Native(_)) => {
+                            a href=(event.url()) hx-get=(event.url()) hx-target="#page" hx-push-url="true"
+                                class="mt-auto self-start text-white bg-orange-600 hover:bg-red-600 px-4 py-2 rounded-lg text-base font-medium" {
+                                "Register"
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub async fn events_page(State(s): State<ClientState>) -> (Meta, Markup) {
+    let events = s.events.get();
+    let today = today();
+    let upcoming = events.upcoming(today);
+    let past = events.past_by_year(today);
+
+    let mut meta = Meta::new(
+        "Events",
+        "Upcoming and past events of NJ Thiruvalluvar Tamil Sangam.",
+    );
+    if let Some(poster) = upcoming.iter().find_map(|event| event.poster.as_ref()) {
+        meta = meta.image(poster);
+    }
+    let content = html! {
+        div class="min-h-screen flex flex-col items-center space-y-10 py-10 px-4 bg-vertical-to-pink text-center" {
+            section class="w-full max-w-6xl space-y-6" {
+                h2 class="text-red-700 font-semibold text-xl md:text-2xl lg:text-3xl" { "Upcoming Events" }
+                a href="/events.ics" class="inline-block text-blue-600 hover:underline text-base" {
+                    "Subscribe to our events calendar"
+                }
+                @if upcoming.is_empty() {
+                    p class="text-lg text-gray-700" {
+                        "No events are scheduled right now. Check back soon!"
+                    }
+                } @else {
+                    div class="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 gap-6" {
+                        @for event in &upcoming {
+                            (event_card(event, true))
+                        }
+                    }
+                }
+            }
+            @for (year, events) in &past {
+                section class="w-full max-w-6xl space-y-6" {
+                    h2 class="text-red-700 font-semibold text-xl md:text-2xl lg:text-3xl" { "Past Events - " (year) }
+                    div class="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 gap-6" {
+                        @for event in events {
+                            (event_card(event, false))
+                        }
+                    }
+                }
+            }
+        }
+    };
+    (meta, content)
+}
+
+pub async fn event_page(State(s): State<ClientState>, Path(slug): Path<String>) -> Response {
+    let events = s.events.get();
+    if let Some(event) = slug.strip_suffix(".ics").and_then(|slug| events.find(slug)) {
+        return calendar::event_ics(&s, event);
+    }
+    let Some(event) = events.find(&slug) else {
+        return crate::not_found().await.into_response();
+    };
+    let registration = registration::section(&s, event, today()).await;
+    let volunteer = volunteers::section(&s, event, today()).await;
+    let schedule = schedule::published(&s, &event.slug).await;
+
+    let summary = match event.location() {
+        Some(location) => format!("{} at {location}.", event.dates()),
+        None => format!("{}.", event.dates()),
+    };
+    let description = if event.description.is_empty() {
+        summary.clone()
+    } else {
+        format!("{summary} {}", event.description)
+    };
+    let mut meta = Meta::new(&event.title, description);
+    if let Some(poster) = &event.poster {
+        meta = meta.image(poster);
+    }
+    let content = html! {
+        div class="min-h-screen flex justify-center py-10 px-4 bg-vertical-to-pink" {
+            article class="w-full max-w-5xl bg-white rounded-lg shadow-lg overflow-hidden grid grid-cols-1 md:grid-cols-2" {
+                @if let Some(poster) = &event.poster {
+                    a href=(poster) target="_blank" rel="noopener noreferrer" {
+                        (images::picture(
+                            poster,
+                            &format!("{} poster", event.title),
+                            "(min-width: 1024px) 32rem, (min-width: 768px) 50vw, 100vw",
+                            "w-full h-full object-cover",
+                            false,
+                        ))
+                    }
+                }
+                div class="p-6 flex flex-col gap-4" {
+                    a href="/events" hx-get="/events" hx-target="#page" hx-push-url="true" class="text-blue-600 hover:underline text-sm" {
+                        "← All events"
+                    }
+                    h1 class="text-3xl font-extrabold text-gray-900" { (event.title) }
+                    dl class="grid grid-cols-[auto_1fr] gap-x-4 gap-y-2 text-base" {
+                        dt class="font-semibold text-gray-700" { "Date" }
+                        dd class="text-gray-900" { (event.dates()) }
+                        @if let Some(times) = event.times() {
+                            dt class="font-semibold text-gray-700" { "Time" }
+                            dd class="text-gray-900" { (times) }
+                        }
+                        @if let Some(venue) = &event.venue {
+                            dt class="font-semibold text-gray-700" { "Venue" }
+                            dd class="text-gray-900" { (venue) }
+                        }
+                        @if let Some(address) = &event.address {
+                            dt class="font-semibold text-gray-700" { "Address" }
+                            dd class="text-gray-900" { (address) }
+                        }
+                    }
+                    @if !event.description.is_empty() {
+                        p class="text-base text-gray-700" { (event.description) }
+                    }
+                    (registration)
+                    (volunteer)
+                    @if schedule {
+                        a href=(format!("{}/schedule", event.url())) class="self-start text-blue-600 hover:underline" {
+                            "Program schedule"
+                        }
+                    }
+                    a href=(format!("{}.ics", event.url())) class="self-start text-blue-600 hover:underline" {
+                        "Add to my calendar"
+                    }
+                    @if !event.sponsors.is_empty() {
+                        section class="space-y-3" {
+                            h2 class="text-xl font-semibold text-gray-900" { "Sponsors" }
+                            div class="grid grid-cols-2 gap-4" {
+                                @for sponsor in &event.sponsors {
+                                    div class="flex flex-col items-center text-center gap-1" {
+                                        @if let Some(logo) = &sponsor.logo {
+                                            img src=(logo) class="w-full h-auto" alt=(sponsor.name) loading="lazy" {}
+                                        }
+                                        p class="font-semibold text-gray-900" { (sponsor.name) }
+                                        @if let Some(tier) = &sponsor.tier {
+                                            p class="text-sm text-gray-600" { (tier) }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+    (meta, content).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn events(toml: &str) -> Events {
+        let events: Events = toml::from_str(toml).unwrap();
+        events.validate().unwrap();
+        events
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn slugs(events: &[&Event]) -> Vec<String> {
+        events.iter().map(|event| event.slug.clone()).collect()
+    }
+
+    const CATALOG: &str = r#"
+[[event]]
+title = "Pongal"
+slug = "pongal"
+date = "2025-01-18"
+
+[[event]]
+title = "Camping"
+slug = "camping"
+date = "2025-06-20"
+end_date = "2025-06-22"
+
+[[event]]
+title = "Deepavali"
+slug = "deepavali"
+date = "2024-11-02"
+
+[[event]]
+title = "Tamil New Year"
+slug = "tamil-new-year"
+date = "2025-04-12"
+start_time = "14:00:00"
+
+[[event]]
+title = "Book Fair"
+slug = "book-fair"
+date = "2025-04-12"
+start_time = "10:00:00"
+"#;
+
+    #[test]
+    fn an_event_is_upcoming_through_its_last_day() {
+        let events = events(CATALOG);
+        let on_the_day = events.upcoming(date("2025-04-12"));
+        assert_eq!(
+            slugs(&on_the_day),
+            ["book-fair", "tamil-new-year", "camping"]
+        );
+        let day_after = events.upcoming(date("2025-04-13"));
+        assert_eq!(slugs(&day_after), ["camping"]);
+
+        assert_eq!(slugs(&events.upcoming(date("2025-06-22"))), ["camping"]);
+        assert!(events.upcoming(date("2025-06-23")).is_empty());
+    }
+
+    #[test]
+    fn past_events_are_grouped_by_year_newest_first() {
+        let events = events(CATALOG);
+        let past = events.past_by_year(date("2025-04-13"));
+        let years: Vec<(i32, Vec<String>)> = past
+            .into_iter()
+            .map(|(year, events)| (year, slugs(&events)))
+            .collect();
+        assert_eq!(
+            years,
+            [
+                (
+                    2025,
+                    vec![
+                        "tamil-new-year".to_string(),
+                        "book-fair".to_string(),
+                        "pongal".to_string()
+                    ]
+                ),
+                (2024, vec!["deepavali".to_string()]),
+            ]
+        );
+        assert!(events
+            .past_by_year(date("2025-04-12"))
+            .iter()
+            .all(|(_, events)| !slugs(events).contains(&"book-fair".to_string())));
+    }
+
+    #[test]
+    fn the_day_turns_over_in_new_jersey() {
+        let events = events(CATALOG);
+        // 10 PM on April 12 in New Jersey is already April 13 in UTC.
+        let late_evening = Utc.with_ymd_and_hms(2025, 4, 13, 2, 0, 0).unwrap();
+        let today = late_evening.with_timezone(&TIME_ZONE).date_naive();
+        assert_eq!(today, date("2025-04-12"));
+        assert!(slugs(&events.upcoming(today)).contains(&"tamil-new-year".to_string()));
+    }
+
+    #[test]
+    fn validation_rejects_bad_slugs_and_dates() {
+        let parse = |toml: &str| toml::from_str::<Events>(toml).unwrap().validate();
+        let one = "[[event]]\ntitle = \"A\"\nslug = \"a\"\ndate = \"2025-01-01\"\n";
+        assert!(parse(&one.repeat(2))
+            .unwrap_err()
+            .contains("more than one event"));
+        assert!(parse(&one.replace("\"a\"", "\"Big Day\"")).is_err());
+        let backwards = format!("{one}end_date = \"2024-12-31\"\n");
+        assert!(parse(&backwards)
+            .unwrap_err()
+            .contains("ends before it starts"));
+    }
+}
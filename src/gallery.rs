@@ -1,125 +1,589 @@
+//! The photo gallery, built from the folders under `img/gallery` in the
+//! static directory: one folder per year, and inside it one folder per
+//! event, e.g. `img/gallery/2024/summer-camping/`. Copying photos there
+//! publishes them. An optional `gallery.toml` beside the photos gives the
+//! event a title and date and the photos captions and alt text.
+//!
+//! The page shows resized copies from [`crate::images`]; the original only
+//! loads when someone opens a photo in the lightbox. Photos showing someone
+//! without photo consent ([`crate::consent`]) are left out, except for
+//! organizers, who see them marked.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use axum::extract::State;
+use axum_extra::extract::SignedCookieJar;
+use chrono::NaiveDate;
 use maud::{html, Markup, PreEscaped};
+use serde::Deserialize;
 
-pub async fn gallery_page() -> Markup {
-    html! {
-        div class="flex justify-center items-center py-12 px-4 sm:px-6 lg:px-8 bg-vertical-to-pink" {
-            // Gallery container
-            div class="max-w-7xl mx-auto px-4 sm:px-6 lg:px-8" {
-                div class="grid grid-cols-1 gap-8 lg:grid-cols-3 lg:gap-10" {
-                    // Year sidebar
-                    div class="hidden lg:block lg:col-span-1" {
-                        div class="sticky top-16 space-y-4"{
-                            a href="#2024" class="text-gray-700 hover:text-gray-900 hover:underline block" {"2024"}
-                        }
-                    }
+use crate::{admin, consent, images, page::error_fragment, seo::Meta, ClientState};
 
-                    // Photo grid
-                    div class="lg:col-span-2 space-y-8 sm:space-y-0" {
-                        // 2024 section
-                        div id="2024" class="space-y-4" {
-                            h2 class="text-2xl font-bold text-gray-900" {"2024"}
+/// The gallery folder, relative to the static directory.
+pub const DIR: &str = "img/gallery";
+pub const METADATA_FILE: &str = "gallery.toml";
+const EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
 
-                            // Event 1
-                            div class="space-y-4" {
-                                h3 class="text-xl font-semibold text-gray-800" {"Father's Day Celebration"}
+/// Layout of `gallery.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Metadata {
+    title: Option<String>,
+    /// Orders events within the year, newest first.
+    date: Option<NaiveDate>,
+    #[serde(rename = "photo", default)]
+    photos: Vec<PhotoMetadata>,
+}
 
-                                div class="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 gap-4 p-4 border rounded-lg shadow-lg bg-white" {
-                                    // Image 1
-                                    a href="assets/img/image1.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/image1.jpg" alt="Photo 1" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PhotoMetadata {
+    file: String,
+    alt: Option<String>,
+    caption: Option<String>,
+}
 
-                                    // Image 2
-                                    a href="assets/img/image2.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/image2.jpg" alt="Photo 2" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
+pub struct Photo {
+    /// Relative to the gallery folder, e.g. "2024/summer-camping/image2.jpg".
+    pub path: String,
+    pub alt: String,
+    pub caption: Option<String>,
+}
 
-                                    // Image 3
-                                    a href="assets/img/image3.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/image3.jpg" alt="Photo 3" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
+impl Photo {
+    /// The original under `/assets`, as a path rather than a URL.
+    pub fn asset(&self) -> String {
+        format!("/assets/{DIR}/{}", self.path)
+    }
 
-                                    // Image 4 (optional)
-                                    a href="assets/img/image4.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/image4.jpg" alt="Photo 4" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
-                                    a href="assets/img/image5.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/image5.jpg" alt="Photo 5" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
-                                    a href="assets/img/image6.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/image6.jpg" alt="Photo 6" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
+    /// The address of the original.
+    pub fn url(&self) -> String {
+        format!(
+            "/assets/{}",
+            images::encode_path(&format!("{DIR}/{}", self.path))
+        )
+    }
+}
 
+pub struct Album {
+    /// The folder name, e.g. "summer-camping".
+    pub slug: String,
+    pub title: String,
+    pub date: Option<NaiveDate>,
+    pub photos: Vec<Photo>,
+}
 
-                                }
-                            }
+pub struct Year {
+    pub year: i32,
+    /// Dated events newest first, then the rest by folder name.
+    pub albums: Vec<Album>,
+}
 
-                            div class="space-y-4" {
-                                h3 class="text-xl font-semibold text-gray-800" {"Summer Camping"}
+fn is_image(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, extension)| {
+        EXTENSIONS
+            .iter()
+            .any(|known| extension.eq_ignore_ascii_case(known))
+    })
+}
 
-                                div class="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 gap-4 p-4 border rounded-lg shadow-lg bg-white" {
+/// "summer-camping" becomes "Summer Camping".
+fn title_from_slug(slug: &str) -> String {
+    slug.split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-                                    // Image 2
-                                    a href="assets/img/camping/image2.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/camping/image2.jpg" alt="Photo 2" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
+/// Visible entries of `dir` with UTF-8 names, sorted by name. A missing
+/// folder is simply empty.
+fn entries(dir: &Path) -> Vec<(String, PathBuf)> {
+    let read = match std::fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            tracing::warn!(path = %dir.display(), error = %e, "could not read gallery folder");
+            return Vec::new();
+        }
+    };
+    let mut entries: Vec<_> = read
+        .filter_map(Result::ok)
+        .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.path())))
+        .filter(|(name, _)| !name.starts_with('.'))
+        .collect();
+    entries.sort();
+    entries
+}
 
-                                    // Image 3
-                                    a href="assets/img/camping/image3.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/camping/image3.jpg" alt="Photo 3" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
+fn metadata(dir: &Path) -> Metadata {
+    let path = dir.join(METADATA_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Metadata::default(),
+        Err(e) => {
+            tracing::error!(path = %path.display(), error = %e, "could not read gallery metadata");
+            return Metadata::default();
+        }
+    };
+    toml::from_str(&text).unwrap_or_else(|e| {
+        tracing::error!(path = %path.display(), error = %e, "ignoring invalid gallery metadata");
+        Metadata::default()
+    })
+}
 
-                                    // Image 4 (optional)
-                                    a href="assets/img/camping/image4.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/camping/image4.jpg" alt="Photo 4" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
-                                    a href="assets/img/camping/image5.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/camping/image5.jpg" alt="Photo 5" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
-                                    a href="assets/img/camping/image6.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-                                        img src="assets/img/camping/image6.jpg" alt="Photo 6" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                                    }
+fn album(year: i32, slug: String, dir: &Path) -> Album {
+    let metadata = metadata(dir);
+    let title = metadata.title.unwrap_or_else(|| title_from_slug(&slug));
+    let mut files: Vec<String> = entries(dir)
+        .into_iter()
+        .filter(|(name, path)| is_image(name) && path.is_file())
+        .map(|(name, _)| name)
+        .collect();
 
+    // Photos named in the metadata first, in its order, then the rest.
+    let mut described: HashMap<String, PhotoMetadata> = HashMap::new();
+    let mut order = Vec::new();
+    for photo in metadata.photos {
+        if !files.contains(&photo.file) {
+            tracing::warn!(
+                path = %dir.join(METADATA_FILE).display(),
+                file = %photo.file,
+                "gallery metadata describes a photo that is not there"
+            );
+            continue;
+        }
+        order.push(photo.file.clone());
+        described.insert(photo.file.clone(), photo);
+    }
+    files.retain(|file| !described.contains_key(file));
+    order.extend(files);
 
-                                }
-                            }
+    let photos = order
+        .into_iter()
+        .enumerate()
+        .map(|(i, file)| {
+            let (alt, caption) = match described.remove(&file) {
+                Some(photo) => (photo.alt, photo.caption),
+                None => (None, None),
+            };
+            Photo {
+                alt: alt
+                    .or_else(|| caption.clone())
+                    .unwrap_or_else(|| format!("{title}, photo {}", i + 1)),
+                caption,
+                path: format!("{year}/{slug}/{file}"),
+            }
+        })
+        .collect();
+    Album {
+        slug,
+        title,
+        date: metadata.date,
+        photos,
+    }
+}
 
+/// Every year and event with at least one photo, newest year first.
+pub fn scan(static_dir: &Path) -> Vec<Year> {
+    let mut years: Vec<Year> = entries(&static_dir.join(DIR))
+        .into_iter()
+        .filter_map(|(name, path)| Some((name.parse::<i32>().ok()?, path)))
+        .map(|(year, path)| {
+            let mut albums: Vec<Album> = entries(&path)
+                .into_iter()
+                .filter(|(_, path)| path.is_dir())
+                .map(|(slug, path)| album(year, slug, &path))
+                .filter(|album| !album.photos.is_empty())
+                .collect();
+            albums.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.slug.cmp(&b.slug)));
+            Year { year, albums }
+        })
+        .filter(|year| !year.albums.is_empty())
+        .collect();
+    years.sort_by_key(|year| std::cmp::Reverse(year.year));
+    years
+}
 
+/// One album of the gallery, if the folder exists.
+pub fn find_album(static_dir: &Path, year: i32, slug: &str) -> Option<Album> {
+    if slug.is_empty() || slug.starts_with('.') || slug.contains(['/', '\\']) {
+        return None;
+    }
+    let dir = static_dir.join(DIR).join(year.to_string()).join(slug);
+    dir.is_dir().then(|| album(year, slug.to_string(), &dir))
+}
 
+/// Leaves out withheld photos, then any album or year left empty.
+fn without_withheld(years: Vec<Year>, withheld: &HashMap<String, Vec<String>>) -> Vec<Year> {
+    years
+        .into_iter()
+        .map(|mut year| {
+            for album in &mut year.albums {
+                album
+                    .photos
+                    .retain(|photo| !withheld.contains_key(&photo.path));
+            }
+            year.albums.retain(|album| !album.photos.is_empty());
+            year
+        })
+        .filter(|year| !year.albums.is_empty())
+        .collect()
+}
 
-                            // // Event 2
-                            // div class="space-y-4" {
-                            //     h3 class="text-xl font-semibold text-gray-800" {"Event 2 Title"}
-                            //     div class="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 gap-4 p-4 border rounded-lg shadow-lg bg-white" {
-                            //         // Image 1
-                            //         div class="group relative" {
-                            //             img src="https://via.placeholder.com/600x400?text=Photo+1" alt="Photo 1" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                            //         }
+pub async fn gallery_page(State(s): State<ClientState>, jar: SignedCookieJar) -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Gallery",
+        "Photos from Tamil Sangam festivals, competitions and community events.",
+    );
+    let organizer = admin::is_organizer(&jar, &s);
+    let static_dir = s.config.static_dir.clone();
+    let years = tokio::task::spawn_blocking(move || scan(&static_dir))
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!(error = %e, "gallery scan failed");
+            Vec::new()
+        });
+    let (years, withheld, unavailable) = match consent::withheld(&s).await {
+        Ok(withheld) if organizer => (years, withheld, false),
+        Ok(withheld) => (without_withheld(years, &withheld), HashMap::new(), false),
+        // Without the tags nobody can tell which photos are safe to show.
+        Err(e) => {
+            tracing::error!(error = %e, "could not load photo consent");
+            let years = if organizer { years } else { Vec::new() };
+            (years, HashMap::new(), true)
+        }
+    };
+    let content = html! {
+        div class="flex justify-center items-center py-12 px-4 sm:px-6 lg:px-8 bg-vertical-to-pink" {
+            div class="max-w-7xl w-full mx-auto px-4 sm:px-6 lg:px-8 space-y-8" {
+                @if unavailable {
+                    (error_fragment(if organizer {
+                        "Photo consent could not be checked, so every photo is shown here but none to the public."
+                    } else {
+                        "The gallery is unavailable right now. Please try again later."
+                    }))
+                }
+                @if years.is_empty() {
+                    @if !unavailable {
+                        p class="text-center text-gray-700" { "Photos from our events are coming soon." }
+                    }
+                } @else {
+                    div class="grid grid-cols-1 gap-8 lg:grid-cols-3 lg:gap-10" {
+                        // Year sidebar
+                        div class="hidden lg:block lg:col-span-1" {
+                            div class="sticky top-16 space-y-4" {
+                                @for year in &years {
+                                    a href={ "#" (year.year) } class="text-gray-700 hover:text-gray-900 hover:underline block" { (year.year) }
+                                }
+                            }
+                        }
 
-                            //         // Image 2
-                            //         div class="group relative" {
-                            //             img src="https://via.placeholder.com/600x400?text=Photo+2" alt="Photo 2" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                            //         }
+                        // Photo grid
+                        div id="gallery" class="lg:col-span-2 space-y-12" {
+                            @for year in &years {
+                                div id=(year.year) class="space-y-8" {
+                                    h2 class="text-2xl font-bold text-gray-900" { (year.year) }
+                                    @for album in &year.albums {
+                                        (album_markup(year.year, album, organizer.then_some(&withheld)))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    (lightbox())
+                }
+                p class="text-center text-sm text-gray-700" {
+                    "Parents: you choose whether your family appears here. "
+                    a href="/photo-consent" hx-get="/photo-consent" hx-target="#page" hx-push-url="true" class="text-blue-600 hover:underline" {
+                        "Manage photo consent"
+                    }
+                }
+            }
+        }
+    };
+    (meta, content)
+}
 
-                            //         // Image 3
-                            //         div class="group relative" {
-                            //             img src="https://via.placeholder.com/600x400?text=Photo+3" alt="Photo 3" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                            //         }
+/// Shows the original of a clicked photo over the page. Without JavaScript
+/// the links simply open the original.
+fn lightbox() -> Markup {
+    html! {
+        dialog id="lightbox" class="max-w-[95vw] max-h-[95vh] p-0 rounded-lg bg-black backdrop:bg-black/80" {
+            img class="max-w-[95vw] max-h-[90vh] object-contain mx-auto" alt="";
+            p class="text-center text-white p-2 empty:hidden" {}
+        }
+        script {
+            (PreEscaped(r#"
+                (function () {
+                    const lightbox = document.getElementById('lightbox');
+                    const photo = lightbox.querySelector('img');
+                    const caption = lightbox.querySelector('p');
+                    document.getElementById('gallery').addEventListener('click', (event) => {
+                        const link = event.target.closest('a[data-lightbox]');
+                        if (!link) return;
+                        event.preventDefault();
+                        photo.src = link.href;
+                        photo.alt = link.dataset.alt;
+                        caption.textContent = link.dataset.caption || '';
+                        lightbox.showModal();
+                    });
+                    lightbox.addEventListener('click', () => lightbox.close());
+                    lightbox.addEventListener('close', () => photo.removeAttribute('src'));
+                })();
+            "#))
+        }
+    }
+}
 
-                            //         // Image 4 (optional)
-                            //         div class="group relative" {
-                            //             img src="https://via.placeholder.com/600x400?text=Photo+4" alt="Photo 4" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
-                            //         }
-                            //     }
-                            // }
+/// Photos fill a third of the two-thirds column on large screens, half the
+/// screen on small ones and all of it on phones.
+const GRID_SIZES: &str = "(min-width: 1024px) 17rem, (min-width: 640px) 45vw, 100vw";
 
-                            // Add more events for 2024
+/// One event's photos. `withheld` is given for organizers, who also see
+/// the photos kept from the public, marked with whom they show.
+fn album_markup(
+    year: i32,
+    album: &Album,
+    withheld: Option<&HashMap<String, Vec<String>>>,
+) -> Markup {
+    html! {
+        div id={ (year) "-" (album.slug) } class="space-y-4" {
+            h3 class="text-xl font-semibold text-gray-800" {
+                (album.title)
+                @if let Some(date) = album.date {
+                    span class="ml-2 text-base font-normal text-gray-600" { (date.format("%B %-d")) }
+                }
+                @if withheld.is_some() {
+                    a href={ "/admin/photos/" (year) "/" (album.slug) } class="ml-2 text-base font-normal text-blue-600 hover:underline" {
+                        "Tag people"
+                    }
+                }
+            }
+            div class="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 gap-4 p-4 border rounded-lg shadow-lg bg-white" {
+                @for photo in &album.photos {
+                    figure class="space-y-1" {
+                        a href=(photo.url()) target="_blank" rel="noopener noreferrer"
+                            data-lightbox data-alt=(photo.alt) data-caption=[photo.caption.as_deref()]
+                            class="group relative block overflow-hidden rounded-lg" {
+                            (images::picture(
+                                &photo.asset(),
+                                &photo.alt,
+                                GRID_SIZES,
+                                "rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105",
+                                true,
+                            ))
+                        }
+                        @if let Some(caption) = &photo.caption {
+                            figcaption class="text-sm text-gray-700" { (caption) }
+                        }
+                        @if let Some(names) = withheld.and_then(|withheld| withheld.get(&photo.path)) {
+                            p class="text-sm text-red-700" {
+                                "Hidden from the public: no photo consent for " (names.join(", "))
+                            }
                         }
-
-                        // Add more year sections as needed
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates the file `path` under the gallery folder of `static_dir`.
+    fn write(static_dir: &Path, path: &str, contents: &str) {
+        let path = static_dir.join(DIR).join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn files(album: &Album) -> Vec<&str> {
+        album
+            .photos
+            .iter()
+            .map(|photo| photo.path.rsplit('/').next().unwrap())
+            .collect()
+    }
+
+    fn only_album(static_dir: &Path) -> Album {
+        let mut years = scan(static_dir);
+        assert_eq!(years.len(), 1);
+        let mut albums = years.remove(0).albums;
+        assert_eq!(albums.len(), 1);
+        albums.remove(0)
+    }
+
+    #[test]
+    fn years_are_newest_first_and_dated_albums_lead() {
+        let dir = tempfile::tempdir().unwrap();
+        for album in [
+            "2023/picnic",
+            "2024/walk",
+            "2024/camping",
+            "2024/pongal",
+            "2024/diwali",
+        ] {
+            write(dir.path(), &format!("{album}/1.jpg"), "");
+        }
+        write(
+            dir.path(),
+            "2024/pongal/gallery.toml",
+            "date = \"2024-01-14\"\n",
+        );
+        write(
+            dir.path(),
+            "2024/diwali/gallery.toml",
+            "date = \"2024-11-02\"\n",
+        );
+        write(dir.path(), "2024/empty/notes.txt", "");
+        write(dir.path(), "misc/photo/1.jpg", "");
+
+        let years = scan(dir.path());
+        let years: Vec<(i32, Vec<&str>)> = years
+            .iter()
+            .map(|year| {
+                let slugs = year
+                    .albums
+                    .iter()
+                    .map(|album| album.slug.as_str())
+                    .collect();
+                (year.year, slugs)
+            })
+            .collect();
+        assert_eq!(
+            years,
+            [
+                (2024, vec!["diwali", "pongal", "camping", "walk"]),
+                (2023, vec!["picnic"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn described_photos_come_first_in_file_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["a.jpg", "b.jpg", "c.jpg", "d.jpg"] {
+            write(dir.path(), &format!("2024/camping/{file}"), "");
+        }
+        write(
+            dir.path(),
+            "2024/camping/gallery.toml",
+            r#"
+[[photo]]
+file = "c.jpg"
+
+[[photo]]
+file = "gone.jpg"
+
+[[photo]]
+file = "a.jpg"
+"#,
+        );
+        assert_eq!(
+            files(&only_album(dir.path())),
+            ["c.jpg", "a.jpg", "b.jpg", "d.jpg"]
+        );
+    }
+
+    #[test]
+    fn alt_text_falls_back_to_the_caption_then_the_title() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["a.jpg", "b.jpg", "c.jpg"] {
+            write(dir.path(), &format!("2024/camping/{file}"), "");
+        }
+        write(
+            dir.path(),
+            "2024/camping/gallery.toml",
+            r#"
+title = "Summer Camping"
+
+[[photo]]
+file = "a.jpg"
+alt = "Tents by the lake"
+caption = "Our campsite"
+
+[[photo]]
+file = "b.jpg"
+caption = "Around the fire"
+"#,
+        );
+        let album = only_album(dir.path());
+        let alts: Vec<&str> = album
+            .photos
+            .iter()
+            .map(|photo| photo.alt.as_str())
+            .collect();
+        assert_eq!(
+            alts,
+            [
+                "Tents by the lake",
+                "Around the fire",
+                "Summer Camping, photo 3"
+            ]
+        );
+        assert_eq!(album.photos[0].caption.as_deref(), Some("Our campsite"));
+        assert_eq!(album.photos[2].caption, None);
+    }
+
+    #[test]
+    fn invalid_or_missing_metadata_is_ignored() {
+        for metadata in [Some("title = \n"), Some("colour = \"blue\"\n"), None] {
+            let dir = tempfile::tempdir().unwrap();
+            write(dir.path(), "2024/summer-camping/b.jpg", "");
+            write(dir.path(), "2024/summer-camping/a.jpg", "");
+            if let Some(metadata) = metadata {
+                write(dir.path(), "2024/summer-camping/gallery.toml", metadata);
+            }
+            let album = only_album(dir.path());
+            assert_eq!(album.title, "Summer Camping");
+            assert_eq!(album.date, None);
+            assert_eq!(files(&album), ["a.jpg", "b.jpg"]);
+            assert_eq!(album.photos[0].alt, "Summer Camping, photo 1");
+        }
+    }
+
+    #[test]
+    fn hidden_and_other_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in [
+            "photo.JPG",
+            "photo.webp",
+            ".hidden.jpg",
+            "notes.txt",
+            "README",
+            "gallery.toml",
+            "folder.jpg/inside.jpg",
+        ] {
+            write(dir.path(), &format!("2024/camping/{file}"), "");
+        }
+        write(dir.path(), "2024/.trash/old.jpg", "");
+        let album = only_album(dir.path());
+        assert_eq!(album.slug, "camping");
+        assert_eq!(files(&album), ["photo.JPG", "photo.webp"]);
+    }
+
+    #[test]
+    fn urls_encode_each_segment() {
+        let photo = Photo {
+            path: "2024/summer camping/Día 1.jpg".to_string(),
+            alt: String::new(),
+            caption: None,
+        };
+        assert_eq!(
+            photo.url(),
+            "/assets/img/gallery/2024/summer%20camping/D%C3%ADa%201.jpg"
+        );
+    }
+}
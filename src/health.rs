@@ -0,0 +1,202 @@
+//! Endpoints for the reverse proxy and uptime monitor: `/healthz` says the
+//! process is alive, `/readyz` checks every dependency and `/version` reports
+//! which build is running. While MongoDB is down but submissions can still
+//! be journaled, `/readyz` reports the database as degraded and stays ready,
+//! since the site keeps serving.
+
+use std::{future::Future, time::Duration};
+
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{mail, ClientState};
+
+/// How long a single dependency check may take before it counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn health_router() -> Router<ClientState> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/version", get(version))
+}
+
+#[derive(Serialize)]
+struct Check {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Check {
+    fn new<E: std::fmt::Display>(result: Result<(), E>) -> Check {
+        let error = result.err().map(|e| e.to_string());
+        Check {
+            status: if error.is_none() { "ok" } else { "error" },
+            error,
+        }
+    }
+
+    fn timed_out() -> Check {
+        Check::new(Err(format!(
+            "no answer within {}s",
+            CHECK_TIMEOUT.as_secs()
+        )))
+    }
+
+    fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+async fn check<E: std::fmt::Display>(future: impl Future<Output = Result<(), E>>) -> Check {
+    match tokio::time::timeout(CHECK_TIMEOUT, future).await {
+        Ok(result) => Check::new(result),
+        Err(_) => Check::timed_out(),
+    }
+}
+
+/// Checks the journal that keeps submissions while the database is down,
+/// if the store has one.
+async fn check_journal(s: &ClientState) -> Option<Check> {
+    match tokio::time::timeout(CHECK_TIMEOUT, s.store.check_journal()).await {
+        Ok(result) => result.map(Check::new),
+        Err(_) => Some(Check::timed_out()),
+    }
+}
+
+async fn healthz() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+/// Ready while the database answers, or while submissions can be journaled
+/// until it does; the second case is reported as degraded.
+async fn readyz(State(s): State<ClientState>) -> (StatusCode, Json<Value>) {
+    let (mut database, mail) =
+        tokio::join!(check(s.store.ping()), check(mail::check(s.mailer.clone())));
+    let journal = if database.is_ok() {
+        None
+    } else {
+        check_journal(&s).await
+    };
+    let journaling = journal.as_ref().is_some_and(Check::is_ok);
+    if journaling {
+        database.status = "degraded";
+    }
+    let (status, summary) = if !mail.is_ok() || !(database.is_ok() || journaling) {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    } else if journaling {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+    let mut checks = json!({ "database": database, "mail": mail });
+    if let Some(journal) = journal {
+        checks["journal"] = json!(journal);
+    }
+    (status, Json(json!({ "status": summary, "checks": checks })))
+}
+
+async fn version(State(s): State<ClientState>) -> Json<Value> {
+    Json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_hash": env!("GIT_HASH"),
+        "started_at": s.started_at.try_to_rfc3339_string().unwrap_or_default(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use mongodb::bson::Document;
+
+    use super::*;
+    use crate::{
+        store::{JournaledStore, Store, StoreError},
+        tests,
+    };
+
+    /// A database that never answers.
+    struct DownStore;
+
+    fn down() -> StoreError {
+        mongodb::error::Error::custom("connection refused").into()
+    }
+
+    #[async_trait]
+    impl Store for DownStore {
+        async fn insert(&self, _: &str, _: Document) -> Result<(), StoreError> {
+            Err(down())
+        }
+
+        async fn find(&self, _: &str, _: Document) -> Result<Vec<Document>, StoreError> {
+            Err(down())
+        }
+
+        async fn count(&self, _: &str, _: Document) -> Result<u64, StoreError> {
+            Err(down())
+        }
+
+        async fn update(&self, _: &str, _: Document, _: Document) -> Result<u64, StoreError> {
+            Err(down())
+        }
+
+        async fn delete(&self, _: &str, _: Document) -> Result<u64, StoreError> {
+            Err(down())
+        }
+
+        async fn ping(&self) -> Result<(), StoreError> {
+            Err(down())
+        }
+    }
+
+    fn with_store(store: Arc<dyn Store>) -> ClientState {
+        ClientState {
+            store,
+            ..tests::test_state()
+        }
+    }
+
+    #[tokio::test]
+    async fn ready_when_everything_answers() {
+        let (status, Json(body)) = readyz(State(tests::test_state())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["checks"]["database"]["status"], "ok");
+        assert!(body["checks"].get("journal").is_none());
+    }
+
+    #[tokio::test]
+    async fn degraded_but_ready_while_the_journal_takes_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JournaledStore::new(Arc::new(DownStore), dir.path().join("journal.jsonl"));
+        let (status, Json(body)) = readyz(State(with_store(Arc::new(store)))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["checks"]["database"]["status"], "degraded");
+        assert!(body["checks"]["database"]["error"].is_string());
+        assert_eq!(body["checks"]["journal"]["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn unavailable_when_the_journal_cannot_be_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("journal.jsonl");
+        let store = JournaledStore::new(Arc::new(DownStore), path);
+        let (status, Json(body)) = readyz(State(with_store(Arc::new(store)))).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["checks"]["journal"]["status"], "error");
+    }
+
+    #[tokio::test]
+    async fn unavailable_without_a_journal() {
+        let (status, Json(body)) = readyz(State(with_store(Arc::new(DownStore)))).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["checks"]["database"]["status"], "error");
+        assert!(body["checks"].get("journal").is_none());
+    }
+}
@@ -0,0 +1,958 @@
+//! Volunteer-hours ledger. Volunteers ask for a private link by email at
+//! `/volunteer-hours`, report the hours they gave at each event there and
+//! see their totals; coordinators approve or reject each report. For any
+//! date range the approved hours can be printed as a PDF certificate whose
+//! signed code anyone can check at `/certificates/{code}`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    Form,
+};
+use chrono::{Datelike, Days, NaiveDate};
+use lettre::message::Mailbox;
+use maud::{html, Markup};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::Organizer,
+    events::{today, Event, TIME_ZONE},
+    links::PHONE,
+    outbox::{self, QueuedEmail},
+    page::error_fragment,
+    pdf::{self, Line},
+    seo::Meta,
+    store::{Record, StoreError},
+    tickets::{self, Purpose},
+    ClientState,
+};
+
+/// Most hours one report may claim.
+const MAX_HOURS: f64 = 24.0;
+/// How far back volunteers may report hours.
+const REPORT_DAYS: u64 = 365;
+
+/// One volunteer's ledger, found by email address.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ledger {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    /// Lowercased.
+    pub email: String,
+    pub name: String,
+    pub created_at: DateTime,
+}
+
+impl Record for Ledger {
+    const COLLECTION: &'static str = "hour_ledgers";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl EntryStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            EntryStatus::Pending => "pending",
+            EntryStatus::Approved => "approved",
+            EntryStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// Hours reported for one event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub ledger: ObjectId,
+    /// Slug of the event.
+    pub event: String,
+    /// Day of the event, kept so the entry does not move if the event does.
+    pub date: NaiveDate,
+    pub hours: f64,
+    #[serde(default)]
+    pub note: String,
+    pub status: EntryStatus,
+    pub created_at: DateTime,
+    pub reviewed_at: Option<DateTime>,
+}
+
+impl Record for Entry {
+    const COLLECTION: &'static str = "volunteer_hours";
+}
+
+/// A certificate as issued, so its code can be verified later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub ledger: ObjectId,
+    pub name: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub hours: f64,
+    pub issued_at: DateTime,
+}
+
+impl Record for Certificate {
+    const COLLECTION: &'static str = "certificates";
+}
+
+fn ledger_url(s: &ClientState, ledger: &Ledger) -> String {
+    format!(
+        "{}/volunteer-hours/{}",
+        s.config.site_url,
+        tickets::code(s, Purpose::Ledger, ledger.id)
+    )
+}
+
+async fn find_ledger(s: &ClientState, code: &str) -> Option<Ledger> {
+    let id = tickets::verify(s, Purpose::Ledger, code)?;
+    match s.repo::<Ledger>().find_one(doc! { "_id": id }).await {
+        Ok(ledger) => ledger,
+        Err(e) => {
+            tracing::error!(ledger_id = %id, error = %e, "could not load hours ledger");
+            None
+        }
+    }
+}
+
+async fn entries(s: &ClientState, ledger: ObjectId) -> Result<Vec<Entry>, StoreError> {
+    let mut entries = s.repo::<Entry>().find(doc! { "ledger": ledger }).await?;
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+    Ok(entries)
+}
+
+/// Hours without trailing zeros, e.g. "2.5". An empty sum is -0.0, so the
+/// `+ 0.0` keeps it from printing as "-0".
+fn hours(hours: f64) -> String {
+    format!("{:.2}", hours + 0.0)
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// The New Jersey day a certificate was issued on.
+fn issued_on(certificate: &Certificate) -> NaiveDate {
+    chrono::DateTime::from_timestamp_millis(certificate.issued_at.timestamp_millis())
+        .map_or_else(today, |at| at.with_timezone(&TIME_ZONE).date_naive())
+}
+
+fn approved(entries: &[Entry]) -> f64 {
+    entries
+        .iter()
+        .filter(|entry| entry.status == EntryStatus::Approved)
+        .map(|entry| entry.hours)
+        .sum()
+}
+
+fn event_title(s: &ClientState, slug: &str) -> String {
+    s.events
+        .get()
+        .find(slug)
+        .map_or(slug.to_string(), |event| event.title.clone())
+}
+
+const INPUT: &str = "mt-1 block w-full p-2 border border-gray-300 rounded-md";
+
+pub async fn start_page() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Volunteer Hours",
+        "Report your volunteer hours and get a service certificate.",
+    );
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen py-10 px-4" {
+            div class="max-w-md mx-auto bg-white p-8 rounded-lg shadow-lg flex flex-col gap-4" {
+                h1 class="text-2xl font-bold text-center" { "Volunteer Hours" }
+                p class="text-gray-700" {
+                    "Report the hours you volunteered at our events, see your totals and print a service "
+                    "certificate for school or award programs. We will email you a private link to your hours."
+                }
+                (link_form(None))
+            }
+        }
+    };
+    (meta, content)
+}
+
+fn link_form(error: Option<&str>) -> Markup {
+    html! {
+        div id="hours-link" {
+            @if let Some(error) = error {
+                (error_fragment(error))
+            }
+            form method="post" action="/volunteer-hours" hx-post="/volunteer-hours" hx-target="#hours-link" hx-swap="outerHTML" class="space-y-4" {
+                div {
+                    label for="name" class="block text-sm font-medium text-gray-700" { "Name" }
+                    input type="text" id="name" name="name" class=(INPUT) required {}
+                }
+                div {
+                    label for="email" class="block text-sm font-medium text-gray-700" { "Email" }
+                    input type="email" id="email" name="email" class=(INPUT) required {}
+                }
+                div class="text-center" {
+                    button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Email me my link" }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkForm {
+    name: String,
+    email: String,
+}
+
+/// Emails the volunteer the link to their ledger, opening one on first use.
+pub async fn send_link(State(s): State<ClientState>, Form(form): Form<LinkForm>) -> Markup {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return link_form(Some("Please tell us your name."));
+    }
+    let Ok(to) = form.email.trim().parse::<Mailbox>() else {
+        return link_form(Some(
+            "That email address does not look right. Please check it and try again.",
+        ));
+    };
+    let email = to.email.to_string().to_lowercase();
+    let sorry = format!("Sorry, we could not send your link right now. Please try again later or call us at {PHONE}.");
+    let repo = s.repo::<Ledger>();
+    let ledger = match repo.find_one(doc! { "email": &email }).await {
+        Ok(Some(ledger)) => ledger,
+        Ok(None) => {
+            let ledger = Ledger {
+                id: ObjectId::new(),
+                email,
+                name: name.to_string(),
+                created_at: DateTime::now(),
+            };
+            if let Err(e) = repo.insert(&ledger).await {
+                tracing::error!(error = %e, "could not open hours ledger");
+                return link_form(Some(&sorry));
+            }
+            tracing::info!(ledger_id = %ledger.id, "hours ledger opened");
+            ledger
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "could not load hours ledger");
+            return link_form(Some(&sorry));
+        }
+    };
+    let body = format!(
+        "Dear {},\n\nHere is your private link to report your volunteer hours, see your totals \
+         and print your service certificate:\n\n{}\n\nPlease keep it to yourself.\n\n\
+         NJ Thiruvalluvar Tamil Sangam\n",
+        ledger.name,
+        ledger_url(&s, &ledger)
+    );
+    let email = QueuedEmail::new(&to, "Your volunteer hours", body);
+    if let Err(e) = outbox::enqueue(&s, email).await {
+        tracing::error!(ledger_id = %ledger.id, error = %e, "could not queue hours link");
+        return link_form(Some(&sorry));
+    }
+    html! {
+        div id="hours-link" class="p-4 rounded-lg bg-green-50 border border-green-200" {
+            p { "We have emailed your link to " (to.email) ". Please check your inbox." }
+        }
+    }
+}
+
+/// Events hours can be reported for on `today`.
+fn reportable(events: &[Event], today: NaiveDate) -> Vec<&Event> {
+    let since = today
+        .checked_sub_days(Days::new(REPORT_DAYS))
+        .unwrap_or(today);
+    let mut events: Vec<&Event> = events
+        .iter()
+        .filter(|event| event.date <= today && event.last_day() >= since)
+        .collect();
+    events.sort_by_key(|event| std::cmp::Reverse(event.date));
+    events
+}
+
+/// The volunteer's entries, totals and forms.
+async fn ledger_markup(
+    s: &ClientState,
+    ledger: &Ledger,
+    code: &str,
+    message: Option<Result<&str, &str>>,
+) -> Markup {
+    let entries = match entries(s, ledger.id).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!(ledger_id = %ledger.id, error = %e, "could not load volunteer hours");
+            return html! {
+                div id="ledger" { (error_fragment("Your hours are unavailable right now. Please try again later.")) }
+            };
+        }
+    };
+    let pending: f64 = entries
+        .iter()
+        .filter(|entry| entry.status == EntryStatus::Pending)
+        .map(|entry| entry.hours)
+        .sum();
+    let events = s.events.get();
+    let today = today();
+    let action = format!("/volunteer-hours/{code}");
+    let year_start = NaiveDate::from_ymd_opt(today.year(), 1, 1).unwrap_or(today);
+    html! {
+        div id="ledger" class="flex flex-col gap-6" {
+            div class="grid grid-cols-2 gap-4 text-center" {
+                div class="bg-white p-4 rounded-lg shadow" {
+                    p class="text-3xl font-bold" { (hours(approved(&entries))) }
+                    p class="text-gray-600" { "hours approved" }
+                }
+                div class="bg-white p-4 rounded-lg shadow" {
+                    p class="text-3xl font-bold" { (hours(pending)) }
+                    p class="text-gray-600" { "hours awaiting approval" }
+                }
+            }
+            @match message {
+                Some(Ok(message)) => {
+                    p class="p-4 rounded-lg bg-green-50 border border-green-200" { (message) }
+                }
+                Some(Err(message)) => (error_fragment(message)),
+                None => {}
+            }
+            section class="bg-white p-6 rounded-lg shadow flex flex-col gap-4" {
+                h2 class="text-xl font-semibold" { "Report hours" }
+                form method="post" action=(action) hx-post=(action) hx-target="#ledger" hx-swap="outerHTML" class="space-y-4" {
+                    div {
+                        label for="event" class="block text-sm font-medium text-gray-700" { "Event" }
+                        select id="event" name="event" class=(INPUT) required {
+                            @for event in reportable(&events.events, today) {
+                                option value=(event.slug) { (event.title) " (" (event.date.format("%b %-d, %Y")) ")" }
+                            }
+                        }
+                    }
+                    div {
+                        label for="hours" class="block text-sm font-medium text-gray-700" { "Hours" }
+                        input type="number" id="hours" name="hours" min="0.25" max=(MAX_HOURS) step="0.25" class=(INPUT) required {}
+                    }
+                    div {
+                        label for="note" class="block text-sm font-medium text-gray-700" { "What you did (optional)" }
+                        input type="text" id="note" name="note" class=(INPUT) {}
+                    }
+                    div class="text-center" {
+                        button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Report" }
+                    }
+                }
+            }
+            @if !entries.is_empty() {
+                section class="bg-white p-6 rounded-lg shadow overflow-x-auto" {
+                    h2 class="text-xl font-semibold mb-2" { "Your hours" }
+                    table class="min-w-full text-left text-sm" {
+                        thead class="bg-gray-100" {
+                            tr {
+                                th class="p-2" { "Date" }
+                                th class="p-2" { "Event" }
+                                th class="p-2" { "Hours" }
+                                th class="p-2" { "Status" }
+                            }
+                        }
+                        tbody {
+                            @for entry in &entries {
+                                tr class="border-t" {
+                                    td class="p-2" { (entry.date.format("%b %-d, %Y")) }
+                                    td class="p-2" { (event_title(s, &entry.event)) }
+                                    td class="p-2" { (hours(entry.hours)) }
+                                    td class="p-2" { (status_text(entry.status)) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            section class="bg-white p-6 rounded-lg shadow flex flex-col gap-4" {
+                h2 class="text-xl font-semibold" { "Service certificate" }
+                p class="text-gray-700" { "A PDF of your approved hours between two dates." }
+                form method="get" action={ (action) "/certificate" } class="grid grid-cols-1 sm:grid-cols-3 gap-4 items-end" {
+                    div {
+                        label for="from" class="block text-sm font-medium text-gray-700" { "From" }
+                        input type="date" id="from" name="from" value=(year_start) class=(INPUT) required {}
+                    }
+                    div {
+                        label for="to" class="block text-sm font-medium text-gray-700" { "To" }
+                        input type="date" id="to" name="to" value=(today) class=(INPUT) required {}
+                    }
+                    button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Download" }
+                }
+            }
+        }
+    }
+}
+
+fn status_text(status: EntryStatus) -> &'static str {
+    match status {
+        EntryStatus::Pending => "Awaiting approval",
+        EntryStatus::Approved => "Approved",
+        EntryStatus::Rejected => "Not approved",
+    }
+}
+
+fn ledger_meta() -> Meta {
+    Meta::new("Your Volunteer Hours", "Your volunteer hours.")
+}
+
+fn ledger_page(ledger: &Ledger, markup: Markup) -> (Meta, Markup) {
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen py-10 px-4" {
+            div class="max-w-2xl mx-auto flex flex-col gap-4" {
+                h1 class="text-3xl font-bold text-center" { "Volunteer Hours" }
+                p class="text-center text-gray-700" { (ledger.name) " · " (ledger.email) }
+                (markup)
+            }
+        }
+    };
+    (ledger_meta(), content)
+}
+
+pub async fn ledger(State(s): State<ClientState>, Path(code): Path<String>) -> Response {
+    let Some(ledger) = find_ledger(&s, &code).await else {
+        return crate::not_found().await.into_response();
+    };
+    let markup = ledger_markup(&s, &ledger, &code, None).await;
+    ledger_page(&ledger, markup).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportForm {
+    event: String,
+    hours: f64,
+    #[serde(default)]
+    note: String,
+}
+
+pub async fn report(
+    State(s): State<ClientState>,
+    Path(code): Path<String>,
+    Form(form): Form<ReportForm>,
+) -> Response {
+    let Some(ledger) = find_ledger(&s, &code).await else {
+        return crate::not_found().await.into_response();
+    };
+    let outcome = record(&s, &ledger, form).await;
+    let markup = ledger_markup(
+        &s,
+        &ledger,
+        &code,
+        Some(outcome.as_deref().map_err(String::as_str)),
+    )
+    .await;
+    (ledger_meta(), markup).into_response()
+}
+
+async fn record(s: &ClientState, ledger: &Ledger, form: ReportForm) -> Result<String, String> {
+    let events = s.events.get();
+    let today = today();
+    let Some(event) = reportable(&events.events, today)
+        .into_iter()
+        .find(|event| event.slug == form.event)
+    else {
+        return Err("Hours cannot be reported for that event.".to_string());
+    };
+    if !(form.hours > 0.0 && form.hours <= MAX_HOURS) {
+        return Err(format!("Please enter between 0.25 and {MAX_HOURS} hours."));
+    }
+    let existing = entries(s, ledger.id).await.map_err(|e| {
+        tracing::error!(ledger_id = %ledger.id, error = %e, "could not load volunteer hours");
+        "Sorry, we could not save your hours right now. Please try again later.".to_string()
+    })?;
+    if existing
+        .iter()
+        .any(|entry| entry.event == event.slug && entry.status != EntryStatus::Rejected)
+    {
+        return Err(format!(
+            "You have already reported hours for {}.",
+            event.title
+        ));
+    }
+    let entry = Entry {
+        id: ObjectId::new(),
+        ledger: ledger.id,
+        event: event.slug.clone(),
+        date: event.date,
+        hours: form.hours,
+        note: form.note.trim().to_string(),
+        status: EntryStatus::Pending,
+        created_at: DateTime::now(),
+        reviewed_at: None,
+    };
+    if let Err(e) = s.repo::<Entry>().insert(&entry).await {
+        tracing::error!(ledger_id = %ledger.id, error = %e, "could not save volunteer hours");
+        return Err(
+            "Sorry, we could not save your hours right now. Please try again later.".to_string(),
+        );
+    }
+    tracing::info!(ledger_id = %ledger.id, entry_id = %entry.id, "volunteer hours reported");
+    Ok(format!(
+        "Thank you! Your {} hours at {} will count once a coordinator approves them.",
+        hours(entry.hours),
+        event.title
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RangeQuery {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+/// The certificate already issued for `total` hours between the dates of
+/// `range`, or a newly recorded one. Downloading again, or a browser
+/// prefetching the link, hands out the same certificate; it only changes
+/// once more hours are approved.
+async fn issue(
+    s: &ClientState,
+    ledger: &Ledger,
+    range: &RangeQuery,
+    total: f64,
+) -> Result<Certificate, StoreError> {
+    let repo = s.repo::<Certificate>();
+    let existing = repo
+        .find(doc! { "ledger": ledger.id })
+        .await?
+        .into_iter()
+        .find(|certificate| {
+            certificate.from == range.from
+                && certificate.to == range.to
+                && certificate.hours == total
+        });
+    if let Some(certificate) = existing {
+        return Ok(certificate);
+    }
+    let issued = Certificate {
+        id: ObjectId::new(),
+        ledger: ledger.id,
+        name: ledger.name.clone(),
+        from: range.from,
+        to: range.to,
+        hours: total,
+        issued_at: DateTime::now(),
+    };
+    repo.insert(&issued).await?;
+    tracing::info!(ledger_id = %ledger.id, certificate_id = %issued.id, "certificate issued");
+    Ok(issued)
+}
+
+/// Issues a certificate for the approved hours between two dates.
+pub async fn certificate(
+    State(s): State<ClientState>,
+    Path(code): Path<String>,
+    Query(range): Query<RangeQuery>,
+) -> Response {
+    let Some(ledger) = find_ledger(&s, &code).await else {
+        return crate::not_found().await.into_response();
+    };
+    let problem = |message: &str| {
+        let markup = html! {
+            (error_fragment(message))
+            a href=(format!("/volunteer-hours/{code}")) class="text-blue-600 hover:underline text-center" { "Back to your hours" }
+        };
+        ledger_page(&ledger, markup).into_response()
+    };
+    if range.to < range.from {
+        return problem("The end date is before the start date.");
+    }
+    let entries = match entries(&s, ledger.id).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!(ledger_id = %ledger.id, error = %e, "could not load volunteer hours");
+            return problem("Your certificate is unavailable right now. Please try again later.");
+        }
+    };
+    let mut counted: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| {
+            entry.status == EntryStatus::Approved
+                && entry.date >= range.from
+                && entry.date <= range.to
+        })
+        .collect();
+    counted.sort_by_key(|entry| entry.date);
+    let total: f64 = counted.iter().map(|entry| entry.hours).sum();
+    if counted.is_empty() {
+        return problem("You have no approved hours between these dates.");
+    }
+
+    let issued = match issue(&s, &ledger, &range, total).await {
+        Ok(issued) => issued,
+        Err(e) => {
+            tracing::error!(ledger_id = %ledger.id, error = %e, "could not record certificate");
+            return problem("Your certificate is unavailable right now. Please try again later.");
+        }
+    };
+
+    let day = |date: NaiveDate| date.format("%B %-d, %Y").to_string();
+    let mut lines = vec![
+        Line::new("Certificate of Volunteer Service", 30.0).bold(),
+        Line::new("NJ Thiruvalluvar Tamil Sangam", 16.0).gap(14.0),
+        Line::new("This certifies that", 14.0).gap(36.0),
+        Line::new(&ledger.name, 28.0).bold().gap(16.0),
+        Line::new(
+            format!("contributed {} hours of volunteer service", hours(total)),
+            14.0,
+        )
+        .gap(16.0),
+        Line::new(
+            format!("between {} and {}, at:", day(range.from), day(range.to)),
+            14.0,
+        ),
+    ];
+    const LISTED: usize = 6;
+    for entry in counted.iter().take(LISTED) {
+        lines.push(Line::new(
+            format!(
+                "{} ({}, {} h)",
+                event_title(&s, &entry.event),
+                entry.date.format("%b %-d, %Y"),
+                hours(entry.hours)
+            ),
+            12.0,
+        ));
+    }
+    if counted.len() > LISTED {
+        lines.push(Line::new(
+            format!("and {} more events", counted.len() - LISTED),
+            12.0,
+        ));
+    }
+    let verify = format!(
+        "{}/certificates/{}",
+        s.config.site_url,
+        tickets::code(&s, Purpose::Certificate, issued.id)
+    );
+    lines.push(
+        Line::new(
+            format!(
+                "Issued {} and signed by NJ Thiruvalluvar Tamil Sangam.",
+                day(issued_on(&issued))
+            ),
+            10.0,
+        )
+        .gap(30.0),
+    );
+    lines.push(Line::new(format!("Verify at {verify}"), 10.0));
+    let pdf = pdf::page("Certificate of Volunteer Service", &lines);
+    (
+        [
+            (CONTENT_TYPE, "application/pdf".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"volunteer-hours-{}-{}.pdf\"",
+                    range.from, range.to
+                ),
+            ),
+        ],
+        pdf,
+    )
+        .into_response()
+}
+
+/// Public check of a certificate's code.
+pub async fn verify_page(State(s): State<ClientState>, Path(code): Path<String>) -> Response {
+    let Some(id) = tickets::verify(&s, Purpose::Certificate, &code) else {
+        return crate::not_found().await.into_response();
+    };
+    let certificate = match s.repo::<Certificate>().find_one(doc! { "_id": id }).await {
+        Ok(Some(certificate)) => certificate,
+        Ok(None) => return crate::not_found().await.into_response(),
+        Err(e) => {
+            tracing::error!(certificate_id = %id, error = %e, "could not load certificate");
+            return crate::not_found().await.into_response();
+        }
+    };
+    let day = |date: NaiveDate| date.format("%B %-d, %Y").to_string();
+    let meta = Meta::new(
+        "Certificate Check",
+        "Check a volunteer service certificate.",
+    );
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen py-10 px-4" {
+            div class="max-w-md mx-auto bg-white p-8 rounded-lg shadow-lg flex flex-col gap-3 text-center" {
+                h1 class="text-2xl font-bold text-green-800" { "Genuine certificate" }
+                p {
+                    "NJ Thiruvalluvar Tamil Sangam certifies that " strong { (certificate.name) }
+                    " contributed " strong { (hours(certificate.hours)) " hours" }
+                    " of volunteer service between " (day(certificate.from)) " and " (day(certificate.to)) "."
+                }
+                p class="text-sm text-gray-600" {
+                    "Issued " (day(issued_on(&certificate)))
+                }
+            }
+        }
+    };
+    (meta, content).into_response()
+}
+
+/// Pending reports and every volunteer's totals, for coordinators.
+async fn review_markup(s: &ClientState) -> Markup {
+    let (ledgers, entries) = match (
+        s.repo::<Ledger>().find(doc! {}).await,
+        s.repo::<Entry>().find(doc! {}).await,
+    ) {
+        (Ok(ledgers), Ok(entries)) => (ledgers, entries),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::error!(error = %e, "could not load volunteer hours");
+            return html! {
+                div id="hours" { (error_fragment("Could not load the volunteer hours.")) }
+            };
+        }
+    };
+    let mut pending: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| entry.status == EntryStatus::Pending)
+        .collect();
+    pending.sort_by_key(|entry| entry.created_at);
+    let owner = |id: ObjectId| ledgers.iter().find(|ledger| ledger.id == id);
+    let mut totals: Vec<(&Ledger, f64, f64)> = ledgers
+        .iter()
+        .map(|ledger| {
+            let sum = |status| {
+                entries
+                    .iter()
+                    .filter(|entry| entry.ledger == ledger.id && entry.status == status)
+                    .map(|entry| entry.hours)
+                    .sum::<f64>()
+            };
+            (
+                ledger,
+                sum(EntryStatus::Approved),
+                sum(EntryStatus::Pending),
+            )
+        })
+        .filter(|(_, approved, pending)| *approved > 0.0 || *pending > 0.0)
+        .collect();
+    totals.sort_by_key(|(ledger, _, _)| ledger.name.to_lowercase());
+    html! {
+        div id="hours" class="flex flex-col gap-6" {
+            section class="flex flex-col gap-3" {
+                h2 class="text-2xl font-semibold" { "Awaiting approval" }
+                @if pending.is_empty() {
+                    p { "Nothing to review." }
+                }
+                @for entry in &pending {
+                    div class="bg-white p-4 rounded-lg shadow flex flex-wrap justify-between items-center gap-4" {
+                        div {
+                            @if let Some(ledger) = owner(entry.ledger) {
+                                p class="font-semibold" { (ledger.name) " · " (ledger.email) }
+                            }
+                            p { (hours(entry.hours)) " hours at " (event_title(s, &entry.event)) ", " (entry.date.format("%b %-d, %Y")) }
+                            @if !entry.note.is_empty() {
+                                p class="text-sm text-gray-600" { (entry.note) }
+                            }
+                        }
+                        div class="flex gap-2" {
+                            @for (action, label, color) in [("approve", "Approve", "bg-green-600 hover:bg-green-700"), ("reject", "Reject", "bg-red-600 hover:bg-red-700")] {
+                                @let url = format!("/admin/hours/{}/{action}", entry.id);
+                                form method="post" action=(url) hx-post=(url) hx-target="#hours" hx-swap="outerHTML" {
+                                    button type="submit" class={ "px-3 py-1 rounded-md text-white " (color) } { (label) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            section class="flex flex-col gap-3" {
+                h2 class="text-2xl font-semibold" { "Totals" }
+                div class="overflow-x-auto bg-white rounded-lg shadow" {
+                    table class="min-w-full text-left text-sm" {
+                        thead class="bg-gray-100" {
+                            tr {
+                                th class="p-2" { "Volunteer" }
+                                th class="p-2" { "Email" }
+                                th class="p-2" { "Approved" }
+                                th class="p-2" { "Pending" }
+                            }
+                        }
+                        tbody {
+                            @for (ledger, approved, pending) in &totals {
+                                tr class="border-t" {
+                                    td class="p-2" { (ledger.name) }
+                                    td class="p-2" { (ledger.email) }
+                                    td class="p-2" { (hours(*approved)) }
+                                    td class="p-2" { (hours(*pending)) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn review_meta() -> Meta {
+    Meta::new("Volunteer Hours", "Review volunteer hours.")
+}
+
+fn review_page(markup: Markup) -> (Meta, Markup) {
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-4xl mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-6 text-center" { "Volunteer Hours" }
+                (markup)
+            }
+        }
+    };
+    (review_meta(), content)
+}
+
+pub async fn review(_: Organizer, State(s): State<ClientState>) -> (Meta, Markup) {
+    review_page(review_markup(&s).await)
+}
+
+pub async fn decide(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path((id, action)): Path<(ObjectId, String)>,
+) -> Response {
+    let status = match action.as_str() {
+        "approve" => EntryStatus::Approved,
+        "reject" => EntryStatus::Rejected,
+        _ => return crate::not_found().await.into_response(),
+    };
+    let result = s
+        .repo::<Entry>()
+        .update(
+            doc! { "_id": id, "status": EntryStatus::Pending.as_str() },
+            doc! { "status": status.as_str(), "reviewed_at": DateTime::now() },
+        )
+        .await;
+    match result {
+        Ok(_) => tracing::info!(entry_id = %id, status = ?status, "volunteer hours reviewed"),
+        Err(e) => tracing::error!(entry_id = %id, error = %e, "could not review volunteer hours"),
+    }
+    (review_meta(), review_markup(&s).await).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+
+    use super::*;
+    use crate::tests;
+
+    fn arun() -> Ledger {
+        Ledger {
+            id: ObjectId::new(),
+            email: "arun@example.com".to_string(),
+            name: "Arun".to_string(),
+            created_at: DateTime::now(),
+        }
+    }
+
+    /// Submits a form the way htmx does, so only the swapped part comes
+    /// back.
+    async fn htmx_post(s: &ClientState, uri: &str, form: &str, cookie: Option<&str>) -> String {
+        let mut request = Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header("hx-request", "true");
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        let response = tests::send(s, request.body(Body::from(form.to_string())).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        tests::text(response).await
+    }
+
+    fn approved(ledger: &Ledger, date: &str, hours: f64) -> Entry {
+        Entry {
+            id: ObjectId::new(),
+            ledger: ledger.id,
+            event: "pongal".to_string(),
+            date: date.parse().unwrap(),
+            hours,
+            note: String::new(),
+            status: EntryStatus::Approved,
+            created_at: DateTime::now(),
+            reviewed_at: Some(DateTime::now()),
+        }
+    }
+
+    async fn download(s: &ClientState, url: &str) {
+        let response = tests::get(s, url, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/pdf");
+    }
+
+    #[tokio::test]
+    async fn downloading_again_reuses_the_certificate() {
+        let s = tests::test_state();
+        let ledger = arun();
+        s.repo::<Ledger>().insert(&ledger).await.unwrap();
+        s.repo::<Entry>()
+            .insert(&approved(&ledger, "2025-03-29", 3.0))
+            .await
+            .unwrap();
+        let url = format!(
+            "/volunteer-hours/{}/certificate?from=2025-01-01&to=2025-12-31",
+            tickets::code(&s, Purpose::Ledger, ledger.id)
+        );
+
+        download(&s, &url).await;
+        download(&s, &url).await;
+        let issued = s.repo::<Certificate>().find(doc! {}).await.unwrap();
+        assert_eq!(issued.len(), 1);
+        assert_eq!(issued[0].hours, 3.0);
+
+        s.repo::<Entry>()
+            .insert(&approved(&ledger, "2025-06-01", 2.5))
+            .await
+            .unwrap();
+        download(&s, &url).await;
+        let issued = s.repo::<Certificate>().find(doc! {}).await.unwrap();
+        assert_eq!(
+            issued.len(),
+            2,
+            "more approved hours need a new certificate"
+        );
+        assert!(issued.iter().any(|certificate| certificate.hours == 5.5));
+    }
+
+    #[tokio::test]
+    async fn reporting_answers_with_the_ledger_alone() {
+        let s = tests::test_state();
+        let ledger = arun();
+        s.repo::<Ledger>().insert(&ledger).await.unwrap();
+        let uri = format!(
+            "/volunteer-hours/{}",
+            tickets::code(&s, Purpose::Ledger, ledger.id)
+        );
+        let fragment = htmx_post(&s, &uri, "event=no-such-event&hours=2", None).await;
+        assert!(fragment.starts_with(r#"<div id="ledger""#), "{fragment}");
+        assert!(fragment.contains("Hours cannot be reported for that event."));
+    }
+
+    #[tokio::test]
+    async fn reviewing_answers_with_the_review_alone() {
+        let s = tests::test_state();
+        let ledger = arun();
+        s.repo::<Ledger>().insert(&ledger).await.unwrap();
+        let entry = Entry {
+            status: EntryStatus::Pending,
+            reviewed_at: None,
+            ..approved(&ledger, "2025-03-29", 3.0)
+        };
+        s.repo::<Entry>().insert(&entry).await.unwrap();
+        let cookie = tests::organizer_cookie(&s).await;
+        let uri = format!("/admin/hours/{}/approve", entry.id);
+        let fragment = htmx_post(&s, &uri, "", Some(&cookie)).await;
+        assert!(fragment.starts_with(r#"<div id="hours""#), "{fragment}");
+        let reviewed = s.repo::<Entry>().find(doc! {}).await.unwrap();
+        assert_eq!(reviewed[0].status, EntryStatus::Approved);
+    }
+}
@@ -0,0 +1,399 @@
+//! Smaller copies of the site's photos so that phones do not download
+//! full-size originals. [`picture`] lists JPEG and WebP copies at a few
+//! widths under `/resized/<width>/<format>/<path>`; the first request for
+//! a copy scales the original in the static directory and keeps the result
+//! under `cache_dir`, and the copy is made again once the original changes.
+//! Copies of photos withheld for lack of consent are only served to
+//! organizers. [`spawn_warmup`] makes the copies of the site's known photos
+//! at startup so that visitors seldom wait for one.
+
+use std::{
+    io::{self, BufRead, Seek},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::SignedCookieJar;
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageDecoder, ImageReader,
+    ImageResult,
+};
+use maud::{html, Markup};
+
+use crate::{admin, consent, gallery, ClientState};
+
+/// Widths copies are made at, in pixels. Only these are served, so a
+/// visitor cannot make the server resize to arbitrary sizes.
+pub const WIDTHS: [u32; 3] = [400, 800, 1600];
+/// Width of the copy in `src`, for browsers that ignore `srcset`.
+const FALLBACK_WIDTH: u32 = 800;
+const JPEG_QUALITY: u8 = 80;
+const WEBP_QUALITY: f32 = 75.0;
+/// Copies only change when their original does, which is rare.
+const CACHE_CONTROL: &str = "public, max-age=86400";
+/// Gallery photos are withheld the moment consent is missing, and
+/// organizers are shown photos visitors are not, so neither browsers nor
+/// shared caches may reuse them without asking again.
+pub const GALLERY_CACHE_CONTROL: &str = "private, no-cache";
+/// The picture at the top of the home page.
+pub const HOME_BACKGROUND: &str = "/assets/img/home_bg.jpeg";
+/// Originals that can be resized.
+const EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Jpeg,
+    Webp,
+}
+
+impl Format {
+    fn parse(name: &str) -> Option<Format> {
+        match name {
+            "jpeg" => Some(Format::Jpeg),
+            "webp" => Some(Format::Webp),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Format::Jpeg => "jpeg",
+            Format::Webp => "webp",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Jpeg => "image/jpeg",
+            Format::Webp => "image/webp",
+        }
+    }
+}
+
+/// `path` with every segment percent-encoded, so that file names with
+/// spaces still load.
+pub fn encode_path(path: &str) -> String {
+    let mut encoded = String::new();
+    for (i, segment) in path.split('/').enumerate() {
+        if i > 0 {
+            encoded.push('/');
+        }
+        for byte in segment.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    encoded.push(byte as char)
+                }
+                _ => encoded.push_str(&format!("%{byte:02X}")),
+            }
+        }
+    }
+    encoded
+}
+
+/// `path` with percent-escapes decoded, or `None` when that is not UTF-8.
+pub fn decode_path(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Whether copies can be made of the file at `path`.
+pub fn is_resizable(path: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, extension)| {
+        EXTENSIONS
+            .iter()
+            .any(|known| extension.eq_ignore_ascii_case(known))
+    })
+}
+
+fn url(path: &str, width: u32, format: Format) -> String {
+    format!("/resized/{width}/{}/{}", format.as_str(), encode_path(path))
+}
+
+fn srcset(path: &str, format: Format) -> String {
+    WIDTHS
+        .iter()
+        .map(|&width| format!("{} {width}w", url(path, width, format)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A responsive `<picture>` for `src`, an address under `/assets`. `sizes`
+/// tells the browser how wide the image is shown so it can pick the
+/// smallest copy that fills it. Anything that cannot be resized, such as an
+/// image on another site, is shown as a plain `<img>`.
+pub fn picture(src: &str, alt: &str, sizes: &str, class: &str, lazy: bool) -> Markup {
+    let loading = if lazy { "lazy" } else { "eager" };
+    let Some(path) = src
+        .strip_prefix("/assets/")
+        .filter(|path| is_resizable(path))
+    else {
+        return html! {
+            img src=(src) alt=(alt) class=(class) loading=(loading);
+        };
+    };
+    html! {
+        picture {
+            source type="image/webp" srcset=(srcset(path, Format::Webp)) sizes=(sizes);
+            img src=(url(path, FALLBACK_WIDTH, Format::Jpeg)) srcset=(srcset(path, Format::Jpeg))
+                sizes=(sizes) alt=(alt) class=(class) loading=(loading);
+        }
+    }
+}
+
+/// Whether every segment of `path` is an ordinary name, so that joining it
+/// onto a directory stays inside that directory.
+fn is_safe(path: &str) -> bool {
+    path.split('/')
+        .all(|segment| !segment.is_empty() && !segment.starts_with('.') && !segment.contains('\\'))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Decodes an image the right way up. Phones store photos as the sensor
+/// saw them and record the rotation in EXIF, which re-encoding drops.
+pub fn decode<R: BufRead + Seek>(reader: ImageReader<R>) -> ImageResult<DynamicImage> {
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Scales `original` down to `width`, never up, and encodes it.
+fn resize(original: &Path, width: u32, format: Format) -> Result<Vec<u8>, String> {
+    let reader = ImageReader::open(original)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let image = decode(reader).map_err(|e| e.to_string())?;
+    let image = if image.width() > width {
+        image.resize(width, u32::MAX, FilterType::CatmullRom)
+    } else {
+        image
+    };
+    // Neither format is worth keeping transparency for in photos.
+    let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+    match format {
+        Format::Jpeg => {
+            let mut bytes = Vec::new();
+            JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)
+                .encode_image(&rgb)
+                .map_err(|e| e.to_string())?;
+            Ok(bytes)
+        }
+        Format::Webp => {
+            let encoder = webp::Encoder::from_rgb(rgb.as_bytes(), rgb.width(), rgb.height());
+            Ok(encoder.encode(WEBP_QUALITY).to_vec())
+        }
+    }
+}
+
+/// The cached copy, made first when it is missing or older than the
+/// original. `None` when there is no such original.
+fn cached(
+    static_dir: &Path,
+    cache_dir: &Path,
+    path: &str,
+    width: u32,
+    format: Format,
+) -> Result<Option<Vec<u8>>, String> {
+    let original = static_dir.join(path);
+    let Some(original_modified) = modified(&original).filter(|_| original.is_file()) else {
+        return Ok(None);
+    };
+    let copy: PathBuf = cache_dir
+        .join("resized")
+        .join(width.to_string())
+        .join(format.as_str())
+        .join(path);
+    if modified(&copy).is_some_and(|at| at >= original_modified) {
+        match std::fs::read(&copy) {
+            Ok(bytes) => return Ok(Some(bytes)),
+            Err(e) => tracing::warn!(path = %copy.display(), error = %e, "remaking resized photo"),
+        }
+    }
+
+    let bytes = resize(&original, width, format)?;
+    // Write beside the copy and rename, so a request racing this one never
+    // reads half a file.
+    let write = || -> io::Result<()> {
+        if let Some(dir) = copy.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let partial = copy.with_extension(format!("partial-{}", rand::random::<u32>()));
+        std::fs::write(&partial, &bytes)?;
+        std::fs::rename(&partial, &copy)
+    };
+    if let Err(e) = write() {
+        // Still serve the copy; the next request tries to cache it again.
+        tracing::error!(path = %copy.display(), error = %e, "could not cache resized photo");
+    } else {
+        tracing::info!(path = %copy.display(), "cached resized photo");
+    }
+    Ok(Some(bytes))
+}
+
+pub async fn resized(
+    State(s): State<ClientState>,
+    jar: SignedCookieJar,
+    UrlPath((width, format, path)): UrlPath<(u32, String, String)>,
+) -> Response {
+    let Some(format) = Format::parse(&format) else {
+        return crate::not_found().await.into_response();
+    };
+    if !WIDTHS.contains(&width) || !is_safe(&path) || !is_resizable(&path) {
+        return crate::not_found().await.into_response();
+    }
+    let photo = path.strip_prefix(&format!("{}/", gallery::DIR));
+    if let Some(photo) = photo {
+        if !admin::is_organizer(&jar, &s) && consent::is_withheld(&s, photo).await {
+            return crate::not_found().await.into_response();
+        }
+    }
+    let cache_control = match photo {
+        Some(_) => GALLERY_CACHE_CONTROL,
+        None => CACHE_CONTROL,
+    };
+    let static_dir = s.config.static_dir.clone();
+    let cache_dir = s.config.cache_dir.clone();
+    let task_path = path.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        cached(&static_dir, &cache_dir, &task_path, width, format)
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()));
+    match result {
+        Ok(Some(bytes)) => (
+            [
+                (header::CONTENT_TYPE, format.content_type()),
+                (header::CACHE_CONTROL, cache_control),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Ok(None) => crate::not_found().await.into_response(),
+        Err(e) => {
+            tracing::error!(path = %path, width, error = %e, "could not resize photo");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Makes every copy of the gallery photos, event posters and the home-page
+/// picture in the background, one at a time so visitors keep priority.
+pub fn spawn_warmup(s: ClientState) {
+    let events = s.events.get();
+    let mut sources: Vec<String> = vec![HOME_BACKGROUND.to_string()];
+    sources.extend(
+        events
+            .events
+            .iter()
+            .filter_map(|event| event.poster.clone()),
+    );
+    tokio::task::spawn_blocking(move || {
+        let static_dir = &s.config.static_dir;
+        let cache_dir = &s.config.cache_dir;
+        sources.extend(
+            gallery::scan(static_dir)
+                .into_iter()
+                .flat_map(|year| year.albums)
+                .flat_map(|album| album.photos)
+                .map(|photo| photo.asset()),
+        );
+        for source in &sources {
+            let Some(path) = source
+                .strip_prefix("/assets/")
+                .filter(|path| is_safe(path) && is_resizable(path))
+            else {
+                continue;
+            };
+            for width in WIDTHS {
+                for format in [Format::Webp, Format::Jpeg] {
+                    if let Err(e) = cached(static_dir, cache_dir, path, width, format) {
+                        tracing::error!(path = %path, width, error = %e, "could not resize photo");
+                    }
+                }
+            }
+        }
+        tracing::info!(photos = sources.len(), "resized photos are ready");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(decode_path("a%20b.jpg").as_deref(), Some("a b.jpg"));
+        assert_eq!(decode_path("%2e%2e/x.jpg").as_deref(), Some("../x.jpg"));
+        assert_eq!(decode_path("%2E%2E%2Fx.jpg").as_deref(), Some("../x.jpg"));
+        assert_eq!(decode_path("img%2fgallery").as_deref(), Some("img/gallery"));
+        assert_eq!(decode_path("a%5Cb").as_deref(), Some("a\\b"));
+        assert_eq!(decode_path("caf%C3%A9").as_deref(), Some("café"));
+        // Stray percent signs are kept as they are.
+        assert_eq!(decode_path("100%").as_deref(), Some("100%"));
+        assert_eq!(decode_path("%zz%2").as_deref(), Some("%zz%2"));
+        assert_eq!(decode_path("%FF"), None);
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let path = "img/gallery/2026/Pongal Fest/photo #1.jpg";
+        assert_eq!(decode_path(&encode_path(path)).as_deref(), Some(path));
+    }
+
+    #[test]
+    fn safe_checks_the_decoded_path() {
+        for path in ["%2e%2e/x.jpg", "img%2f..%2fx.jpg", "img%5C..%5Cx.jpg"] {
+            let decoded = decode_path(path).unwrap();
+            assert!(!is_safe(&decoded), "{path:?}");
+        }
+    }
+    #[test]
+    fn safe_paths_stay_inside_the_directory() {
+        assert!(is_safe("img/gallery/2026/fest/x.jpg"));
+        assert!(is_safe("img/photo.v2.jpg"));
+        for path in [
+            "",
+            "..",
+            "../x.jpg",
+            "img/../../x.jpg",
+            "img//x.jpg",
+            "/img/x.jpg",
+            "img/x.jpg/",
+            "./img/x.jpg",
+            "img/./x.jpg",
+            "img/.hidden.jpg",
+            "img\\..\\x.jpg",
+            "img/a\\b.jpg",
+        ] {
+            assert!(!is_safe(path), "{path:?}");
+        }
+    }
+}
@@ -1,19 +1,23 @@
 use axum::{extract::State, Form};
-use lettre::{
-    message::header::ContentType, transport::smtp::authentication::Credentials, Message,
-    SmtpTransport, Transport,
-};
+use lettre::message::Mailbox;
 use maud::{html, Markup};
-use mongodb::Collection;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     links::{EMAIL, PHONE, WHATSAPP_LINK},
+    outbox::{self, QueuedEmail},
+    page::error_fragment,
+    seo::Meta,
+    store::Record,
     ClientState,
 };
 
-pub async fn join_page() -> Markup {
-    html! {
+pub async fn join_page() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Join Us",
+        "Become a member of NJ Thiruvalluvar Tamil Sangam and hear about upcoming events.",
+    );
+    let content = html! {
         div class="bg-vertical-to-pink"{
         div class="max-w-7xl mx-auto p-8" {
             h1 class="text-3xl font-bold mb-6 text-center" { "Join Our Community" }
@@ -92,7 +96,8 @@ pub async fn join_page() -> Markup {
             }
         }
         }
-    }
+    };
+    (meta, content)
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JoinFormData {
@@ -103,40 +108,32 @@ pub struct JoinFormData {
     #[serde(default)]
     agree_emails: String,
 }
+impl Record for JoinFormData {
+    const COLLECTION: &'static str = "users";
+}
 
 pub async fn join_response(State(s): State<ClientState>, Form(data): Form<JoinFormData>) -> Markup {
-    let smtp_username = std::env::var("SMTP_USERNAME").expect("SMTP_USERNAME must be set");
-    let smtp_password = std::env::var("SMTP_PASSWORD").expect("SMTP_PASSWORD must be set");
-    let smtp_server = std::env::var("SMTP_SERVER").expect("SMTP_SERVER must be set");
-    let email = Message::builder()
-        .from(smtp_username.parse().unwrap())
-        .to(data.email.parse().unwrap())
-        .subject(format!("Contact from {}", data.email))
-        .header(ContentType::TEXT_PLAIN)
-        .body("Thanks for joining njtts".to_string())
-        .unwrap();
-
-    let creds = Credentials::new(smtp_username.to_owned(), smtp_password.to_owned());
-
-    // Open a remote connection to gmail
-    let mailer = SmtpTransport::relay(smtp_server.as_str())
-        .unwrap()
-        .credentials(creds)
-        .build();
-
-    // Send the email
-    match mailer.send(&email) {
-        Ok(_) => println!("Email sent successfully!"),
-        Err(e) => panic!("Could not send email: {e:?}"),
-    }
-    // Insert data into MongoDB
-    let db = s.client.database("tts"); // replace with your DB name
-    let collection: Collection<JoinFormData> = db.collection("users"); // replace with your collection name
-
-    match collection.insert_one(data.clone()).await {
-        Ok(_) => println!("Form data inserted successfully!"),
-        Err(e) => eprintln!("Failed to insert form data: {:?}", e),
+    let Ok(to) = data.email.parse::<Mailbox>() else {
+        tracing::info!("join form rejected: invalid email address");
+        return error_fragment(
+            "That email address does not look right. Please check it and try again.",
+        );
     };
+    if let Err(e) = s.repo::<JoinFormData>().insert(&data).await {
+        tracing::error!(error = %e, "could not save join form");
+        return error_fragment(&format!(
+            "Sorry, we could not complete your request right now. Please try again later or call us at {PHONE}."
+        ));
+    }
+    tracing::info!("join form saved");
+    let email = QueuedEmail::new(
+        &to,
+        format!("Contact from {}", data.email),
+        "Thanks for joining njtts",
+    );
+    if let Err(e) = outbox::enqueue(&s, email).await {
+        tracing::error!(error = %e, "could not queue welcome email");
+    }
 
     html! {
             div {
@@ -148,3 +145,67 @@ pub async fn join_response(State(s): State<ClientState>, Form(data): Form<JoinFo
             }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mongodb::bson::doc;
+
+    use super::*;
+    use crate::{mail::MemoryMailer, tests};
+
+    fn form(email: &str) -> Form<JoinFormData> {
+        Form(JoinFormData {
+            first_name: "Kavya".to_string(),
+            last_name: "R".to_string(),
+            email: email.to_string(),
+            phone: "555".to_string(),
+            agree_emails: String::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn joining_saves_the_member_and_emails_them() {
+        let mailer = Arc::new(MemoryMailer::new());
+        let s = ClientState {
+            mailer: mailer.clone(),
+            ..tests::test_state()
+        };
+        let page = join_response(State(s.clone()), form("kavya@example.com")).await;
+        assert!(page
+            .into_string()
+            .contains("Thank you for joining us, Kavya!"));
+
+        let saved = s.repo::<JoinFormData>().find(doc! {}).await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].email, "kavya@example.com");
+
+        assert!(mailer.sent().is_empty(), "email waits in the outbox");
+        outbox::deliver_due(&s).await.unwrap();
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        let to: Vec<String> = sent[0]
+            .envelope()
+            .to()
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(to, ["kavya@example.com"]);
+    }
+
+    #[tokio::test]
+    async fn a_bad_address_is_rejected_without_saving() {
+        let s = tests::test_state();
+        let page = join_response(State(s.clone()), form("not-an-address")).await;
+        assert!(page
+            .into_string()
+            .contains("That email address does not look right"));
+        assert!(s
+            .repo::<JoinFormData>()
+            .find(doc! {})
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+//! Outgoing email. Handlers build a [`Message`] and hand it to [`send`],
+//! which runs the configured [`Mailer`] on the blocking thread pool so a slow
+//! relay never stalls the async runtime.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use lettre::{
+    transport::{file::FileTransport, smtp::authentication::Credentials},
+    Message, SmtpTransport, Transport,
+};
+
+use crate::config::{MailConfig, MailTransport};
+
+#[derive(Debug)]
+pub enum MailError {
+    Address(lettre::address::AddressError),
+    Build(lettre::error::Error),
+    Smtp(lettre::transport::smtp::Error),
+    File(lettre::transport::file::Error),
+    Io(std::io::Error),
+    Join(tokio::task::JoinError),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::Address(e) => write!(f, "invalid email address: {e}"),
+            MailError::Build(e) => write!(f, "could not build email: {e}"),
+            MailError::Smtp(e) => write!(f, "SMTP delivery failed: {e}"),
+            MailError::File(e) => write!(f, "could not write email file: {e}"),
+            MailError::Io(e) => write!(f, "{e}"),
+            MailError::Join(e) => write!(f, "mail task failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+impl From<lettre::address::AddressError> for MailError {
+    fn from(e: lettre::address::AddressError) -> Self {
+        MailError::Address(e)
+    }
+}
+
+impl From<lettre::error::Error> for MailError {
+    fn from(e: lettre::error::Error) -> Self {
+        MailError::Build(e)
+    }
+}
+
+/// A blocking email transport.
+pub trait Mailer: Send + Sync {
+    fn send(&self, message: &Message) -> Result<(), MailError>;
+
+    /// Checks that messages could be delivered right now.
+    fn check(&self) -> Result<(), MailError> {
+        Ok(())
+    }
+}
+
+/// Delivers through an SMTP relay.
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+}
+
+impl SmtpMailer {
+    pub fn new(server: &str, username: &str, password: &str) -> Result<SmtpMailer, MailError> {
+        let creds = Credentials::new(username.to_owned(), password.to_owned());
+        let transport = SmtpTransport::relay(server)
+            .map_err(MailError::Smtp)?
+            .credentials(creds)
+            .build();
+        Ok(SmtpMailer { transport })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, message: &Message) -> Result<(), MailError> {
+        self.transport.send(message).map_err(MailError::Smtp)?;
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), MailError> {
+        match self.transport.test_connection() {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(MailError::Io(std::io::Error::other(
+                "SMTP server did not answer NOOP",
+            ))),
+            Err(e) => Err(MailError::Smtp(e)),
+        }
+    }
+}
+
+/// Writes every message as an `.eml` file into a directory instead of
+/// sending it.
+pub struct FileMailer {
+    transport: FileTransport,
+}
+
+impl FileMailer {
+    pub fn new(dir: &std::path::Path) -> Result<FileMailer, MailError> {
+        std::fs::create_dir_all(dir).map_err(MailError::Io)?;
+        Ok(FileMailer {
+            transport: FileTransport::new(dir),
+        })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, message: &Message) -> Result<(), MailError> {
+        self.transport.send(message).map_err(MailError::File)?;
+        Ok(())
+    }
+}
+
+/// Keeps sent messages in memory.
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<Message>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> MemoryMailer {
+        MemoryMailer::default()
+    }
+
+    /// Messages sent so far, oldest first.
+    #[cfg(test)]
+    pub fn sent(&self) -> Vec<Message> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    fn send(&self, message: &Message) -> Result<(), MailError> {
+        self.sent.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+pub fn from_config(config: &MailConfig) -> Result<Arc<dyn Mailer>, MailError> {
+    Ok(match &config.transport {
+        MailTransport::Smtp(smtp) => Arc::new(SmtpMailer::new(
+            &smtp.server,
+            &smtp.username,
+            &smtp.password,
+        )?),
+        MailTransport::File(dir) => Arc::new(FileMailer::new(dir)?),
+        MailTransport::Memory => Arc::new(MemoryMailer::new()),
+    })
+}
+
+/// Sends `message` without blocking the async runtime.
+pub async fn send(mailer: Arc<dyn Mailer>, message: Message) -> Result<(), MailError> {
+    tokio::task::spawn_blocking(move || mailer.send(&message))
+        .await
+        .map_err(MailError::Join)?
+}
+
+/// Runs [`Mailer::check`] without blocking the async runtime.
+pub async fn check(mailer: Arc<dyn Mailer>) -> Result<(), MailError> {
+    tokio::task::spawn_blocking(move || mailer.check())
+        .await
+        .map_err(MailError::Join)?
+}
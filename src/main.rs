@@ -2,136 +2,304 @@ use std::sync::Arc;
 
 use axum::{
     extract::State,
+    http::StatusCode,
+    middleware,
+    response::Redirect,
     routing::{get, post},
-    Form, Router,
+    Router,
 };
+use axum_extra::extract::cookie::Key;
+use config::{Config, StorageConfig};
 use dotenv::dotenv;
-use links::{EMAIL, FACEBOOK_LINK, INSTAGRAM_LINK, PHONE, PHONE_LINK, WHATSAPP_LINK, YOUTUBE_LINK};
-use maud::{html, Markup, PreEscaped, DOCTYPE};
-use mongodb::{bson::Document, options::ClientOptions, Client};
-use tokio::net::TcpListener;
-use tower_http::{add_extension::AddExtensionLayer, services::ServeDir};
+use maud::{html, Markup, PreEscaped};
+use mongodb::bson::DateTime;
+use tokio::{
+    net::TcpListener,
+    signal,
+    sync::{Mutex, Notify},
+};
+use tower::ServiceBuilder;
+use tower_http::services::ServeDir;
 
 mod about;
+mod admin;
+mod announcements;
+mod calendar;
 mod club;
+mod config;
+mod consent;
+mod content;
+mod events;
 mod gallery;
+mod health;
+mod hours;
+mod images;
 mod join;
 mod links;
+mod logging;
+mod mail;
+mod meetups;
+mod nav;
+mod outbox;
 mod page;
+mod pdf;
+mod photos;
+mod redirects;
+mod registration;
+mod schedule;
+mod seo;
+mod store;
 mod strings;
 mod tamil_school;
+mod tickets;
+mod volunteers;
 use about::*;
+use admin::admin_router;
+use announcements::AnnouncementsFile;
+use calendar::calendar_router;
 use club::*;
+use events::{event_page, events_page, EventsFile};
 use gallery::*;
+use health::health_router;
 use join::*;
+use mail::Mailer;
+use meetups::MeetupsFile;
+use redirects::RedirectsFile;
+use seo::{seo_router, Meta};
+use store::{JournaledStore, MemoryStore, MongoStore, Record, Repository, Store};
 use tamil_school::*;
 #[derive(Clone)]
 pub struct ClientState {
-    client: Arc<Client>,
+    store: Arc<dyn Store>,
+    mailer: Arc<dyn Mailer>,
+    /// Wakes the outbox worker when a message is queued.
+    outbox: Arc<Notify>,
+    cookie_key: Key,
+    config: Arc<Config>,
+    events: Arc<EventsFile>,
+    meetups: Arc<MeetupsFile>,
+    redirects: Arc<RedirectsFile>,
+    announcements: Arc<AnnouncementsFile>,
+    /// Held while an event registration or volunteer sign-up checks
+    /// capacity and is saved.
+    registration_lock: Arc<Mutex<()>>,
+    started_at: DateTime,
 }
-async fn connect_to_mongodb() -> mongodb::error::Result<Client> {
-    let uri = std::env::var("MONGODB_URI").expect("MONGODB_URI not set");
-    let mut client_options = ClientOptions::parse(&uri).await?;
-    client_options.app_name = Some("MyApp".to_string());
-    let client = Client::with_options(client_options)?;
-    Ok(client)
+impl ClientState {
+    pub fn repo<T: Record>(&self) -> Repository<T> {
+        Repository::new(self.store.clone())
+    }
 }
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    let client = connect_to_mongodb()
-        .await
-        .expect("Failed to connect to MongoDB");
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    logging::init(&config.log);
+    let store: Arc<dyn Store> = match &config.storage {
+        StorageConfig::Mongo(mongo) => {
+            let mongo_store = Arc::new(MongoStore::new(&mongo.uri, &mongo.database));
+            let store = Arc::new(JournaledStore::new(mongo_store, mongo.journal.clone()));
+            store.spawn_monitor();
+            store
+        }
+        StorageConfig::Memory => Arc::new(MemoryStore::new()),
+    };
+    let mailer = match mail::from_config(&config.mail) {
+        Ok(mailer) => mailer,
+        Err(e) => {
+            tracing::error!("could not set up email delivery: {e}");
+            std::process::exit(2);
+        }
+    };
+    let cookie_key = match &config.secret_key {
+        Some(secret) => Key::derive_from(secret.as_bytes()),
+        None => {
+            tracing::warn!(
+                "secret_key is not set; organizers will be logged out and tickets invalidated on restart"
+            );
+            Key::generate()
+        }
+    };
+    let events = match EventsFile::load(config.content_dir.join(events::FILE_NAME)) {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::error!("could not load the events: {e}");
+            std::process::exit(2);
+        }
+    };
+    let meetups = match MeetupsFile::load(config.content_dir.join(meetups::FILE_NAME)) {
+        Ok(meetups) => meetups,
+        Err(e) => {
+            tracing::error!("could not load the meetups: {e}");
+            std::process::exit(2);
+        }
+    };
+    let redirects = match RedirectsFile::load(config.content_dir.join(redirects::FILE_NAME)) {
+        Ok(redirects) => redirects,
+        Err(e) => {
+            tracing::error!("could not load the redirects: {e}");
+            std::process::exit(2);
+        }
+    };
+    let announcements =
+        match AnnouncementsFile::load(config.content_dir.join(announcements::FILE_NAME)) {
+            Ok(announcements) => announcements,
+            Err(e) => {
+                tracing::error!("could not load the announcements: {e}");
+                std::process::exit(2);
+            }
+        };
     let client_state = ClientState {
-        client: Arc::new(client),
+        store,
+        mailer,
+        outbox: Arc::new(Notify::new()),
+        cookie_key,
+        config: Arc::new(config),
+        events: Arc::new(events),
+        meetups: Arc::new(meetups),
+        redirects: Arc::new(redirects),
+        announcements: Arc::new(announcements),
+        registration_lock: Arc::new(Mutex::new(())),
+        started_at: DateTime::now(),
     };
+    outbox::spawn_worker(client_state.clone());
+    volunteers::spawn_reminders(client_state.clone());
+    images::spawn_warmup(client_state.clone());
 
-    let serve_dir = ServeDir::new("src/static");
-
-    let app = Router::new()
-        .nest_service("/assets", serve_dir)
-        .nest("/about", about_router())
-        .route("/", get(index))
-        .route("/navbar", get(navbar))
-        .route("/home", get(home))
-        .route("/byLaw", get(under_construction))
-        .route("/events", get(events_page))
-        .route("/gallery", get(gallery_page))
-        .route("/hiking_club", get(hiking_page))
-        .route("/walking_club", get(walking_page))
-        .route("/running_club", get(running_page))
-        .route("/vattam", get(vattam_page))
-        .route("/tamil_school", get(tamil_school_page))
-        .route("/enrollment_guide", get(enrollment_guide))
-        .route("/join", get(join_page))
-        .route("/join_response", post(join_response))
-        .route("/sponsors", get(under_construction))
-        .route("/tny25", get(newyear_redirect))
-        .route("/tny25S", get(pgmSchedule_redirect))
-        .route("/library", get(under_construction))
-        .route("/faq", get(under_construction))
-        .with_state(client_state)
-        .fallback(not_found);
-
-    let listener = TcpListener::bind("0.0.0.0:3300").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let bind = client_state.config.bind;
+    let app = app(client_state);
+
+    let listener = TcpListener::bind(bind).await.unwrap();
+    tracing::info!("listening on {bind}");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
 }
-pub async fn newyear_redirect() -> Markup {
-    html! {
-        (DOCTYPE)
-        html lang="en" {
-            head {
-                meta charset="UTF-8";
-                //meta http-equiv="refresh" content="0; url=https://forms.gle/bkuLehsKS1Y5Gfrv5";
-                meta http-equiv="refresh" content="0; url=https://forms.gle/BFTQZzQ8B19hvseJ8";
-                title { "Redirecting..." }
-            }
-            body {
-                p {
-                    "If you are not redirected, "
-                   // a href="https://forms.gle/bkuLehsKS1Y5Gfrv5" { "click here" }
-                    a href="https://forms.gle/BFTQZzQ8B19hvseJ8" { "click here" }
-                    "."
-                }
-            }
-        }
-    }
+
+/// All routes, wrapped in the site-wide middleware.
+fn app(client_state: ClientState) -> Router {
+    logging::layer(
+        Router::new()
+            .nest_service(
+                "/assets",
+                ServiceBuilder::new()
+                    .layer(middleware::from_fn_with_state(
+                        client_state.clone(),
+                        consent::guard_assets,
+                    ))
+                    .service(ServeDir::new(&client_state.config.static_dir)),
+            )
+            .nest("/about", about_router())
+            .nest("/admin", admin_router())
+            .merge(health_router())
+            .merge(seo_router())
+            .merge(calendar_router())
+            .route("/", get(home))
+            .route("/home", get(home))
+            .route("/announcements/next", get(announcements::next))
+            .route("/byLaw", get(|| async { Redirect::permanent("/about/bylaw") }))
+            .route("/events", get(events_page))
+            .route("/events/:slug", get(event_page))
+            .route("/events/:slug/register", post(registration::register))
+            .route("/events/:slug/volunteer", post(volunteers::signup))
+            .route("/events/:slug/schedule", get(schedule::schedule_page))
+            .route("/events/:slug/schedule/live", get(schedule::live))
+            .route("/tickets/:code", get(tickets::ticket_page))
+            .route(
+                "/volunteer-hours",
+                get(hours::start_page).post(hours::send_link),
+            )
+            .route(
+                "/volunteer-hours/:code",
+                get(hours::ledger).post(hours::report),
+            )
+            .route("/volunteer-hours/:code/certificate", get(hours::certificate))
+            .route("/certificates/:code", get(hours::verify_page))
+            .route("/gallery", get(gallery_page))
+            .route(
+                "/photo-consent",
+                get(consent::start_page).post(consent::send_link),
+            )
+            .route("/photo-consent/:code", get(consent::family))
+            .route("/photo-consent/:code/members", post(consent::add_member))
+            .route(
+                "/photo-consent/:code/members/:id",
+                post(consent::set_consent),
+            )
+            .route(
+                "/photo-consent/:code/members/:id/delete",
+                post(consent::remove_member),
+            )
+            .route("/resized/:width/:format/*path", get(images::resized))
+            .route("/hiking_club", get(hiking_page))
+            .route("/walking_club", get(walking_page))
+            .route("/running_club", get(running_page))
+            .route("/vattam", get(vattam_page))
+            .route("/tamil_school", get(tamil_school_page))
+            .route("/enrollment_guide", get(enrollment_guide))
+            .route("/join", get(join_page))
+            .route("/join_response", post(join_response))
+            .route("/sponsors", get(under_construction))
+            .route("/library", get(under_construction))
+            .route("/faq", get(under_construction))
+            .fallback(redirects::follow)
+            .with_state(client_state.clone())
+            .layer(middleware::from_fn_with_state(
+                client_state,
+                page::full_page,
+            )),
+    )
 }
 
-pub async fn pgmSchedule_redirect() -> Markup {
-    html! {
-        (DOCTYPE)
-        html lang="en" {
-            head {
-                meta charset="UTF-8";
-                //meta http-equiv="refresh" content="0; url=https://forms.gle/bkuLehsKS1Y5Gfrv5";
-                meta http-equiv="refresh" content="0; url=https://docs.google.com/spreadsheets/d/1SVuRdgiWkNfRw4NV_sUKw2kniT98Iulw/edit?usp=sharing&ouid=102627512621447650689&rtpof=true&sd=true";
-                title { "Redirecting..." }
-            }
-            body {
-                p {
-                    "If you are not redirected, "
-                   // a href="https://forms.gle/bkuLehsKS1Y5Gfrv5" { "click here" }
-                    a href="https://docs.google.com/spreadsheets/d/1SVuRdgiWkNfRw4NV_sUKw2kniT98Iulw/edit?usp=sharing&ouid=102627512621447650689&rtpof=true&sd=true" { "click here" }
-                    "."
-                }
-            }
-        }
+/// Resolves on Ctrl+C or SIGTERM. The server then stops accepting
+/// connections and waits for in-flight requests to finish.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c().await.expect("failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
     }
+    tracing::info!("shutting down, finishing in-flight requests");
 }
-
-pub async fn vattam_page() -> Markup {
-    html! {
-        section class="bg-cover bg-center bg-no-repeat bg-fixed" style="background-image: url('assets/img/vasagar_vattam_bg.jpeg');" {
+pub async fn vattam_page(State(s): State<ClientState>) -> (Meta, Markup) {
+    let meetups = s.meetups.get();
+    let circle = meetups.on_page("/vattam").next();
+    let meta = Meta::new(
+        "NJ Vasagar Vattam",
+        "NJ Vasagar Vattam, the Tamil reading circle of NJ Thiruvalluvar Tamil Sangam.",
+    );
+    let content = html! {
+        section class="bg-cover bg-center bg-no-repeat bg-fixed" style="background-image: url('/assets/img/vasagar_vattam_bg.jpeg');" {
             div class="bg-white bg-opacity-90 py-8 lg:py-16 px-4 mx-auto max-w-screen-md relative z-5" {
                 h2 class="mb-4 text-center text-4xl tracking-tight font-extrabold text-gray-900" { "Welcome to NJ Vasagar Vattam" }
 
                 p class="mb-8 lg:mb-16 font-light text-center sm:text-xl" {
-                    "Since January 21, 2024, our members have gathered every Wednesday to explore Tamil short stories through virtual discussions. But is reading storybooks necessary? As GK Chesterton aptly put it in 1901, \"Literature is a luxury; Fiction is a necessity.\""
+                    @if let Some(circle) = circle {
+                        "Since " (circle.since.format("%B %-d, %Y")) ", our members have gathered every " (circle.weekday_name()) " to explore Tamil short stories through virtual discussions. "
+                    }
+                    "But is reading storybooks necessary? As GK Chesterton aptly put it in 1901, \"Literature is a luxury; Fiction is a necessity.\""
                 }
 
                 div class="text-center mb-8" {
-                    img src="assets/img/books.jpeg" alt="QR Code" class="mx-auto mb-4" {}
+                    img src="/assets/img/books.jpeg" alt="QR Code" class="mx-auto mb-4" {}
                 }
 
                 p class="mb-8 lg:mb-16 font-light text-center sm:text-xl" {
@@ -143,255 +311,21 @@ pub async fn vattam_page() -> Markup {
                 }
 
                 div class="text-center mb-8" {
-                    img src="assets/img/vattam_w.jpeg" alt="QR Code" class="mx-auto mb-4" {}
+                    img src="/assets/img/vattam_w.jpeg" alt="QR Code" class="mx-auto mb-4" {}
                     a href="https://chat.whatsapp.com/FjyUCpSVjIQDv04xSnBAZc" target="_blank" rel="noopener noreferrer" class="text-green-500 hover:underline" { "Join Our Whatsapp Group!" }
                 }
 
+                (meetups::schedule_markup(&meetups, "/vattam"))
+
                 p class="mb-8 lg:mb-16 font-light text-center sm:text-xl" {
                     "If you wish to be part of our literary discussion, join us using the QR code above."
                 }
             }
         }
-    }
-}
-
-async fn events_page() -> Markup {
-    html! {
-        div class="min-h-screen flex items-center justify-center flex-col space-y-10 bg-vertical-to-pink text-center text-xl md:text-2xl lg:text-3xl" {
-            // Upcoming Events Section
-                h2 class="text-red-700 font-semibold" { "Upcoming Events" }
-             p class="text-sm md:text-base lg:text-lg xl:text-xl 2xl:text-2xl font-semibold cursor-pointer"{
-                                   a href="https://njtts.org/tny25" class="text-blue-500 underline"{"Click here to register for Tamil New Year 2025 Event"}
-            }
-             p class="text-sm md:text-base lg:text-lg xl:text-xl 2xl:text-2xl font-semibold cursor-pointer"{
-                                   a href="" class="text-blue-500 underline"{"TTS 2025 Event"
-                    br;
-                                   "Parambhariyavillaiyattu"
-                    br;
-
-                                   "Tamil New year"
-                    br;
-                                   "Science Fair"
-                    br;
-                                   "Father's Day"
-                    br;
-                                   "Camping"
-                    br;
-                                   "Diwali"
-                    br;
-                                   "PechuPotti"
-            }}
-
-              div  a href="https://njtts.org/tny25"  class="transition-transform transform hover:scale-105 relative" {
-                        img src="assets/img/posters/TTS-Tamil_New_Year_2025.jpg" class="w-full max-w-xs md:max-w-sm lg:max-w-md object-cover" alt="Tamil New Year 2025 poster" {}
-                    }
-
-
-           div class="w-full max-w-xs md:max-w-sm lg:max-w-md object-cover" {
-                        img src="assets/img/posters/TTS-Parambhariyavillaiyattu_2025.jpg" class="w-full h-full object-cover" alt="TTS-Parambhariyavillaiyattu_2025" {}}
-
-
-
-                }
-
-                // Add more events here as needed
-
-            // Past Events Section
-            div class="space-y-4 px-4" {
-                h2 class="text-red-700 font-semibold" { "Past Events - 2024" }
-                div class="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-3 gap-4 justify-center items-center"{
-                div class="flex flex-row space-x-4"{
-                            img src="assets/img/posters/fall_festival.jpg" class="w-full max-w-xs md:max-w-sm lg:max-w-md object-cover" alt="fall festival Poster" {}
-                    }
-             div class="flex flex-row space-x-4"{
-                            img src="assets/img/posters/Diwali-2024.jpg" class="w-full max-w-xs md:max-w-sm lg:max-w-md object-cover" alt="Diwali-2024" {}
-                    }
-                    div class="transition-transform transform hover:scale-105 relative aspect-w-3 aspect-h-4"{
-                        img src="assets/img/posters/camping.jpeg" class="w-full h-full object-cover" alt="Camping poster" {}
-                    }
-                    div class="transition-transform transform hover:scale-105 relative aspect-w-3 aspect-h-4"{
-                        img src="assets/img/posters/sciencefair.jpeg" class="w-full h-full object-cover" alt="Science Fair poster" {}
-                }
-
-                    div class="transition-transform transform hover:scale-105 relative aspect-w-3 aspect-h-4"{
-                        img src="assets/img/posters/fathersday.jpeg" class="w-full h-full object-cover" alt="Father's Day poster" {}
-            }
-
-
-                    a href="https://drive.google.com/file/d/1tbAjLiUVyjootpo2b6hAeE4RH9vDQWy4/view?ts=66bbd394" class="transition-transform transform hover:scale-105 relative aspect-w-3 aspect-h-4"{
-                        img src="assets/img/posters/villaiyattu.jpeg" class="w-full h-full object-cover" alt="Villaiyattu poster" {}
-                    }
-
-                    // Add more past events here as needed
-                }
-            }
-        }
-}
-
-
-async fn index() -> Markup {
-    let content = html! {
-
-        div class="bg-gray-50" id="page" hx-trigger="load" hx-get="/home"{}
     };
-    page::page(content)
+    (meta, content)
 }
 
-pub fn mobile_navbar() -> Markup {
-    html! {
-        div class="flex bg-pink-to-white md:hidden" {
-            div class = "flex flex-col item-center" {
-                    img src="assets/img/logo.jpg" class="h-28 w-28 rounded-full object-cover" alt="Logo" {}
-           // p class =  "text-Navy-500 font-extrabold" {"501(C)(3)-Non-Profit"}
-            }
-                    div class="flex flex-col py-2 text-sm"{
-                        div class="flex justify-center flex-grow"{
-
-
-                             div class="hover:text-blue-700 hover:underline px-4 py-2 rounded"
-                               hx-get="/home" hx-trigger="click" hx-target="#page" {
-                                 "Home"
-                             }
-                             div class=" hover:text-blue-700 hover:underline px-4 py-2 rounded"
-                               hx-get="/events" hx-trigger="click" hx-target="#page" {
-                                 "Events"
-                             }
-
-                              div class=" hover:text-blue-700 hover:underline px-4 py-2 rounded"
-                                hx-get="/gallery" hx-trigger="click" hx-target="#page" {
-                                  "Gallery"
-                              }
-
-
-
-                        }
-                        div class="flex justify-center flex-grow"{
-                             div class="relative"{
-                                 button type="button" class="dropdown-toggle py-2 px-3 hover:bg-gray-100 flex items-center gap-2 rounded"{
-                                     span class="select-none"{
-                                         "About"
-                                     }
-                                     svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-6"{
-                                       path stroke-linecap="round" stroke-linejoin="round" d="m19.5 8.25-7.5 7.5-7.5-7.5"{}
-                                     }
-                                 }
-                                 div class="hidden dropdown-menu absolute bg-gray-100 rounded-b-lg pb-2 w-48 flex flex-col z-30"{
-                                     div class="hover:text-blue-700 hover:underline px-4 py-2"
-                                       hx-get="/about/about" hx-trigger="click" hx-target="#page" {
-                                         "About Us"
-                                     }
-
-                                     div class="hover:text-blue-700 hover:underline px-4 py-2"
-                                       hx-get="/bylaw" hx-trigger="click" hx-target="#page" {
-                                         "Bylaw"
-                                     }
-                                     div class="hover:text-blue-700 px-4 py-2"
-                                       hx-get="/about/team" hx-trigger="click" hx-target="#page" {
-                                         "Our Team"
-                                     }
-                                     div class="hover:text-blue-700 px-4 py-2"
-                                       hx-get="/faq" hx-trigger="click" hx-target="#page" {
-                                         "FAQ's"
-                                     }
-                                     div class="hover:text-blue-700 px-4 py-2"
-                                       hx-get="/sponsors" hx-trigger="click" hx-target="#page" {
-                                         "Our Sponsors"
-                                     }
-
-
-
-                                     div class="hover:text-blue-700 px-4 py-2"
-                                       hx-get="/about/contact" hx-trigger="click" hx-target="#page" {
-                                         "Contact Us"
-                                     }
-
-                                 }
-                             }
-
-
-
-                             div class="relative"{
-
-                                 button type="button" class="dropdown-toggle py-2 px-3 hover:bg-gray-100 flex items-center gap-2 rounded"{
-                                     span class="select-none"{
-                                         "Community"
-                                     }
-                                     svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-6"{
-                                       path stroke-linecap="round" stroke-linejoin="round" d="m19.5 8.25-7.5 7.5-7.5-7.5"{}
-                                     }
-                                 }
-                                 div class="hidden dropdown-menu absolute bg-gray-100 rounded-b-lg pb-2 w-48 flex flex-col z-30"{
-
-                                     div class="hover:text-blue-700 hover:underline px-4 py-2"
-                                       hx-get="/vattam" hx-trigger="click" hx-target="#page" {
-                                         "NJ Vasagar Vattam"
-                                     }
-
-
-                                     div class="hover:text-blue-700 hover:underline px-4 py-2"
-                                       hx-get="/walking_club" hx-trigger="click" hx-target="#page" {
-                                         "Walking Club"
-                                     }
-                                     div class="hover:text-blue-700 hover:underline px-4 py-2"
-                                       hx-get="/hiking_club" hx-trigger="click" hx-target="#page" {
-                                         "Hiking Club"
-                                     }
-                                     div class="hover:text-blue-700 hover:underline px-4 py-2"
-                                       hx-get="/running_club" hx-trigger="click" hx-target="#page" {
-                                         "Running Club"
-                                     }
-                                     div class="hover:text-blue-700 hover:underline px-4 py-2"
-                                       hx-get="/tamil_school" hx-trigger="click" hx-target="#page" {
-                                         "NJ Tamil Schools"
-                                     }
-                                     div class="hover:text-blue-700 hover:underline px-4 py-2"
-                                       hx-get="/library" hx-trigger="click" hx-target="#page" {
-                                         "Tamil Library"
-                                     }
-
-
-                                 }
-
-                             }
-
-                             }
-
-
-
-
-
-                    }
-                    div class="flex-grow flex items-center justify-center"{
-                            a hx-get="/join" hx-trigger="click" hx-target="#page" class="text-white bg-orange-600 hover:bg-red-600 px-3 py-1 rounded-md text-sm font-small" {
-                                "Join Us"
-                            }
-                        }
-                }
-
-            div class="flex w-full items-center justify-between bg-orange-600 md:hidden text-white" {
-                div class="ml-3 flex space-x-4"{
-                    a href=(FACEBOOK_LINK) class="" { i class="fab fa-facebook" {} }
-                    a href=(INSTAGRAM_LINK) class="" { i class="fab fa-instagram" {} }
-                    a href=(WHATSAPP_LINK) class="" { i class="fab fa-whatsapp" {} }
-                        a href=(YOUTUBE_LINK) class="" { i class="fab fa-youtube" {} }
-
-
-
-                }
-                    // Email icon and clickable email link
-                    a href=(format!("mailto:{EMAIL}")) class="text-white hover:text-gray-200 flex items-center" {
-                        i class="fas fa-envelope mr-1" {}
-                        p class="text-sm" { (EMAIL) }
-                    }
-                    // Phone icon and clickable phone link
-                    a href=(PHONE_LINK) class="text-white hover:text-gray-200 flex items-center mr-2" {
-                        i class="fas fa-phone-alt mr-1" {}
-                        p class="text-sm" { (PHONE)}
-                    }
-
-            }
-    }
-}
 pub fn sponsors_markup() -> Markup {
     html! {
        // div class="flex flex-col items-center bg-vertical-to-white relative mx-[10%] my-[5%]" {
@@ -402,7 +336,7 @@ pub fn sponsors_markup() -> Markup {
                     div class="carousel-items flex transition-transform duration-4500 ease-in-out" id="carousel-items" {
                         // Repeat the following div for each sponsor image
                         div class="carousel-item min-w-full flex-shrink items-center" {
-                           // img src="assets/img/sponsor.jpg" class="w-full h-auto" alt="Sponsor 1" {}
+                           // img src="/assets/img/sponsor.jpg" class="w-full h-auto" alt="Sponsor 1" {}
                          /*p class="mb-2  text-xl tracking-tight font-extrabold animate-blink-color text-center" {
                                                      "1000$ - Gold Sponsors"
                            }
@@ -412,11 +346,11 @@ pub fn sponsors_markup() -> Markup {
                                        p class="mb-2  text-xl tracking-tight font-extrabold animate-blink-color text-center" {
                                                                    "500$(discounted price) - 2years"
                                          }*/
-                            img src="assets/img/Sponsors-Package.jpg" class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/Sponsors-Package.jpg" class="w-full h-auto" alt="Sponsor 2" {}
 
                         }
                         div class="carousel-item min-w-full flex-shrink items-center" {
-                            img src="assets/img/Trinity-sponsor1.jpg"   class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/Trinity-sponsor1.jpg"   class="w-full h-auto" alt="Sponsor 2" {}
                             //p class="text-sm md:text-base lg:text-lg xl:text-xl 50xl:text-50xl animate-blink-color text-center" { "Gold Sponsor" }
                          p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"{ " Gold Sponsor " }
                        //p class="mb-5 text-5l tracking-tight font-extrabold size=100 animate-blink-color text-center" { "Gold Sponsor" }
@@ -425,53 +359,53 @@ pub fn sponsors_markup() -> Markup {
                                                     // "Gold Sponsor"}
                         }
                         div class="carousel-item min-w-full flex-shrink items-center" {
-                            img src="assets/img/Finminds-sponsor.jpg" class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/Finminds-sponsor.jpg" class="w-full h-auto" alt="Sponsor 2" {}
                              // p class="mb-2  text-xxxl tracking-tight font-extrabold animate-blink-color text-center size=100"
                              p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"
                             {" Gold Sponsor "}
                         }
                          div class="carousel-item min-w-full flex-shrink items-center" {
-                            img src="assets/img/Gold_Sponsor_cricket.jpg" class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/Gold_Sponsor_cricket.jpg" class="w-full h-auto" alt="Sponsor 2" {}
                              // p class="mb-2  text-xxxl tracking-tight font-extrabold animate-blink-color text-center size=100"
                              p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"
                             {" Gold Sponsor "}
                         }
 
                          div class="carousel-item min-w-full flex-shrink items-center" {
-                            img src="assets/img/Financial-advisor-sponsor.jpg" class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/Financial-advisor-sponsor.jpg" class="w-full h-auto" alt="Sponsor 2" {}
                              // p class="mb-2  text-xxxl tracking-tight font-extrabold animate-blink-color text-center size=100"
                              p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"
                             {" Silver Sponsor "}
                         }
                         div class="carousel-item min-w-full flex-shrink items-center" {
-                            img src="assets/img/Veda-dentist.png" class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/Veda-dentist.png" class="w-full h-auto" alt="Sponsor 2" {}
                              // p class="mb-2  text-xxxl tracking-tight font-extrabold animate-blink-color text-center size=100"
                              p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"
                             {" Silver Sponsor "}
                         }
                          div class="carousel-item min-w-full flex-shrink items-center" {
-                            img src="assets/img/Penn-Drapes-Final.jpeg" class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/Penn-Drapes-Final.jpeg" class="w-full h-auto" alt="Sponsor 2" {}
                              // p class="mb-2  text-xxxl tracking-tight font-extrabold animate-blink-color text-center size=100"
                              p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"
                             {"Bronze Sponsor"}
                         }
 
                            div class="carousel-item min-w-full flex-shrink items-center" {
-                            img src="assets/img/VELCAB-Broonze-sponsor.jpg" class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/VELCAB-Broonze-sponsor.jpg" class="w-full h-auto" alt="Sponsor 2" {}
                              // p class="mb-2  text-xxxl tracking-tight font-extrabold animate-blink-color text-center size=100"
                              p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"
                             {"Bronze Sponsor"}
                         }
 
                         div class="carousel-item min-w-full flex-shrink items-center" {
-                            img src="assets/img/NMLS-sponsor.jpg" class="w-full h-auto" alt="Sponsor 2" {}
+                            img src="/assets/img/NMLS-sponsor.jpg" class="w-full h-auto" alt="Sponsor 2" {}
                              // p class="mb-2  text-xxxl tracking-tight font-extrabold animate-blink-color text-center size=100"
                              p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"
                             {"Bronze Sponsor"}
                         }
 
                           div class="carousel-item min-w-full flex-shrink items-center" {
-                           img src="assets/img/Rajini-Food-Partner.png" class="w-full h-auto" alt="Sponsor 2" {}
+                           img src="/assets/img/Rajini-Food-Partner.png" class="w-full h-auto" alt="Sponsor 2" {}
                              // p class="mb-2  text-xxxl tracking-tight font-extrabold animate-blink-color text-center size=100"
                              p class="text-lg md:text-2xl lg:text-5xl xl:text-5xl 100xl:text-100xl font-bold animate-blink-color text-center"
                             {"Food Partner"}
@@ -513,25 +447,36 @@ pub fn sponsors_markup() -> Markup {
     }
 }
 
-async fn under_construction() -> Markup {
-    html! {
+async fn under_construction() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Coming Soon",
+        "This page of the NJ Thiruvalluvar Tamil Sangam website is being prepared.",
+    );
+    let content = html! {
         div class="w-full flex flex-col items-center mt-8 bg-vertical-to-pink" {
             // h2 class="text-3xl font-bold text-center text-gray-800 mb-8" { "Annual Sponsors" }
-            // img src="assets/img/sponsor-collage.jpg" class="h-auto" alt="Sponsor Collage" {}
+            // img src="/assets/img/sponsor-collage.jpg" class="h-auto" alt="Sponsor Collage" {}
              // Image 1
-            a href="assets/img/under construction.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
-            img src="assets/img/under construction.jpg" alt="Photo 1" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
+            a href="/assets/img/under construction.jpg" target="_blank" rel="noopener noreferrer" class="group relative" {
+            img src="/assets/img/under construction.jpg" alt="Photo 1" class="rounded-lg object-cover w-full h-full transform transition-transform duration-300 group-hover:scale-105";
                                     }
         }
 
-    }
+    };
+    (meta, content)
 }
-async fn home() -> Markup {
-    html! {
+async fn home(State(s): State<ClientState>) -> (Meta, Markup) {
+    let meta = Meta::default();
+    let announcements = s.announcements.get();
+    let now = announcements::now();
+    let hero = announcements.hero(now);
+    let banner = announcements.banner(now);
+    let content = html! {
         div class="z-0 relative" {
             div class="w-full relative" {
 
-                img src="assets/img/home_bg.jpeg" class="w-full h-auto" alt="Background Image" {}
+                (images::picture(images::HOME_BACKGROUND, "Background Image", "100vw", "w-full h-auto", false))
+                @if let Some(hero) = hero {
                         div class=
                             "absolute bottom-5 left-1/2
                                 transform
@@ -542,226 +487,27 @@ async fn home() -> Markup {
                                 flex flex-col items-center justify-center text-center font-taviraj "
 
                             {
-
-                                p class="text-lg md:text-2xl lg:text-3xl xl:text-4xl 2xl:text-5xl font-bold animate-blink-color" {
-                                    "🎉  Tamil New year 🎉"
-                                }
-                                p class="text-lg md:text-2xl lg:text-3xl xl:text-4xl 2xl:text-5xl font-bold animate-blink-color" {
-                                    "29th Mar 2025"
-                                }
-                                p class="text-sm md:text-base lg:text-lg xl:text-xl 2xl:text-2xl font-semibold cursor-pointer"{
-                                    a href="https://njtts.org/tny25" class="text-blue-500 underline"{"Click here"} " to register"
-
-                                }
-
+                                (announcements::hero_markup(hero))
                         }
-
-
-            }
-
-            (sponsors_markup())
-        }
-        (PreEscaped(r##"
-        <style>
-          #notificationBanner {
-            transition: opacity 0.5s ease-in-out; /* Smooth opacity transition */
-          }
-        </style>
-        <script>
-        const events = [
-            '<div id="event" class="font-roboto sm:text-xl md:text-2xl lg:text-3xl">Tamil School Registration for the upcoming 2024-2025 year is now <a hx-get="/enrollment_guide" hx-trigger="click" hx-target="#page" class="text-blue-600 underline">open!</a></div>',
-
-            '<div id="event" class="font-roboto sm:text-xl md:text-2xl lg:text-3xl"><a class="text-blue-600 underline" hx-get="/events" hx-trigger="click" hx-target="#page" >TTS-Fall Festival</a>: Sep 15th at Smith Field Park, Parsippany</div>',
-
-        ];
-
-          let eventIndex = 0;
-
-          function updateBanner() {
-            const banner = document.getElementById('notificationBanner');
-            banner.style.opacity = 0; // Start fade out
-          setTimeout(() => {
-              banner.innerHTML = events[eventIndex];
-              banner.style.opacity = 1; // Fade in
-              eventIndex = (eventIndex + 1) % events.length;
-             htmx.process(document.getElementById('event'));
-
-            }, 500); // Wait for fade out to complete before changing text
-          }
-
-          setInterval(updateBanner, 5000); // Change event every 5 seconds
-          updateBanner(); // Initial update
-        </script>
-        "##))
-    }
-}
-async fn navbar() -> Markup {
-    html! {
-        nav id="navb" class="bg-pink-to-white p-4"{
-
-            div class="container mx-auto flex items-center justify-between gap-6"{
-                div class = "flex flex-col items-center" {
-                div class = "flex items-center gap-4"
-                    hx-get="/home" hx-trigger="click" hx-target="#page" {
-                        img src="assets/img/logo.jpg" class="h-28 w-28 rounded-full object-cover" alt="Logo" {}
-
-                }
-                   // p class =  "text-red-500" {"TTS – A Non-Profit 501(C)(3)"}
                 }
 
-               div class="flex justify-center flex-grow"{
-                    div class="hover:text-blue-700 hover:underline px-4 py-2 rounded"
-                      hx-get="/home" hx-trigger="click" hx-target="#page" {
-                        "Home"
-                    }
-                    div class="relative"{
-                        button type="button" class="dropdown-toggle py-2 px-3 hover:bg-gray-100 flex items-center gap-2 rounded"{
-                            span class="select-none"{
-                                "About"
-                            }
-                            svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-6"{
-                              path stroke-linecap="round" stroke-linejoin="round" d="m19.5 8.25-7.5 7.5-7.5-7.5"{}
-                            }
-                        }
-                        div class="hidden dropdown-menu absolute bg-gray-100 rounded-b-lg pb-2 w-48 flex flex-col z-30"{
-                            div class="hover:text-blue-700 hover:underline px-4 py-2"
-                              hx-get="/about/about" hx-trigger="click" hx-target="#page" {
-                                "About Us"
-                            }
-                            div class="hover:text-blue-700 hover:underline px-4 py-2"
-                              hx-get="/byLaw" hx-trigger="click" hx-target="#page" {
-                                "Bylaw"
-                            }
-                            div class="hover:text-blue-700 px-4 py-2"
-                              hx-get="/about/team" hx-trigger="click" hx-target="#page" {
-                                "Our Team"
-                            }
-                            div class="hover:text-blue-700 px-4 py-2"
-                              hx-get="/faq" hx-trigger="click" hx-target="#page" {
-                                "FAQ's"
-                            }
-                            div class="hover:text-blue-700 px-4 py-2"
-                              hx-get="/sponsors" hx-trigger="click" hx-target="#page" {
-                                "Our Sponsors"
-                            }
-                            div class="hover:text-blue-700 px-4 py-2"
-                              hx-get="/about/contact" hx-trigger="click" hx-target="#page" {
-                                "Contact Us"
-                            }
-                        }
-                    }
-
-
-                    div class=" hover:text-blue-700 hover:underline px-4 py-2 rounded"
-                      hx-get="/events" hx-trigger="click" hx-target="#page" {
-                        "Events"
-                    }
-                    div class=" hover:text-blue-700 hover:underline px-4 py-2 rounded"
-                      hx-get="/gallery" hx-trigger="click" hx-target="#page" {
-                        "Gallery"
-                    }
 
-                    div class="relative"{
-                        button type="button" class="dropdown-toggle py-2 px-3 hover:bg-gray-100 flex items-center gap-2 rounded"{
-                            span class="select-none"{
-                                "Community"
-                            }
-                            svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-6"{
-                              path stroke-linecap="round" stroke-linejoin="round" d="m19.5 8.25-7.5 7.5-7.5-7.5"{}
-                            }
-                        }
-                        div class="hidden dropdown-menu absolute bg-gray-100 rounded-b-lg pb-2 w-48 flex flex-col z-30"{
-
-                            div class="hover:text-blue-700 hover:underline px-4 py-2"
-                              hx-get="/vattam" hx-trigger="click" hx-target="#page" {
-                                "NJ Vasagar Vattam"
-                            }
-
-
-                            div class="hover:text-blue-700 hover:underline px-4 py-2"
-                              hx-get="/walking_club" hx-trigger="click" hx-target="#page" {
-                                "Walking Club"
-                            }
-                            div class="hover:text-blue-700 hover:underline px-4 py-2"
-                              hx-get="/hiking_club" hx-trigger="click" hx-target="#page" {
-                                "Hiking Club"
-                            }
-                            div class="hover:text-blue-700 hover:underline px-4 py-2"
-                              hx-get="/running_club" hx-trigger="click" hx-target="#page" {
-                                "Running Club"
-                            }
-                            div class="hover:text-blue-700 hover:underline px-4 py-2"
-                              hx-get="/tamil_school" hx-trigger="click" hx-target="#page" {
-                                "NJ Tamil Schools"
-                            }
-                            div class="hover:text-blue-700 hover:underline px-4 py-2"
-                              hx-get="/library" hx-trigger="click" hx-target="#page" {
-                                "Tamil Library"
-                            }
+            }
 
-                        }
+            (announcements::banner_markup(&banner, 0))
 
-                    }
-                    }
-                    div {
-                            div hx-get="/join" hx-trigger="click" hx-target="#page" class="text-white bg-orange-600 hover:bg-red-600 px-6 py-3 rounded-lg text-lg font-medium" {
-                                "Join Us"
-                            }
-                        }
-            }
+            (sponsors_markup())
         }
-        script {
-            (PreEscaped(r#"
-                // Select all dropdown toggle buttons
-                const dropdownToggles = document.querySelectorAll(".dropdown-toggle");
-
-                dropdownToggles.forEach((toggle) => {
-                    toggle.addEventListener("click", () => {
-                        // Find the next sibling element which is the dropdown menu
-                        const dropdownMenu = toggle.nextElementSibling;
-
-                        // Toggle the 'hidden' class to show or hide the dropdown menu
-                        if (dropdownMenu.classList.contains("hidden")) {
-                            // Hide any open dropdown menus before showing the new one
-                            document.querySelectorAll(".dropdown-menu").forEach((menu) => {
-                                menu.classList.add("hidden");
-                            });
-
-                            dropdownMenu.classList.remove("hidden");
-                        } else {
-                            dropdownMenu.classList.add("hidden");
-                        }
-                    });
-
-                    // Close the dropdown when a link inside it is clicked
-                    const links = toggle.nextElementSibling.querySelectorAll("div");
-                    links.forEach(link => {
-                        link.addEventListener("click", () => {
-                            toggle.nextElementSibling.classList.add("hidden");
-                        });
-                    });
-                });
-
-                // Clicking outside of an open dropdown menu closes it
-                window.addEventListener("click", function (e) {
-                    // Check if the clicked element or any of its ancestors contain the class 'dropdown-toggle'
-                    if (!e.target.closest(".dropdown-toggle")) {
-                        // Iterate through each dropdown menu
-                        document.querySelectorAll(".dropdown-menu").forEach((menu) => {
-                            // Check if the clicked element is not within the dropdown menu
-                            if (!menu.contains(e.target)) {
-                                // Hide the dropdown menu
-                                menu.classList.add("hidden");
-                            }
-                        });
-                    }
-                });
-            "#))
+        style {
+            // Fades the banner out while htmx swaps in the next announcement.
+            "#notificationBanner { transition: opacity 0.5s ease-in-out; }"
+            "#notificationBanner.htmx-swapping { opacity: 0; }"
         }
-    }
+    };
+    (meta, content)
 }
-async fn not_found() -> Markup {
-    html! {
+async fn not_found() -> (StatusCode, Markup) {
+    let page = html! {
         html lang="en" {
             head {
                 meta charset=(strings::UTF8);
@@ -775,5 +521,179 @@ async fn not_found() -> Markup {
             }
             (PreEscaped(strings::NOT_FOUND_COMMENT))
         }
+    };
+    (StatusCode::NOT_FOUND, page)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use axum::{
+        body::Body,
+        http::{header, Request},
+        response::Response,
+    };
+    use clap::Parser;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// The organizer password of [`test_state`].
+    pub(crate) const ADMIN_PASSWORD: &str = "test-password";
+
+    /// A site backed by memory storage and mail, with `args` added to its
+    /// command line.
+    pub(crate) fn test_state_with(args: &[&str]) -> ClientState {
+        let admin_password = format!("--admin-password={ADMIN_PASSWORD}");
+        let args = config::Args::parse_from(
+            [
+                "website",
+                "--storage=memory",
+                "--mail-transport=memory",
+                "--mail-from=info@njtts.org",
+                &admin_password,
+            ]
+            .iter()
+            .chain(args),
+        );
+        let config = Config::from_args(args).expect("test configuration is valid");
+        let events = EventsFile::load(config.content_dir.join(events::FILE_NAME))
+            .expect("content/events.toml is valid");
+        let meetups = MeetupsFile::load(config.content_dir.join(meetups::FILE_NAME))
+            .expect("content/meetups.toml is valid");
+        let redirects = RedirectsFile::load(config.content_dir.join(redirects::FILE_NAME))
+            .expect("content/redirects.toml is valid");
+        let announcements =
+            AnnouncementsFile::load(config.content_dir.join(announcements::FILE_NAME))
+                .expect("content/announcements.toml is valid");
+        ClientState {
+            store: Arc::new(MemoryStore::new()),
+            mailer: Arc::new(mail::MemoryMailer::new()),
+            outbox: Arc::new(Notify::new()),
+            cookie_key: Key::generate(),
+            config: Arc::new(config),
+            events: Arc::new(events),
+            meetups: Arc::new(meetups),
+            redirects: Arc::new(redirects),
+            announcements: Arc::new(announcements),
+            registration_lock: Arc::new(Mutex::new(())),
+            started_at: DateTime::now(),
+        }
+    }
+
+    pub(crate) fn test_state() -> ClientState {
+        test_state_with(&[])
+    }
+
+    /// `s` serving `toml` as its events file, written into `dir`.
+    pub(crate) fn with_events(s: ClientState, dir: &Path, toml: &str) -> ClientState {
+        let path = dir.join(events::FILE_NAME);
+        std::fs::write(&path, toml).unwrap();
+        let events = EventsFile::load(path).expect("test events are valid");
+        ClientState {
+            events: Arc::new(events),
+            ..s
+        }
+    }
+
+    /// Runs `request` through every route and middleware of the site.
+    pub(crate) async fn send(s: &ClientState, request: Request<Body>) -> Response {
+        app(s.clone()).oneshot(request).await.unwrap()
+    }
+
+    /// Fetches `uri`, sending `cookie` if given.
+    pub(crate) async fn get(s: &ClientState, uri: &str, cookie: Option<&str>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        send(s, request.body(Body::empty()).unwrap()).await
+    }
+
+    /// Submits a form the way a browser without htmx would.
+    pub(crate) async fn post_form(
+        s: &ClientState,
+        uri: &str,
+        form: &str,
+        cookie: Option<&str>,
+    ) -> Response {
+        let mut request =
+            Request::post(uri).header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        send(s, request.body(Body::from(form.to_string())).unwrap()).await
+    }
+
+    pub(crate) async fn text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    /// Logs in as an organizer and returns the session cookie.
+    pub(crate) async fn organizer_cookie(s: &ClientState) -> String {
+        let response = post_form(
+            s,
+            "/admin/login",
+            &format!("password={ADMIN_PASSWORD}"),
+            None,
+        )
+        .await;
+        let cookie = response
+            .headers()
+            .get(header::SET_COOKIE)
+            .expect("logging in sets the session cookie")
+            .to_str()
+            .unwrap();
+        cookie.split(';').next().unwrap().to_string()
+    }
+
+    /// Requests `target` the way a click in the menu does.
+    async fn click(s: &ClientState, target: &str, cookie: Option<&str>) -> Response {
+        let mut request = Request::get(target)
+            .header("hx-request", "true")
+            .header("hx-target", "page");
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        send(s, request.body(Body::empty()).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn every_menu_target_is_routed() {
+        let s = test_state();
+        let public = nav::targets(false);
+        for target in public.iter().chain(seo::EXTRA_PAGES) {
+            let status = click(&s, target, None).await.status();
+            assert!(
+                status.is_success(),
+                "menu links to {target}, which answers {status}"
+            );
+        }
+
+        let cookie = organizer_cookie(&s).await;
+        let organizer_only: Vec<&str> = nav::targets(true)
+            .into_iter()
+            .filter(|target| !public.contains(target))
+            .collect();
+        assert!(!organizer_only.is_empty());
+        for target in organizer_only {
+            let response = click(&s, target, None).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER, "{target}");
+            assert_eq!(
+                response.headers()[header::LOCATION],
+                "/admin/login",
+                "{target}"
+            );
+
+            let status = click(&s, target, Some(&cookie)).await.status();
+            assert!(
+                status.is_success(),
+                "menu links to {target}, which answers {status} to organizers"
+            );
+        }
     }
 }
@@ -0,0 +1,138 @@
+//! Weekly gatherings: the reading circle and the club meetups. They live in
+//! `meetups.toml` in the content directory and are shown on the page each
+//! one belongs to and published as a recurring calendar feed.
+
+use std::collections::HashSet;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use maud::{html, Markup};
+use serde::Deserialize;
+
+use crate::content::{Content, ContentFile};
+
+pub const FILE_NAME: &str = "meetups.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Meetup {
+    pub title: String,
+    /// Lowercase letters, digits and dashes; unique across all meetups.
+    pub slug: String,
+    /// Path of the page that describes the meetup.
+    pub page: String,
+    pub weekday: Weekday,
+    /// When the meetup started; need not fall on `weekday`.
+    pub since: NaiveDate,
+    pub until: Option<NaiveDate>,
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub location: Option<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Meetup {
+    /// Path of the meetup's calendar feed.
+    pub fn calendar_url(&self) -> String {
+        format!("/calendar/{}.ics", self.slug)
+    }
+
+    /// The first session: the first `weekday` on or after `since`.
+    pub fn first_session(&self) -> NaiveDate {
+        let offset = (7 + self.weekday.num_days_from_monday()
+            - self.since.weekday().num_days_from_monday())
+            % 7;
+        self.since + Duration::days(offset.into())
+    }
+
+    /// The weekday's name, e.g. "Wednesday".
+    pub fn weekday_name(&self) -> &'static str {
+        match self.weekday {
+            Weekday::Mon => "Monday",
+            Weekday::Tue => "Tuesday",
+            Weekday::Wed => "Wednesday",
+            Weekday::Thu => "Thursday",
+            Weekday::Fri => "Friday",
+            Weekday::Sat => "Saturday",
+            Weekday::Sun => "Sunday",
+        }
+    }
+
+    /// When it meets, e.g. "Every Wednesday, 8:00 PM – 9:00 PM".
+    pub fn schedule(&self) -> String {
+        let mut schedule = format!("Every {}", self.weekday_name());
+        if let Some(start) = self.start_time {
+            schedule.push_str(&format!(", {}", start.format("%-I:%M %p")));
+            if let Some(end) = self.end_time {
+                schedule.push_str(&format!(" – {}", end.format("%-I:%M %p")));
+            }
+        }
+        schedule
+    }
+}
+
+/// Layout of `meetups.toml`: a list of `[[meetup]]` tables.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Meetups {
+    #[serde(rename = "meetup", default)]
+    pub meetups: Vec<Meetup>,
+}
+
+impl Content for Meetups {
+    fn validate(&self) -> Result<(), String> {
+        let mut slugs = HashSet::new();
+        for meetup in &self.meetups {
+            let slug = &meetup.slug;
+            if slug.is_empty()
+                || !slug
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            {
+                return Err(format!(
+                    "slug {slug:?} may only contain lowercase letters, digits and dashes"
+                ));
+            }
+            if !slugs.insert(slug) {
+                return Err(format!("slug {slug:?} is used by more than one meetup"));
+            }
+            if meetup.until.is_some_and(|until| until < meetup.since) {
+                return Err(format!("meetup {slug:?} ends before it starts"));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub type MeetupsFile = ContentFile<Meetups>;
+
+impl Meetups {
+    pub fn find(&self, slug: &str) -> Option<&Meetup> {
+        self.meetups.iter().find(|meetup| meetup.slug == slug)
+    }
+
+    /// Meetups described on the page at `path`.
+    pub fn on_page<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a Meetup> {
+        self.meetups
+            .iter()
+            .filter(move |meetup| meetup.page == path)
+    }
+}
+
+/// When and where the meetups of one page take place, with a link to
+/// subscribe. Empty when the page has none.
+pub fn schedule_markup(meetups: &Meetups, path: &str) -> Markup {
+    html! {
+        @for meetup in meetups.on_page(path) {
+            div class="mb-8 p-4 rounded-lg bg-orange-50 border border-orange-200 text-center" {
+                p class="text-xl font-semibold text-gray-900" { (meetup.schedule()) }
+                @if let Some(location) = &meetup.location {
+                    p class="text-gray-700" { (location) }
+                }
+                a href=(meetup.calendar_url()) class="text-blue-600 hover:underline" {
+                    "Add to my calendar"
+                }
+            }
+        }
+    }
+}
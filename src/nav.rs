@@ -0,0 +1,254 @@
+//! The site menu. [`MENU`] is the only list of navigation targets; the
+//! desktop navbar and the mobile header both render it, highlighting the
+//! entry for the page being shown.
+
+use maud::{html, Markup, PreEscaped};
+
+/// Who an entry is shown to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Everyone,
+    /// Only logged-in organizers.
+    Organizers,
+}
+
+#[derive(Debug)]
+pub enum Target {
+    /// A route rendered into `#page`.
+    Page(&'static str),
+    /// A dropdown of further entries.
+    Menu(&'static [Entry]),
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub label: &'static str,
+    pub target: Target,
+    pub visibility: Visibility,
+}
+
+const fn page(label: &'static str, href: &'static str) -> Entry {
+    Entry {
+        label,
+        target: Target::Page(href),
+        visibility: Visibility::Everyone,
+    }
+}
+
+pub const MENU: &[Entry] = &[
+    page("Home", "/"),
+    Entry {
+        label: "About",
+        target: Target::Menu(&[
+            page("About Us", "/about/about"),
+            page("Bylaw", "/about/bylaw"),
+            page("Our Team", "/about/team"),
+            page("FAQ's", "/faq"),
+            page("Our Sponsors", "/sponsors"),
+            page("Contact Us", "/about/contact"),
+        ]),
+        visibility: Visibility::Everyone,
+    },
+    page("Events", "/events"),
+    page("Gallery", "/gallery"),
+    Entry {
+        label: "Community",
+        target: Target::Menu(&[
+            page("NJ Vasagar Vattam", "/vattam"),
+            page("Walking Club", "/walking_club"),
+            page("Hiking Club", "/hiking_club"),
+            page("Running Club", "/running_club"),
+            page("NJ Tamil Schools", "/tamil_school"),
+            page("Tamil Library", "/library"),
+        ]),
+        visibility: Visibility::Everyone,
+    },
+    Entry {
+        label: "Organizers",
+        target: Target::Menu(&[
+            page("Registrations", "/admin/registrations"),
+            page("Volunteers", "/admin/volunteers"),
+            page("Volunteer Hours", "/admin/hours"),
+            page("Program Schedules", "/admin/schedule"),
+            page("Add Photos", "/admin/photos"),
+            page("Short Links", "/admin/links"),
+            page("Failed Emails", "/admin/outbox"),
+        ]),
+        visibility: Visibility::Organizers,
+    },
+];
+
+/// The call to action shown next to the menu.
+pub const JOIN: Entry = page("Join Us", "/join");
+
+/// Every route the menu shows to organizers or, with `organizer` unset, to
+/// everyone else. Includes [`JOIN`].
+pub fn targets(organizer: bool) -> Vec<&'static str> {
+    fn collect(state: &NavState, entries: &'static [Entry], out: &mut Vec<&'static str>) {
+        for entry in entries.iter().filter(|entry| state.shows(entry)) {
+            match entry.target {
+                Target::Page(href) => out.push(href),
+                Target::Menu(entries) => collect(state, entries, out),
+            }
+        }
+    }
+    let state = NavState {
+        path: "",
+        organizer,
+    };
+    let mut out = Vec::new();
+    collect(&state, MENU, &mut out);
+    collect(&state, std::slice::from_ref(&JOIN), &mut out);
+    out
+}
+
+/// What the menu is rendered for.
+#[derive(Debug, Clone, Copy)]
+pub struct NavState<'a> {
+    /// Path of the page being shown.
+    pub path: &'a str,
+    pub organizer: bool,
+}
+
+impl NavState<'_> {
+    fn shows(&self, entry: &Entry) -> bool {
+        match entry.visibility {
+            Visibility::Everyone => true,
+            Visibility::Organizers => self.organizer,
+        }
+    }
+
+    fn is_active(&self, entry: &Entry) -> bool {
+        match entry.target {
+            Target::Page("/") => self.path == "/" || self.path == "/home",
+            // Pages below a menu target, such as one event, keep it active.
+            Target::Page(href) => {
+                self.path == href
+                    || self
+                        .path
+                        .strip_prefix(href)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            }
+            Target::Menu(entries) => entries.iter().any(|entry| self.is_active(entry)),
+        }
+    }
+}
+
+/// A link to a [`Target::Page`] entry.
+fn link(state: &NavState, entry: &Entry, class: &str) -> Markup {
+    let Target::Page(href) = entry.target else {
+        return html! {};
+    };
+    let active = state.is_active(entry);
+    html! {
+        a href=(href) hx-get=(href) hx-target="#page" hx-push-url="true"
+            class={ (class) @if active { " text-blue-700 font-bold" } }
+            aria-current=[active.then_some("page")] {
+            (entry.label)
+        }
+    }
+}
+
+fn dropdown(state: &NavState, entry: &Entry, entries: &[Entry]) -> Markup {
+    html! {
+        div class="relative" {
+            button type="button" class={ "dropdown-toggle py-2 px-3 hover:bg-gray-100 flex items-center gap-2 rounded" @if state.is_active(entry) { " text-blue-700 font-bold" } } {
+                span class="select-none" { (entry.label) }
+                svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-6" {
+                    path stroke-linecap="round" stroke-linejoin="round" d="m19.5 8.25-7.5 7.5-7.5-7.5" {}
+                }
+            }
+            div class="hidden dropdown-menu absolute bg-gray-100 rounded-b-lg pb-2 w-48 flex flex-col z-30" {
+                @for item in entries.iter().filter(|item| state.shows(item)) {
+                    (link(state, item, "hover:text-blue-700 hover:underline px-4 py-2"))
+                }
+            }
+        }
+    }
+}
+
+fn entry(state: &NavState, entry: &Entry) -> Markup {
+    match entry.target {
+        Target::Page(_) => link(
+            state,
+            entry,
+            "hover:text-blue-700 hover:underline px-4 py-2 rounded",
+        ),
+        Target::Menu(entries) => dropdown(state, entry, entries),
+    }
+}
+
+fn logo() -> Markup {
+    html! {
+        a href="/" hx-get="/" hx-target="#page" hx-push-url="true" class="flex items-center gap-4" {
+            img src="/assets/img/logo.jpg" class="h-28 w-28 rounded-full object-cover" alt="Logo" {}
+        }
+    }
+}
+
+/// Menu bar for wide screens. With `oob` set it replaces the one already on
+/// the page when sent along with an htmx response.
+pub fn desktop(state: &NavState, oob: bool) -> Markup {
+    html! {
+        nav id="navb" class="bg-pink-to-white p-4" hx-swap-oob=[oob.then_some("true")] {
+            div class="container mx-auto flex items-center justify-between gap-6" {
+                div class="flex flex-col items-center" { (logo()) }
+                div class="flex justify-center flex-grow" {
+                    @for item in MENU.iter().filter(|item| state.shows(item)) {
+                        (entry(state, item))
+                    }
+                }
+                div {
+                    (link(state, &JOIN, "text-white bg-orange-600 hover:bg-red-600 px-6 py-3 rounded-lg text-lg font-medium"))
+                }
+            }
+        }
+    }
+}
+
+/// Header for small screens: plain links on the first row, dropdowns on the
+/// second.
+pub fn mobile(state: &NavState, oob: bool) -> Markup {
+    let shown: Vec<&Entry> = MENU.iter().filter(|item| state.shows(item)).collect();
+    html! {
+        div id="mobile-nav" class="flex bg-pink-to-white md:hidden" hx-swap-oob=[oob.then_some("true")] {
+            div class="flex flex-col item-center" { (logo()) }
+            div class="flex flex-col py-2 text-sm" {
+                div class="flex justify-center flex-grow" {
+                    @for item in shown.iter().filter(|item| matches!(item.target, Target::Page(_))) {
+                        (entry(state, item))
+                    }
+                }
+                div class="flex justify-center flex-grow" {
+                    @for item in shown.iter().filter(|item| matches!(item.target, Target::Menu(_))) {
+                        (entry(state, item))
+                    }
+                }
+            }
+            div class="flex-grow flex items-center justify-center" {
+                (link(state, &JOIN, "text-white bg-orange-600 hover:bg-red-600 px-3 py-1 rounded-md text-sm font-small"))
+            }
+        }
+    }
+}
+
+/// Opens and closes the dropdowns. Listens on the document so menus swapped
+/// in later work without rebinding.
+pub fn dropdown_script() -> Markup {
+    html! {
+        script {
+            (PreEscaped(r#"
+                document.addEventListener("click", function (e) {
+                    const toggle = e.target.closest(".dropdown-toggle");
+                    document.querySelectorAll(".dropdown-menu").forEach((menu) => {
+                        if (toggle && menu === toggle.nextElementSibling) {
+                            menu.classList.toggle("hidden");
+                        } else {
+                            menu.classList.add("hidden");
+                        }
+                    });
+                });
+            "#))
+        }
+    }
+}
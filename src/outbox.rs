@@ -0,0 +1,391 @@
+//! Persistent queue of outgoing email. Form handlers [`enqueue`] a message
+//! and return straight away; a background worker delivers it through the
+//! configured [`Mailer`](crate::mail::Mailer), retrying with exponential
+//! backoff until it is sent or runs out of attempts.
+
+use std::time::Duration;
+
+use lettre::{
+    message::{self, header::ContentType, Mailbox, MultiPart, SinglePart},
+    Message,
+};
+use mongodb::bson::{doc, oid::ObjectId, spec::BinarySubtype, Binary, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mail::{self, MailError},
+    store::{Record, StoreError},
+    ClientState,
+};
+
+/// Longest the worker sleeps before looking for due messages again.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Upper bound on the delay between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+impl EmailStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            EmailStatus::Pending => "pending",
+            EmailStatus::Sent => "sent",
+            EmailStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedEmail {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub to: String,
+    pub reply_to: Option<String>,
+    pub subject: String,
+    pub body: String,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    pub status: EmailStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime,
+    pub last_error: Option<String>,
+    pub created_at: DateTime,
+    pub sent_at: Option<DateTime>,
+}
+
+/// A file sent along with an email.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Binary,
+}
+
+impl Attachment {
+    pub fn new(
+        filename: impl Into<String>,
+        content_type: impl Into<String>,
+        data: Vec<u8>,
+    ) -> Self {
+        Attachment {
+            filename: filename.into(),
+            content_type: content_type.into(),
+            data: Binary {
+                subtype: BinarySubtype::Generic,
+                bytes: data,
+            },
+        }
+    }
+}
+
+impl Record for QueuedEmail {
+    const COLLECTION: &'static str = "outbox";
+}
+
+impl QueuedEmail {
+    pub fn new(to: &Mailbox, subject: impl Into<String>, body: impl Into<String>) -> Self {
+        let now = DateTime::now();
+        QueuedEmail {
+            id: ObjectId::new(),
+            to: to.to_string(),
+            reply_to: None,
+            subject: subject.into(),
+            body: body.into(),
+            attachments: Vec::new(),
+            status: EmailStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+            sent_at: None,
+        }
+    }
+
+    pub fn reply_to(mut self, reply_to: &Mailbox) -> Self {
+        self.reply_to = Some(reply_to.to_string());
+        self
+    }
+
+    pub fn attach(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    fn to_message(&self, from: &Mailbox) -> Result<Message, MailError> {
+        let mut builder = Message::builder()
+            .from(from.clone())
+            .to(self.to.parse()?)
+            .subject(&self.subject);
+        if let Some(reply_to) = &self.reply_to {
+            builder = builder.reply_to(reply_to.parse()?);
+        }
+        if self.attachments.is_empty() {
+            return Ok(builder
+                .header(ContentType::TEXT_PLAIN)
+                .body(self.body.clone())?);
+        }
+        let mut parts = MultiPart::mixed().singlepart(SinglePart::plain(self.body.clone()));
+        for attachment in &self.attachments {
+            let content_type =
+                ContentType::parse(&attachment.content_type).unwrap_or(ContentType::TEXT_PLAIN);
+            parts = parts.singlepart(
+                message::Attachment::new(attachment.filename.clone())
+                    .body(attachment.data.bytes.clone(), content_type),
+            );
+        }
+        Ok(builder.multipart(parts)?)
+    }
+}
+
+/// Stores `email` for delivery and wakes the worker.
+pub async fn enqueue(s: &ClientState, email: QueuedEmail) -> Result<(), StoreError> {
+    s.repo::<QueuedEmail>().insert(&email).await?;
+    tracing::info!(email_id = %email.id, "email queued");
+    s.outbox.notify_one();
+    Ok(())
+}
+
+/// Puts a failed message back in the queue with a fresh set of attempts.
+pub async fn resend(s: &ClientState, id: ObjectId) -> Result<bool, StoreError> {
+    let matched = s
+        .repo::<QueuedEmail>()
+        .update(
+            doc! { "_id": id, "status": EmailStatus::Failed.as_str() },
+            doc! {
+                "status": EmailStatus::Pending.as_str(),
+                "attempts": 0,
+                "next_attempt_at": DateTime::now(),
+            },
+        )
+        .await?;
+    s.outbox.notify_one();
+    Ok(matched > 0)
+}
+
+pub async fn failed(s: &ClientState) -> Result<Vec<QueuedEmail>, StoreError> {
+    let mut emails = s
+        .repo::<QueuedEmail>()
+        .find(doc! { "status": EmailStatus::Failed.as_str() })
+        .await?;
+    emails.sort_by_key(|email| std::cmp::Reverse(email.created_at));
+    Ok(emails)
+}
+
+fn backoff(base: Duration, attempts: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+/// Makes one delivery attempt for every message that is due and returns
+/// how long to wait before the next retry falls due.
+pub async fn deliver_due(s: &ClientState) -> Result<Duration, StoreError> {
+    let repo = s.repo::<QueuedEmail>();
+    let now = DateTime::now();
+    let (mut due, waiting): (Vec<QueuedEmail>, Vec<QueuedEmail>) = repo
+        .find(doc! { "status": EmailStatus::Pending.as_str() })
+        .await?
+        .into_iter()
+        .partition(|email| email.next_attempt_at <= now);
+    due.sort_by_key(|email| email.created_at);
+    let mut next_due = waiting
+        .iter()
+        .map(|email| email.next_attempt_at)
+        .min()
+        .map(|at| Duration::from_millis((at.timestamp_millis() - now.timestamp_millis()) as u64))
+        .unwrap_or(POLL_INTERVAL);
+
+    for email in due {
+        let attempts = email.attempts as u32 + 1;
+        let result = match email.to_message(&s.config.mail.from) {
+            Ok(message) => mail::send(s.mailer.clone(), message).await,
+            Err(e) => Err(e),
+        };
+        let update = match result {
+            Ok(()) => {
+                tracing::info!(email_id = %email.id, attempts, "email sent");
+                doc! {
+                    "status": EmailStatus::Sent.as_str(),
+                    "attempts": attempts as i32,
+                    "sent_at": DateTime::now(),
+                    "last_error": null,
+                }
+            }
+            Err(e) => {
+                let gave_up =
+                    attempts >= s.config.outbox.max_attempts || matches!(e, MailError::Address(_));
+                tracing::warn!(email_id = %email.id, attempts, gave_up, error = %e, "could not send email");
+                let delay = backoff(s.config.outbox.retry_base, attempts);
+                if !gave_up {
+                    next_due = next_due.min(delay);
+                }
+                let retry_at = DateTime::from_millis(
+                    DateTime::now().timestamp_millis() + delay.as_millis() as i64,
+                );
+                doc! {
+                    "status": if gave_up { EmailStatus::Failed } else { EmailStatus::Pending }.as_str(),
+                    "attempts": attempts as i32,
+                    "next_attempt_at": retry_at,
+                    "last_error": e.to_string(),
+                }
+            }
+        };
+        repo.update(doc! { "_id": email.id }, update).await?;
+    }
+    Ok(next_due.min(POLL_INTERVAL))
+}
+
+/// Starts the delivery worker. It runs until the process exits.
+pub fn spawn_worker(s: ClientState) {
+    tokio::spawn(async move {
+        loop {
+            let wait = deliver_due(&s).await.unwrap_or_else(|e| {
+                tracing::error!(error = %e, "outbox delivery failed");
+                POLL_INTERVAL
+            });
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = s.outbox.notified() => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        mail::{Mailer, MemoryMailer},
+        tests,
+    };
+
+    /// A relay that turns every message away.
+    struct FailingMailer;
+
+    impl Mailer for FailingMailer {
+        fn send(&self, _: &Message) -> Result<(), MailError> {
+            Err(MailError::Io(std::io::Error::other("relay refused")))
+        }
+    }
+
+    fn email() -> QueuedEmail {
+        QueuedEmail::new(
+            &"meena@example.com".parse().unwrap(),
+            "Hello",
+            "Welcome to the Sangam.",
+        )
+    }
+
+    async fn stored(s: &ClientState, id: ObjectId) -> QueuedEmail {
+        s.repo::<QueuedEmail>()
+            .find_one(doc! { "_id": id })
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let base = Duration::from_secs(30);
+        let delays: Vec<u64> = (1..=8).map(|n| backoff(base, n).as_secs()).collect();
+        assert_eq!(delays, [30, 60, 120, 240, 480, 960, 1920, 3600]);
+        assert_eq!(backoff(base, 0), base);
+        assert_eq!(backoff(base, 40), MAX_BACKOFF);
+        assert_eq!(backoff(base, u32::MAX), MAX_BACKOFF);
+        assert_eq!(backoff(Duration::ZERO, 5), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn due_email_is_sent_once() {
+        let mailer = Arc::new(MemoryMailer::new());
+        let s = ClientState {
+            mailer: mailer.clone(),
+            ..tests::test_state()
+        };
+        let queued = email();
+        enqueue(&s, queued.clone()).await.unwrap();
+
+        deliver_due(&s).await.unwrap();
+        let sent = stored(&s, queued.id).await;
+        assert_eq!(sent.status, EmailStatus::Sent);
+        assert_eq!(sent.attempts, 1);
+        assert!(sent.sent_at.is_some());
+        assert_eq!(mailer.sent().len(), 1);
+
+        deliver_due(&s).await.unwrap();
+        assert_eq!(mailer.sent().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn email_waits_until_its_next_attempt() {
+        let mailer = Arc::new(MemoryMailer::new());
+        let s = ClientState {
+            mailer: mailer.clone(),
+            ..tests::test_state()
+        };
+        let mut later = email();
+        later.next_attempt_at = DateTime::from_millis(DateTime::now().timestamp_millis() + 10_000);
+        enqueue(&s, later.clone()).await.unwrap();
+
+        let wait = deliver_due(&s).await.unwrap();
+        assert!(mailer.sent().is_empty());
+        assert_eq!(stored(&s, later.id).await.status, EmailStatus::Pending);
+        assert!(wait <= Duration::from_secs(10), "{wait:?}");
+    }
+
+    #[tokio::test]
+    async fn failing_email_is_retried_then_given_up() {
+        let s = ClientState {
+            mailer: Arc::new(FailingMailer),
+            ..tests::test_state_with(&["--outbox-max-attempts=3", "--outbox-retry-secs=0"])
+        };
+        let queued = email();
+        enqueue(&s, queued.clone()).await.unwrap();
+
+        for attempts in 1..=2 {
+            deliver_due(&s).await.unwrap();
+            let retrying = stored(&s, queued.id).await;
+            assert_eq!(retrying.status, EmailStatus::Pending);
+            assert_eq!(retrying.attempts, attempts);
+            assert!(retrying.last_error.unwrap().contains("relay refused"));
+        }
+        deliver_due(&s).await.unwrap();
+        let failed = stored(&s, queued.id).await;
+        assert_eq!(failed.status, EmailStatus::Failed);
+        assert_eq!(failed.attempts, 3);
+        assert_eq!(failed_ids(&s).await, [queued.id]);
+
+        assert!(resend(&s, queued.id).await.unwrap());
+        let again = stored(&s, queued.id).await;
+        assert_eq!((again.status, again.attempts), (EmailStatus::Pending, 0));
+    }
+
+    #[tokio::test]
+    async fn a_bad_address_is_given_up_at_once() {
+        let s = tests::test_state();
+        let mut queued = email();
+        queued.to = "not an address".to_string();
+        enqueue(&s, queued.clone()).await.unwrap();
+
+        deliver_due(&s).await.unwrap();
+        let failed = stored(&s, queued.id).await;
+        assert_eq!(failed.status, EmailStatus::Failed);
+        assert_eq!(failed.attempts, 1);
+    }
+
+    async fn failed_ids(s: &ClientState) -> Vec<ObjectId> {
+        failed(s)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|email| email.id)
+            .collect()
+    }
+}
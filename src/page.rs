@@ -1,15 +1,29 @@
 use crate::{
+    admin,
     links::{EMAIL, FACEBOOK_LINK, INSTAGRAM_LINK, PHONE, PHONE_LINK, WHATSAPP_LINK, YOUTUBE_LINK},
-    mobile_navbar, navbar, strings,
+    nav::{self, NavState},
+    seo::Meta,
+    strings, ClientState,
 };
-use maud::{html, Markup, DOCTYPE};
-fn body(content: Markup) -> Markup {
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE, VARY},
+        HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::SignedCookieJar;
+use maud::{html, Markup, PreEscaped, DOCTYPE};
+fn body(nav: &NavState, content: Markup) -> Markup {
     html! {
         body {
-            script src="assets/js/vendor/htmx.min.js" {}
-            script src="assets/js/vendor/modernizr-3.11.2.min.js" {}
-            script src="assets/js/plugins.js" {}
-            script src="assets/js/main.js" {}
+            script src="/assets/js/vendor/htmx.min.js" {}
+            script src="/assets/js/vendor/modernizr-3.11.2.min.js" {}
+            script src="/assets/js/plugins.js" {}
+            script src="/assets/js/main.js" {}
             link href="https://fonts.googleapis.com/css2?family=Roboto:wght@400;700&display=swap" rel="stylesheet";
             link href="https://fonts.googleapis.com/css2?family=Taviraj:wght@400;700&display=swap" rel="stylesheet";
             script{
@@ -17,14 +31,16 @@ fn body(content: Markup) -> Markup {
                         window.scrollTo(0, 0);
                     });"
             }
+            (nav::dropdown_script())
 
             div class="min-h-screen flex flex-col" {
                 div class="hidden md:flex flex-col" {
-                    (navbar_markup())
+                    (navbar_markup(nav))
                 }
-                (mobile_navbar())
+                (nav::mobile(nav, false))
+                (mobile_contact_markup())
 
-                div class="flex-grow" {
+                div class="flex-grow bg-gray-50" id="page" {
                     (content)
                 }
 
@@ -33,7 +49,7 @@ fn body(content: Markup) -> Markup {
         }
     }
 }
-pub fn navbar_markup() -> Markup {
+pub fn navbar_markup(nav: &NavState) -> Markup {
     html! {
         div class="bg-orange-600 text-white py-2 px-4 flex justify-between items-center" {
             div class="flex items-center space-x-4" {
@@ -59,8 +75,30 @@ pub fn navbar_markup() -> Markup {
                             }
             }
         }
-        div hx-trigger="load" hx-get="/navbar"{}
+        (nav::desktop(nav, false))
+    }
+}
 
+fn mobile_contact_markup() -> Markup {
+    html! {
+        div class="flex w-full items-center justify-between bg-orange-600 md:hidden text-white" {
+            div class="ml-3 flex space-x-4" {
+                a href=(FACEBOOK_LINK) { i class="fab fa-facebook" {} }
+                a href=(INSTAGRAM_LINK) { i class="fab fa-instagram" {} }
+                a href=(WHATSAPP_LINK) { i class="fab fa-whatsapp" {} }
+                a href=(YOUTUBE_LINK) { i class="fab fa-youtube" {} }
+            }
+            // Email icon and clickable email link
+            a href=(format!("mailto:{EMAIL}")) class="text-white hover:text-gray-200 flex items-center" {
+                i class="fas fa-envelope mr-1" {}
+                p class="text-sm" { (EMAIL) }
+            }
+            // Phone icon and clickable phone link
+            a href=(PHONE_LINK) class="text-white hover:text-gray-200 flex items-center mr-2" {
+                i class="fas fa-phone-alt mr-1" {}
+                p class="text-sm" { (PHONE) }
+            }
+        }
     }
 }
 
@@ -104,9 +142,9 @@ pub fn footer_markup() -> Markup {
                     div class="mb-6 md:mb-0 md:w-1/3 text-center md:text-left" {
                         h2 class="text-2xl font-bold mb-2" { "Quick Links" }
                         div class="text-gray-400 flex flex-col space-y-2 mt-4" {
-                            a hx-get="/about/about" hx-trigger="click" hx-target="#page" class="hover:text-white"{ "About Us" }
-                            a hx-get="/about/contact" hx-trigger="click" hx-target="#page" class="hover:text-white" { "Contact Us" }
-                            a hx-get="/faq" hx-trigger="click" hx-target="#page" class="hover:text-white"{ "FAQ's" }
+                            a hx-get="/about/about" hx-trigger="click" hx-target="#page" hx-push-url="true" class="hover:text-white"{ "About Us" }
+                            a hx-get="/about/contact" hx-trigger="click" hx-target="#page" hx-push-url="true" class="hover:text-white" { "Contact Us" }
+                            a hx-get="/faq" hx-trigger="click" hx-target="#page" hx-push-url="true" class="hover:text-white"{ "FAQ's" }
                         }
                     }
 
@@ -114,12 +152,12 @@ pub fn footer_markup() -> Markup {
                     div class="text-center md:text-left md:w-1/3" {
                         h2 class="text-2xl font-bold mb-2" { "Get Involved" }
                         div class="text-gray-400 flex flex-col space-y-2 mt-4" {
-                            a hx-get="/sponsors" hx-trigger="click" hx-target="#page" class="hover:text-white" {"Sponsors" }
-                            a hx-get="/join" hx-trigger="click" hx-target="#page" class="hover:text-white" { "Join Us" }
+                            a hx-get="/sponsors" hx-trigger="click" hx-target="#page" hx-push-url="true" class="hover:text-white" {"Sponsors" }
+                            a hx-get="/join" hx-trigger="click" hx-target="#page" hx-push-url="true" class="hover:text-white" { "Join Us" }
 
 
-                            // a hx-get="/membership" hx-trigger="click" hx-target="#page" class="hover:text-white" { "Membership" }
-                            // a hx-get="/donate" hx-trigger="click" hx-target="#page" class="hover:text-white" { "Donate" }
+                            // a hx-get="/membership" hx-trigger="click" hx-target="#page" hx-push-url="true" class="hover:text-white" { "Membership" }
+                            // a hx-get="/donate" hx-trigger="click" hx-target="#page" hx-push-url="true" class="hover:text-white" { "Donate" }
                         }
                     }
                 }
@@ -134,39 +172,119 @@ pub fn footer_markup() -> Markup {
     }
 }
 
-fn head(title: &str, desc: &str, url: &str) -> Markup {
+fn head(meta: &Meta, site_url: &str, path: &str) -> Markup {
+    let url = format!("{site_url}{path}");
     html! {
         head {
             meta charset=(strings::UTF8);
-            title { (title) }
-            meta name=(strings::DESCRIPTION) content=(desc);
+            title { (meta.full_title()) }
+            meta name=(strings::DESCRIPTION) content=(meta.description);
             meta name=(strings::VIEWPORT) content=(strings::VIEWPORT_CONTENT);
-            meta property="og:title" content=(title);
+            link rel="canonical" href=(url);
+            meta property="og:title" content=(meta.full_title());
+            meta property="og:description" content=(meta.description);
             meta property="og:type" content=(strings::WEBSITE);
             meta property="og:url" content=(url);
-            meta property="og:image" content="";
-            link rel="manifest" href="assets/site.webmanifest";
-            link rel="apple-touch-icon" href="assets/icon.png";
+            meta property="og:image" content=(format!("{site_url}{}", meta.image));
+            meta name="twitter:card" content="summary_large_image";
+            link rel="manifest" href="/assets/site.webmanifest";
+            link rel="apple-touch-icon" href="/assets/icon.png";
             link rel="stylesheet" href="https://cdnjs.cloudflare.com/ajax/libs/font-awesome/6.0.0-beta3/css/all.min.css";
 
-            link rel = "stylesheet" href="assets/css/main.css";
+            link rel = "stylesheet" href="/assets/css/main.css";
 
             meta name="theme-color" content="#fafafa";
         }
     }
 }
 
-pub(crate) fn page(content: Markup) -> Markup {
-    let host = "njtts.org";
-    let title = "njtts";
-    let desc = "Tamil sangam website";
+pub(crate) fn page(nav: &NavState, meta: &Meta, site_url: &str, content: Markup) -> Markup {
     let lang = "en";
 
     html! {
         (DOCTYPE)
         html class="no-js" lang=(lang) {
-            (head(title, desc, host))
-            (body(content))
+            (head(meta, site_url, nav.path))
+            (body(nav, content))
         }
     }
 }
+
+/// Inline error shown in place of a form response when a request could not
+/// be completed.
+pub fn error_fragment(message: &str) -> Markup {
+    html! {
+        div class="bg-red-100 border border-red-400 text-red-700 px-4 py-3 rounded" role="alert" {
+            p { (message) }
+        }
+    }
+}
+
+/// Most routes render only the fragment htmx swaps into `#page`. When one
+/// is requested directly (a shared link, a refresh, or htmx restoring
+/// history it no longer has cached) it is wrapped in the full page here, so
+/// every URL the navbar pushes also works on its own, with the head built
+/// from the [`Meta`] the handler returned. Fragments swapped into `#page`
+/// carry the title and fresh copies of both menus so the active entry
+/// follows.
+pub async fn full_page(
+    State(s): State<ClientState>,
+    jar: SignedCookieJar,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let is_htmx = headers.contains_key("hx-request");
+    let wants_page = !is_htmx || headers.contains_key("hx-history-restore-request");
+    let wants_nav = is_htmx
+        && request.method() == Method::GET
+        && headers
+            .get("hx-target")
+            .is_some_and(|target| target.as_bytes() == b"page");
+    let path = request.uri().path().to_string();
+    let organizer = admin::is_organizer(&jar, &s);
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .append(VARY, HeaderValue::from_static("HX-Request"));
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    // Friendly error pages such as an expired short link are pages too.
+    let is_page = matches!(
+        response.status(),
+        StatusCode::OK | StatusCode::NOT_FOUND | StatusCode::GONE
+    );
+    if !(wants_page || wants_nav) || !is_html || !is_page {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let meta = parts.extensions.remove::<Meta>().unwrap_or_default();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    if bytes.starts_with(b"<!DOCTYPE") || bytes.starts_with(b"<html") {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+    let fragment = PreEscaped(String::from_utf8_lossy(&bytes).into_owned());
+    let nav = NavState {
+        path: &path,
+        organizer,
+    };
+    let body = if wants_page {
+        page(&nav, &meta, &s.config.site_url, fragment)
+    } else {
+        // htmx moves the title into the document head.
+        html! {
+            title { (meta.full_title()) }
+            (fragment)
+            (nav::desktop(&nav, true))
+            (nav::mobile(&nav, true))
+        }
+    };
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body.into_string()))
+}
@@ -0,0 +1,110 @@
+//! Just enough PDF to print a one-page document of centered text lines in
+//! the standard Helvetica fonts, which every viewer has built in. Text is
+//! encoded as WinAnsi, so characters outside Latin-1 print as `?`.
+
+use std::fmt::Write;
+
+/// Landscape US Letter, in points.
+const WIDTH: f32 = 792.0;
+const HEIGHT: f32 = 612.0;
+/// Average Helvetica glyph width as a fraction of the font size, used to
+/// center lines.
+const AVERAGE_GLYPH: f32 = 0.5;
+
+pub struct Line {
+    text: String,
+    size: f32,
+    bold: bool,
+    /// Space above the line, in points.
+    gap: f32,
+}
+
+impl Line {
+    pub fn new(text: impl Into<String>, size: f32) -> Line {
+        Line {
+            text: text.into(),
+            size,
+            bold: false,
+            gap: size * 0.6,
+        }
+    }
+
+    pub fn bold(mut self) -> Line {
+        self.bold = true;
+        self
+    }
+
+    pub fn gap(mut self, gap: f32) -> Line {
+        self.gap = gap;
+        self
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{c}"),
+            c if (' '..='~').contains(&c) => c.to_string(),
+            c if (c as u32) < 256 => format!("\\{:03o}", c as u32),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+/// A one-page PDF with `lines` centered from the top, inside a border.
+pub fn page(title: &str, lines: &[Line]) -> Vec<u8> {
+    let mut content = String::new();
+    // Double border.
+    content.push_str("2 w 30 30 732 552 re S 0.5 w 38 38 716 536 re S\n");
+    let mut y = HEIGHT - 70.0;
+    for line in lines {
+        y -= line.gap + line.size;
+        let font = if line.bold { "F2" } else { "F1" };
+        let width = line.text.chars().count() as f32 * line.size * AVERAGE_GLYPH;
+        let x = ((WIDTH - width) / 2.0).max(50.0);
+        let _ = writeln!(
+            content,
+            "BT /{font} {} Tf {x:.1} {y:.1} Td ({}) Tj ET",
+            line.size,
+            escape(&line.text)
+        );
+    }
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {WIDTH} {HEIGHT}] \
+             /Resources << /Font << /F1 5 0 R /F2 6 0 R >> >> /Contents 4 0 R >>"
+        ),
+        format!(
+            "<< /Length {} >>\nstream\n{content}endstream",
+            content.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        format!("<< /Title ({}) /Producer (njtts.org) >>", escape(title)),
+    ];
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", i + 1).as_bytes());
+    }
+    let xref = pdf.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(trailer, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1,
+        objects.len()
+    );
+    pdf.extend_from_slice(trailer.as_bytes());
+    pdf
+}
@@ -0,0 +1,587 @@
+//! Organizers add photos to the gallery from the browser. Each upload is
+//! decoded and saved again as a fresh JPEG in the event's album folder, so
+//! nothing of the original file survives but the picture: no EXIF, and so
+//! no GPS position of where it was taken. Files that are not photos, or
+//! are too large, are turned away one by one while the rest are saved.
+
+use std::{
+    fs::OpenOptions,
+    io::{Cursor, Write},
+    path::Path,
+};
+
+use axum::{
+    extract::{multipart::Field, Multipart, State},
+    http::StatusCode,
+};
+use chrono::{Datelike, NaiveDate};
+use image::{codecs::jpeg::JpegEncoder, ImageFormat, ImageReader, Limits};
+use maud::{html, Markup};
+
+use crate::{
+    admin::Organizer,
+    events::today,
+    gallery::{self, METADATA_FILE},
+    images,
+    page::error_fragment,
+    seo::Meta,
+    ClientState,
+};
+
+/// Largest photo accepted, in bytes. Phone cameras stay well below it.
+pub const MAX_PHOTO_BYTES: usize = 20 * 1024 * 1024;
+/// Largest upload as a whole, to allow a batch of photos at once.
+pub const MAX_UPLOAD_BYTES: usize = 250 * 1024 * 1024;
+/// Largest width or height accepted, so a small file cannot unpack into an
+/// enormous image.
+const MAX_DIMENSION: u32 = 12_000;
+/// Saved copies are meant for viewing, not printing.
+const JPEG_QUALITY: u8 = 90;
+const NOT_A_PHOTO: &str = "not a JPEG, PNG or WebP photo";
+
+/// An album photos can be added to: one already in the gallery, or a new
+/// one for an event that has taken place.
+struct Choice {
+    year: i32,
+    slug: String,
+    title: String,
+    date: Option<NaiveDate>,
+    photos: usize,
+}
+
+impl Choice {
+    /// The value of the album field, e.g. "2024/summer-camping".
+    fn key(&self) -> String {
+        format!("{}/{}", self.year, self.slug)
+    }
+}
+
+fn choices(s: &ClientState) -> Vec<Choice> {
+    let mut choices: Vec<Choice> = gallery::scan(&s.config.static_dir)
+        .into_iter()
+        .flat_map(|year| {
+            year.albums.into_iter().map(move |album| Choice {
+                year: year.year,
+                slug: album.slug,
+                title: album.title,
+                date: album.date,
+                photos: album.photos.len(),
+            })
+        })
+        .collect();
+    let events = s.events.get();
+    let today = today();
+    for event in events.events.iter().filter(|event| event.date <= today) {
+        let year = event.date.year();
+        if !choices
+            .iter()
+            .any(|choice| choice.year == year && choice.slug == event.slug)
+        {
+            choices.push(Choice {
+                year,
+                slug: event.slug.clone(),
+                title: event.title.clone(),
+                date: Some(event.date),
+                photos: 0,
+            });
+        }
+    }
+    choices.sort_by(|a, b| {
+        (b.year, b.date)
+            .cmp(&(a.year, a.date))
+            .then_with(|| a.slug.cmp(&b.slug))
+    });
+    choices
+}
+
+fn upload_form(choices: &[Choice]) -> Markup {
+    html! {
+        form hx-post="/admin/photos" hx-encoding="multipart/form-data" hx-target="#upload-result"
+            method="post" action="/admin/photos" enctype="multipart/form-data"
+            class="bg-white p-6 rounded-lg shadow-lg space-y-4" {
+            div {
+                label for="album" class="block text-sm font-medium text-gray-700" { "Album" }
+                select id="album" name="album" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {
+                    @for choice in choices {
+                        option value=(choice.key()) {
+                            (choice.year) " · " (choice.title)
+                            @if choice.photos == 0 {
+                                " (new album)"
+                            } @else {
+                                " (" (choice.photos) " photos)"
+                            }
+                        }
+                    }
+                }
+            }
+            div {
+                label for="photos" class="block text-sm font-medium text-gray-700" { "Photos" }
+                input type="file" id="photos" name="photos" multiple required
+                    accept="image/jpeg,image/png,image/webp" class="mt-1 block w-full" {}
+                p class="text-sm text-gray-600 mt-1" {
+                    "JPEG, PNG or WebP, up to " (MAX_PHOTO_BYTES / 1024 / 1024) " MB each. "
+                    "Location data is removed before saving."
+                }
+            }
+            div class="text-center" {
+                button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Upload" }
+            }
+        }
+    }
+}
+
+pub async fn upload_page(_: Organizer, State(s): State<ClientState>) -> (Meta, Markup) {
+    let meta = Meta::new("Add Photos", "Add photos to the gallery.");
+    let choices = choices(&s);
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-2xl mx-auto p-8 space-y-6" {
+                h1 class="text-3xl font-bold text-center" { "Add Photos" }
+                @if choices.is_empty() {
+                    p class="text-center" { "There is no album yet and no past event to start one for." }
+                } @else {
+                    (upload_form(&choices))
+                }
+                div id="upload-result" {}
+                @if choices.iter().any(|choice| choice.photos > 0) {
+                    div class="bg-white p-6 rounded-lg shadow-lg" {
+                        h2 class="text-xl font-semibold mb-2" { "Tag People" }
+                        p class="text-sm text-gray-600 mb-2" {
+                            "Photos showing someone without photo consent are hidden from the public gallery."
+                        }
+                        ul class="list-disc pl-6" {
+                            @for choice in choices.iter().filter(|choice| choice.photos > 0) {
+                                li {
+                                    a href={ "/admin/photos/" (choice.key()) } class="text-blue-600 hover:underline" {
+                                        (choice.year) " · " (choice.title)
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+    (meta, content)
+}
+
+/// "IMG_2041 (1).HEIC.jpg" becomes "img-2041-1-heic": safe in a URL and on
+/// any file system.
+fn file_stem(name: &str) -> String {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let mut clean = String::new();
+    for c in stem.chars() {
+        if c.is_ascii_alphanumeric() {
+            clean.push(c.to_ascii_lowercase());
+        } else if !clean.is_empty() && !clean.ends_with('-') {
+            clean.push('-');
+        }
+    }
+    let clean = clean.trim_end_matches('-');
+    if clean.is_empty() {
+        "photo".to_string()
+    } else {
+        clean.chars().take(60).collect()
+    }
+}
+
+/// The photo as a new JPEG, the right way up and without metadata.
+fn clean(bytes: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|_| NOT_A_PHOTO)?;
+    if !matches!(
+        reader.format(),
+        Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP)
+    ) {
+        return Err(NOT_A_PHOTO);
+    }
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+    let image = images::decode(reader).map_err(|e| match e {
+        image::ImageError::Limits(_) => "too large in width or height",
+        _ => NOT_A_PHOTO,
+    })?;
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+        .encode_image(&image::DynamicImage::ImageRgb8(image.to_rgb8()))
+        .map_err(|_| NOT_A_PHOTO)?;
+    Ok(jpeg)
+}
+
+/// Writes `jpeg` into `dir` under a name no other photo has. Returns the
+/// file name.
+fn save(dir: &Path, stem: &str, jpeg: &[u8]) -> std::io::Result<String> {
+    for n in 1.. {
+        let name = if n == 1 {
+            format!("{stem}.jpg")
+        } else {
+            format!("{stem}-{n}.jpg")
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(&name))
+        {
+            Ok(mut file) => {
+                file.write_all(jpeg)?;
+                return Ok(name);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("some numbered name is free")
+}
+
+/// Creates the album folder, with a metadata file naming the event when
+/// the album is new.
+fn prepare_album(dir: &Path, choice: &Choice) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let metadata = dir.join(METADATA_FILE);
+    if metadata.exists() {
+        return Ok(());
+    }
+    let mut table = toml::Table::new();
+    table.insert("title".into(), choice.title.clone().into());
+    if let Some(date) = choice.date {
+        table.insert("date".into(), date.to_string().into());
+    }
+    let text = toml::to_string(&table).map_err(std::io::Error::other)?;
+    std::fs::write(metadata, text)
+}
+
+/// Reads a file field, giving up once it passes [`MAX_PHOTO_BYTES`].
+async fn read_photo(
+    mut field: Field<'_>,
+) -> Result<Option<Vec<u8>>, axum::extract::multipart::MultipartError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field.chunk().await? {
+        if bytes.len() + chunk.len() > MAX_PHOTO_BYTES {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+enum Outcome {
+    Saved(String),
+    Rejected(String, String),
+}
+
+pub async fn upload(
+    _: Organizer,
+    State(s): State<ClientState>,
+    mut multipart: Multipart,
+) -> Markup {
+    let mut album = None;
+    let mut photos = Vec::new();
+    let mut outcomes = Vec::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!(error = %e, "could not read photo upload");
+                return error_fragment(if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                    "The upload is too large as a whole. Please send fewer photos at a time."
+                } else {
+                    "The upload could not be read. Please try again."
+                });
+            }
+        };
+        match field.name() {
+            Some("album") => album = field.text().await.ok(),
+            Some("photos") => {
+                let name = field.file_name().unwrap_or("photo").to_string();
+                match read_photo(field).await {
+                    Ok(Some(bytes)) if bytes.is_empty() => {}
+                    Ok(Some(bytes)) => photos.push((name, bytes)),
+                    Ok(None) => outcomes.push(Outcome::Rejected(
+                        name,
+                        format!("larger than {} MB", MAX_PHOTO_BYTES / 1024 / 1024),
+                    )),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "could not read photo upload");
+                        return error_fragment("The upload could not be read. Please try again.");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let choices = choices(&s);
+    let Some(choice) =
+        album.and_then(|album| choices.into_iter().find(|choice| choice.key() == album))
+    else {
+        return error_fragment("Please choose an album.");
+    };
+    if photos.is_empty() && outcomes.is_empty() {
+        return error_fragment("Please choose photos to upload.");
+    }
+
+    let dir = s
+        .config
+        .static_dir
+        .join(gallery::DIR)
+        .join(choice.year.to_string())
+        .join(&choice.slug);
+    let key = choice.key();
+    // The gallery's id for the album's section.
+    let album_link = format!("/gallery#{}-{}", choice.year, choice.slug);
+    let tag_link = format!("/admin/photos/{key}");
+    let task = tokio::task::spawn_blocking(move || {
+        if let Err(e) = prepare_album(&dir, &choice) {
+            tracing::error!(path = %dir.display(), error = %e, "could not create album");
+            return Err(());
+        }
+        let saved: Vec<Outcome> = photos
+            .into_iter()
+            .map(|(name, bytes)| match clean(&bytes) {
+                Ok(jpeg) => match save(&dir, &file_stem(&name), &jpeg) {
+                    Ok(file) => {
+                        tracing::info!(album = %key, file = %file, "saved uploaded photo");
+                        Outcome::Saved(file)
+                    }
+                    Err(e) => {
+                        tracing::error!(path = %dir.display(), error = %e, "could not save photo");
+                        Outcome::Rejected(name, "could not be saved".to_string())
+                    }
+                },
+                Err(reason) => Outcome::Rejected(name, reason.to_string()),
+            })
+            .collect();
+        Ok(saved)
+    });
+    match task.await {
+        Ok(Ok(saved)) => outcomes.extend(saved),
+        _ => return error_fragment("The album could not be created. Please try again."),
+    }
+
+    let saved = outcomes
+        .iter()
+        .filter(|outcome| matches!(outcome, Outcome::Saved(_)))
+        .count();
+    html! {
+        div class="bg-white p-6 rounded-lg shadow-lg space-y-2" {
+            p class="font-semibold" {
+                (saved) " of " (outcomes.len()) " photos added. "
+                @if saved > 0 {
+                    a href=(album_link) class="text-blue-600 hover:underline" { "See the album" }
+                    " · "
+                    a href=(tag_link) class="text-blue-600 hover:underline" { "Tag people" }
+                }
+            }
+            ul class="list-disc pl-6 text-sm" {
+                @for outcome in &outcomes {
+                    @match outcome {
+                        Outcome::Saved(file) => li { "Saved as " (file) },
+                        Outcome::Rejected(name, reason) => li class="text-red-700" { (name) ": " (reason) },
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request},
+    };
+    use image::{codecs::jpeg::JpegDecoder, ImageDecoder, RgbImage};
+
+    use super::*;
+    use crate::tests;
+
+    const BOUNDARY: &str = "photo-boundary";
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    /// A JPEG carrying an EXIF block with an orientation and a GPS latitude.
+    fn jpeg_with_gps() -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        RgbImage::new(8, 8)
+            .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+        // IFD0: the orientation, and where the GPS IFD starts.
+        tiff.extend_from_slice(&[0, 2]);
+        tiff.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 1, 0, 0]);
+        tiff.extend_from_slice(&[0x88, 0x25, 0, 4, 0, 0, 0, 1, 0, 0, 0, 38]);
+        tiff.extend_from_slice(&[0, 0, 0, 0]);
+        // GPS IFD: GPSLatitudeRef "N".
+        tiff.extend_from_slice(&[0, 1]);
+        tiff.extend_from_slice(&[0, 1, 0, 2, 0, 0, 0, 2, b'N', 0, 0, 0]);
+        tiff.extend_from_slice(&[0, 0, 0, 0]);
+        let mut app1 = vec![0xff, 0xe1];
+        app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        app1.extend_from_slice(b"Exif\0\0");
+        app1.extend_from_slice(&tiff);
+        jpeg.splice(2..2, app1);
+        jpeg
+    }
+
+    fn exif(jpeg: &[u8]) -> Option<Vec<u8>> {
+        JpegDecoder::new(Cursor::new(jpeg))
+            .unwrap()
+            .exif_metadata()
+            .unwrap()
+    }
+
+    /// A multipart body with `album` and one `photos` field per file.
+    fn multipart(album: &str, files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut body = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"album\"\r\n\r\n{album}\r\n"
+        )
+        .into_bytes();
+        for (name, bytes) in files {
+            body.extend_from_slice(
+                format!(
+                    "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"photos\"; filename=\"{name}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(bytes);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+        body
+    }
+
+    fn multipart_request(body: Vec<u8>, cookie: Option<&str>) -> Request<Body> {
+        let mut request = Request::post("/admin/photos").header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={BOUNDARY}"),
+        );
+        if let Some(cookie) = cookie {
+            request = request.header(header::COOKIE, cookie);
+        }
+        request.body(Body::from(body)).unwrap()
+    }
+
+    /// A site serving `static_dir`, which holds the album 2024/camping
+    /// with one photo.
+    fn with_album(static_dir: &Path) -> ClientState {
+        let album = static_dir.join(gallery::DIR).join("2024/camping");
+        std::fs::create_dir_all(&album).unwrap();
+        std::fs::write(album.join("first.jpg"), clean(&png(4, 4)).unwrap()).unwrap();
+        tests::test_state_with(&[&format!("--static-dir={}", static_dir.display())])
+    }
+
+    #[test]
+    fn cleaning_drops_the_exif_and_its_gps_position() {
+        let original = jpeg_with_gps();
+        assert!(exif(&original).is_some_and(|exif| exif.windows(4).any(|w| w == b"MM\0\x2a")));
+        let cleaned = clean(&original).unwrap();
+        assert_eq!(exif(&cleaned), None);
+        assert!(!cleaned.windows(6).any(|w| w == b"Exif\0\0"));
+        let image = image::load_from_memory(&cleaned).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 8));
+    }
+
+    #[test]
+    fn only_photos_of_a_sensible_size_are_accepted() {
+        assert_eq!(clean(b"not a photo at all"), Err(NOT_A_PHOTO));
+        assert_eq!(clean(b"GIF89a\x01\0\x01\0\0\0\0;"), Err(NOT_A_PHOTO));
+        assert_eq!(clean(&png(4, 4)[..20]), Err(NOT_A_PHOTO));
+        assert_eq!(
+            clean(&png(MAX_DIMENSION + 1, 1)),
+            Err("too large in width or height")
+        );
+        assert!(clean(&png(MAX_DIMENSION, 1)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn oversized_photos_are_turned_away_one_by_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = with_album(dir.path());
+        let cookie = tests::organizer_cookie(&s).await;
+        let huge = vec![0; MAX_PHOTO_BYTES + 1];
+        let body = multipart(
+            "2024/camping",
+            &[("huge.jpg", &huge), ("small.png", &png(4, 4))],
+        );
+        let response = tests::send(&s, multipart_request(body, Some(&cookie))).await;
+        let result = tests::text(response).await;
+        assert!(result.contains("1 of 2 photos added"), "{result}");
+        assert!(result.contains("huge.jpg: larger than 20 MB"), "{result}");
+        let album = dir.path().join(gallery::DIR).join("2024/camping");
+        assert!(album.join("small.jpg").exists());
+        assert!(!album.join("huge.jpg").exists());
+    }
+
+    #[test]
+    fn file_names_are_made_safe() {
+        assert_eq!(file_stem("IMG_2041 (1).HEIC.jpg"), "img-2041-1-heic");
+        assert_eq!(file_stem("Pongal Fest.png"), "pongal-fest");
+        assert_eq!(file_stem("பொங்கல்.jpg"), "photo");
+        assert_eq!(file_stem(".jpg"), "photo");
+        assert_eq!(file_stem(&"a".repeat(100)).len(), 60);
+    }
+
+    #[test]
+    fn colliding_names_are_numbered() {
+        let dir = tempfile::tempdir().unwrap();
+        let names: Vec<String> = (0..3)
+            .map(|i| save(dir.path(), "pongal", &[i]).unwrap())
+            .collect();
+        assert_eq!(names, ["pongal.jpg", "pongal-2.jpg", "pongal-3.jpg"]);
+        assert_eq!(std::fs::read(dir.path().join("pongal-3.jpg")).unwrap(), [2]);
+    }
+
+    #[tokio::test]
+    async fn uploading_needs_an_organizer() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = with_album(dir.path());
+        let body = multipart("2024/camping", &[("new.png", &png(4, 4))]);
+        let response = tests::send(&s, multipart_request(body, None)).await;
+        assert!(response.status().is_redirection());
+        assert_eq!(response.headers()[header::LOCATION], "/admin/login");
+        let album = dir.path().join(gallery::DIR).join("2024/camping");
+        assert!(!album.join("new.jpg").exists());
+    }
+
+    #[tokio::test]
+    async fn uploaded_photos_show_in_the_gallery_at_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = with_album(dir.path());
+        let cookie = tests::organizer_cookie(&s).await;
+        let body = multipart(
+            "2024/camping",
+            &[
+                ("First.png", &png(4, 4)),
+                ("notes.txt", b"not a photo"),
+                ("empty.jpg", b""),
+            ],
+        );
+        let response = tests::send(&s, multipart_request(body, Some(&cookie))).await;
+        let result = tests::text(response).await;
+        assert!(result.contains("1 of 2 photos added"), "{result}");
+        assert!(result.contains("Saved as first-2.jpg"), "{result}");
+        assert!(result.contains("notes.txt: not a JPEG, PNG or WebP photo"));
+
+        let years = gallery::scan(dir.path());
+        let files: Vec<&str> = years[0].albums[0]
+            .photos
+            .iter()
+            .map(|photo| photo.path.as_str())
+            .collect();
+        assert_eq!(
+            files,
+            ["2024/camping/first-2.jpg", "2024/camping/first.jpg"]
+        );
+    }
+}
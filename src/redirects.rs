@@ -0,0 +1,456 @@
+//! Short links such as `/tny25`. They live in `redirects.toml` in the
+//! content directory and are served by the router's fallback, so a link
+//! never shadows a page, and a code naming a page is rejected. Each visit
+//! is stored in `link_clicks`; a link outside its active window shows a
+//! friendly page instead of a 404.
+
+use std::collections::HashSet;
+
+use axum::{
+    extract::State,
+    http::{header::LOCATION, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use chrono::NaiveDate;
+use maud::{html, Markup};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::Organizer,
+    content::{Content, ContentFile},
+    events::today,
+    page::error_fragment,
+    seo::Meta,
+    store::Record,
+    ClientState,
+};
+
+pub const FILE_NAME: &str = "redirects.toml";
+
+/// First path segments the router serves itself. A link with one of these
+/// codes could never be followed.
+const RESERVED: &[&str] = &[
+    "about",
+    "admin",
+    "announcements",
+    "assets",
+    "byLaw",
+    "calendar",
+    "certificates",
+    "enrollment_guide",
+    "events",
+    "faq",
+    "gallery",
+    "healthz",
+    "hiking_club",
+    "home",
+    "join",
+    "join_response",
+    "library",
+    "photo-consent",
+    "readyz",
+    "resized",
+    "running_club",
+    "sponsors",
+    "tamil_school",
+    "tickets",
+    "vattam",
+    "version",
+    "volunteer-hours",
+    "walking_club",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Redirect {
+    pub code: String,
+    pub target: String,
+    pub from: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    /// 301 or 302.
+    #[serde(default = "found")]
+    pub status: u16,
+}
+
+fn found() -> u16 {
+    302
+}
+
+/// Where a link stands on a given day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    Scheduled,
+    Active,
+    Expired,
+}
+
+impl Redirect {
+    pub fn window(&self, today: NaiveDate) -> Window {
+        if self.from.is_some_and(|from| today < from) {
+            Window::Scheduled
+        } else if self.until.is_some_and(|until| today > until) {
+            Window::Expired
+        } else {
+            Window::Active
+        }
+    }
+
+    fn active_dates(&self) -> String {
+        let day = |date: NaiveDate| date.format("%b %-d, %Y").to_string();
+        match (self.from, self.until) {
+            (Some(from), Some(until)) => format!("{} – {}", day(from), day(until)),
+            (Some(from), None) => format!("from {}", day(from)),
+            (None, Some(until)) => format!("until {}", day(until)),
+            (None, None) => "always".to_string(),
+        }
+    }
+}
+
+/// Layout of `redirects.toml`: a list of `[[redirect]]` tables.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Redirects {
+    #[serde(rename = "redirect", default)]
+    pub redirects: Vec<Redirect>,
+}
+
+impl Content for Redirects {
+    fn validate(&self) -> Result<(), String> {
+        let mut codes = HashSet::new();
+        for redirect in &self.redirects {
+            let code = &redirect.code;
+            if code.is_empty()
+                || !code
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "code {code:?} may only contain letters, digits, dashes and underscores"
+                ));
+            }
+            if RESERVED.contains(&code.as_str()) {
+                return Err(format!("code {code:?} is the address of a page"));
+            }
+            if !codes.insert(code) {
+                return Err(format!("code {code:?} is used by more than one redirect"));
+            }
+            let target = &redirect.target;
+            if !(target.starts_with('/')
+                || target.starts_with("https://")
+                || target.starts_with("http://"))
+            {
+                return Err(format!(
+                    "redirect {code:?} has to lead to a path starting with / or to an http(s) address"
+                ));
+            }
+            if let (Some(from), Some(until)) = (redirect.from, redirect.until) {
+                if until < from {
+                    return Err(format!("redirect {code:?} ends before it starts"));
+                }
+            }
+            if ![301, 302].contains(&redirect.status) {
+                return Err(format!("redirect {code:?} has to use status 301 or 302"));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub type RedirectsFile = ContentFile<Redirects>;
+
+impl Redirects {
+    pub fn find(&self, code: &str) -> Option<&Redirect> {
+        self.redirects.iter().find(|redirect| redirect.code == code)
+    }
+}
+
+/// One visit to a short link.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Click {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub code: String,
+    pub at: DateTime,
+}
+
+impl Record for Click {
+    const COLLECTION: &'static str = "link_clicks";
+}
+
+/// Follows the short link named by a path the router does not know, or
+/// answers 404 like any other unknown path.
+pub async fn follow(State(s): State<ClientState>, method: Method, uri: Uri) -> Response {
+    let code = uri.path().trim_start_matches('/');
+    let redirects = s.redirects.get();
+    let redirect = match method {
+        Method::GET | Method::HEAD => redirects.find(code),
+        _ => None,
+    };
+    let Some(redirect) = redirect else {
+        return crate::not_found().await.into_response();
+    };
+    match redirect.window(today()) {
+        Window::Active => {}
+        window => return inactive(window),
+    }
+
+    let click = Click {
+        id: ObjectId::new(),
+        code: redirect.code.clone(),
+        at: DateTime::now(),
+    };
+    if let Err(e) = s.repo::<Click>().insert(&click).await {
+        tracing::warn!(code = %redirect.code, error = %e, "could not count click");
+    }
+    let status = match redirect.status {
+        301 => StatusCode::MOVED_PERMANENTLY,
+        _ => StatusCode::FOUND,
+    };
+    (status, [(LOCATION, redirect.target.clone())]).into_response()
+}
+
+/// The friendly page for a link that is not active today.
+fn inactive(window: Window) -> Response {
+    let (status, heading, message) = match window {
+        Window::Scheduled => (
+            StatusCode::NOT_FOUND,
+            "This link is not active yet",
+            "The page it leads to has not been published yet. Please check back closer to the event.",
+        ),
+        _ => (
+            StatusCode::GONE,
+            "This link has expired",
+            "The event or form it led to is over.",
+        ),
+    };
+    let meta = Meta::new(heading, message);
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen py-16 px-4" {
+            div class="max-w-md mx-auto bg-white p-8 rounded-lg shadow-lg text-center space-y-4" {
+                h1 class="text-2xl font-bold text-gray-900" { (heading) }
+                p class="text-gray-700" { (message) }
+                p {
+                    a href="/events" class="text-blue-600 hover:underline" { "See our upcoming events" }
+                    " or go to the "
+                    a href="/" class="text-blue-600 hover:underline" { "home page" }
+                    "."
+                }
+            }
+        }
+    };
+    (status, meta, content).into_response()
+}
+
+/// Organizer list of short links with their click counts.
+pub async fn links_page(_: Organizer, State(s): State<ClientState>) -> (Meta, Markup) {
+    let redirects = s.redirects.get();
+    let today = today();
+    let clicks = s.repo::<Click>();
+    let mut counts = Vec::new();
+    for redirect in &redirects.redirects {
+        counts.push(clicks.count(doc! { "code": &redirect.code }).await);
+    }
+    let table = match counts.into_iter().collect::<Result<Vec<_>, _>>() {
+        Ok(counts) => {
+            html! {
+                table class="w-full bg-white rounded-lg shadow text-left" {
+                    thead {
+                        tr class="border-b" {
+                            th class="p-2" { "Link" }
+                            th class="p-2" { "Leads to" }
+                            th class="p-2" { "Active" }
+                            th class="p-2" { "Status" }
+                            th class="p-2 text-right" { "Clicks" }
+                        }
+                    }
+                    tbody {
+                        @for (redirect, clicks) in redirects.redirects.iter().zip(counts) {
+                            tr class="border-b" {
+                                td class="p-2 font-mono" { "/" (redirect.code) }
+                                td class="p-2 break-all" { (redirect.target) }
+                                td class="p-2" {
+                                    (redirect.active_dates())
+                                    @match redirect.window(today) {
+                                        Window::Scheduled => { " (not yet)" }
+                                        Window::Expired => { " (expired)" }
+                                        Window::Active => {}
+                                    }
+                                }
+                                td class="p-2" { (redirect.status) }
+                                td class="p-2 text-right" { (clicks) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "could not count clicks");
+            error_fragment("Could not load the click counts.")
+        }
+    };
+    let meta = Meta::new("Short Links", "Short links and how often they are used.");
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-5xl mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-2 text-center" { "Short Links" }
+                p class="text-center mb-6 text-gray-700" {
+                    "Links are set in " code { (FILE_NAME) } " in the content directory."
+                }
+                (table)
+            }
+        }
+    };
+    (meta, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::header;
+
+    use super::*;
+    use crate::{nav, seo, tests};
+
+    const REDIRECTS: &str = r#"
+[[redirect]]
+code = "tny"
+target = "/events"
+
+[[redirect]]
+code = "old"
+target = "https://example.com/form"
+until = "2000-01-31"
+
+[[redirect]]
+code = "soon"
+target = "/events"
+from = "2999-01-01"
+"#;
+
+    fn with_redirects(dir: &std::path::Path) -> ClientState {
+        let path = dir.join(FILE_NAME);
+        std::fs::write(&path, REDIRECTS).unwrap();
+        ClientState {
+            redirects: Arc::new(RedirectsFile::load(path).expect("test redirects are valid")),
+            ..tests::test_state()
+        }
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn validate(toml: &str) -> Result<(), String> {
+        toml::from_str::<Redirects>(toml).unwrap().validate()
+    }
+
+    #[test]
+    fn a_link_is_active_from_its_first_through_its_last_day() {
+        let redirect = Redirect {
+            code: "tny".to_string(),
+            target: "/events".to_string(),
+            from: Some(date("2025-04-01")),
+            until: Some(date("2025-04-30")),
+            status: 302,
+        };
+        assert_eq!(redirect.window(date("2025-03-31")), Window::Scheduled);
+        assert_eq!(redirect.window(date("2025-04-01")), Window::Active);
+        assert_eq!(redirect.window(date("2025-04-30")), Window::Active);
+        assert_eq!(redirect.window(date("2025-05-01")), Window::Expired);
+
+        let open = Redirect {
+            from: None,
+            until: None,
+            ..redirect
+        };
+        assert_eq!(open.window(date("1999-01-01")), Window::Active);
+        assert_eq!(open.window(date("2999-01-01")), Window::Active);
+    }
+
+    #[test]
+    fn codes_have_to_be_unique() {
+        let error = validate(
+            r#"
+[[redirect]]
+code = "tny"
+target = "/events"
+
+[[redirect]]
+code = "tny"
+target = "/join"
+"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("more than one"), "{error}");
+    }
+
+    #[test]
+    fn codes_cannot_name_a_page() {
+        for code in ["events", "join", "admin", "assets"] {
+            let toml = format!("[[redirect]]\ncode = \"{code}\"\ntarget = \"/\"\n");
+            let error = validate(&toml).unwrap_err();
+            assert!(error.contains("address of a page"), "{code}: {error}");
+        }
+        assert!(validate(REDIRECTS).is_ok());
+    }
+
+    #[test]
+    fn every_page_in_the_menu_is_reserved() {
+        for page in nav::targets(true).iter().chain(seo::EXTRA_PAGES) {
+            let segment = page.trim_start_matches('/').split('/').next().unwrap();
+            assert!(
+                segment.is_empty() || RESERVED.contains(&segment),
+                "{page} is not reserved"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn following_a_link_counts_the_click() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = with_redirects(dir.path());
+        let response = tests::get(&s, "/tny", None).await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(response.headers()[header::LOCATION], "/events");
+        tests::get(&s, "/tny", None).await;
+
+        let clicks = s.repo::<Click>();
+        assert_eq!(clicks.count(doc! { "code": "tny" }).await.unwrap(), 2);
+        assert_eq!(clicks.count(doc! {}).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn inactive_links_are_not_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = with_redirects(dir.path());
+        let expired = tests::get(&s, "/old", None).await;
+        assert_eq!(expired.status(), StatusCode::GONE);
+        assert!(tests::text(expired).await.contains("This link has expired"));
+        let scheduled = tests::get(&s, "/soon", None).await;
+        assert_eq!(scheduled.status(), StatusCode::NOT_FOUND);
+        assert!(tests::text(scheduled).await.contains("not active yet"));
+        assert_eq!(s.repo::<Click>().count(doc! {}).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn organizers_see_the_clicks_of_each_link() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = with_redirects(dir.path());
+        for _ in 0..3 {
+            tests::get(&s, "/tny", None).await;
+        }
+        let cookie = tests::organizer_cookie(&s).await;
+        let page = tests::text(tests::get(&s, "/admin/links", Some(&cookie)).await).await;
+        assert!(
+            page.contains(r#"<td class="p-2 text-right">3</td>"#),
+            "{page}"
+        );
+        assert!(
+            page.contains(r#"<td class="p-2 text-right">0</td>"#),
+            "{page}"
+        );
+    }
+}
@@ -0,0 +1,616 @@
+//! On-site event registration. A family registers once per event with its
+//! adult and child counts; when the event's capacity is reached further
+//! families join the waitlist. Registrants are kept in the `registrations`
+//! collection and receive a confirmation email through the outbox, with a
+//! [ticket](crate::tickets) once their place is confirmed.
+
+use axum::{
+    extract::{Path, State},
+    Form,
+};
+use chrono::NaiveDate;
+use lettre::message::Mailbox;
+use maud::{html, Markup};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::Organizer,
+    events::{Event, NativeRegistration, Registration},
+    links::PHONE,
+    outbox::{self, Attachment, QueuedEmail},
+    page::error_fragment,
+    seo::Meta,
+    store::{Record, StoreError},
+    tickets::{self, Purpose},
+    ClientState,
+};
+
+/// Most people one registration may bring.
+const MAX_PARTY: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrantStatus {
+    Confirmed,
+    /// Registered after the event was full.
+    Waitlisted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Registrant {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    /// Slug of the event.
+    pub event: String,
+    pub name: String,
+    pub email: String,
+    pub phone: String,
+    pub adults: i32,
+    pub children: i32,
+    pub status: RegistrantStatus,
+    pub created_at: DateTime,
+    /// When the family arrived at the event.
+    #[serde(default)]
+    pub checked_in_at: Option<DateTime>,
+}
+
+impl Record for Registrant {
+    const COLLECTION: &'static str = "registrations";
+}
+
+impl Registrant {
+    pub fn attendees(&self) -> i32 {
+        self.adults + self.children
+    }
+}
+
+/// Registrants of one event, oldest first.
+pub async fn registrants(s: &ClientState, slug: &str) -> Result<Vec<Registrant>, StoreError> {
+    let mut registrants = s.repo::<Registrant>().find(doc! { "event": slug }).await?;
+    registrants.sort_by_key(|registrant| registrant.created_at);
+    Ok(registrants)
+}
+
+/// Head counts of one event's registrations.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Counts {
+    /// Confirmed attendees, adults and children together.
+    pub confirmed: i32,
+    pub waitlisted: i32,
+    /// Attendees who have arrived.
+    pub checked_in: i32,
+    /// Confirmed registrations.
+    pub families: i32,
+    pub families_checked_in: i32,
+}
+
+impl Counts {
+    pub fn of(registrants: &[Registrant]) -> Counts {
+        registrants
+            .iter()
+            .fold(Counts::default(), |mut counts, registrant| {
+                match registrant.status {
+                    RegistrantStatus::Confirmed => {
+                        counts.confirmed += registrant.attendees();
+                        counts.families += 1;
+                    }
+                    RegistrantStatus::Waitlisted => counts.waitlisted += registrant.attendees(),
+                }
+                if registrant.checked_in_at.is_some() {
+                    counts.checked_in += registrant.attendees();
+                    counts.families_checked_in += 1;
+                }
+                counts
+            })
+    }
+
+    /// Places left under `capacity`, or `None` when it is unlimited.
+    pub fn left(&self, native: &NativeRegistration) -> Option<i32> {
+        native
+            .capacity
+            .map(|capacity| (capacity as i32 - self.confirmed).max(0))
+    }
+}
+
+fn ended() -> Markup {
+    html! { p class="text-base text-gray-600" { "This event has ended." } }
+}
+
+/// Registration status and, while it is open, the form, for the event page.
+pub async fn section(s: &ClientState, event: &Event, today: NaiveDate) -> Markup {
+    if !event.is_upcoming(today) {
+        return ended();
+    }
+    let native = match &event.registration {
+        None => {
+            return html! {
+                p class="text-base text-gray-600" { "No registration is needed; just come along." }
+            }
+        }
+        Some(Registration::Link(link)) => {
+            return html! {
+                p class="text-base text-green-700 font-semibold" { "Registration is open." }
+                a href=(link) target="_blank" rel="noopener noreferrer"
+                    class="self-start text-white bg-orange-600 hover:bg-red-600 px-6 py-3 rounded-lg text-lg font-medium" {
+                    "Register"
+                }
+            }
+        }
+        Some(Registration::Native(native)) => native,
+    };
+    if !event.registration_open(today) {
+        return html! { p class="text-base text-gray-600" { "Registration has closed." } };
+    }
+    let counts = match registrants(s, &event.slug).await {
+        Ok(registrants) => Counts::of(&registrants),
+        Err(e) => {
+            tracing::error!(event = %event.slug, error = %e, "could not count registrations");
+            return error_fragment(&format!(
+                "Registration is unavailable right now. Please try again later or call us at {PHONE}."
+            ));
+        }
+    };
+    let left = counts.left(native);
+    if left == Some(0) && !native.waitlist {
+        return html! { p class="text-base text-gray-600" { "Sorry, this event is full." } };
+    }
+    html! {
+        div id="register" class="flex flex-col gap-4" {
+            h2 class="text-xl font-semibold text-gray-900" { "Register" }
+            @match left {
+                Some(0) => {
+                    p class="text-base text-orange-700 font-semibold" {
+                        "This event is full. Register to join the waitlist and we will contact you if places open up."
+                    }
+                }
+                Some(left) => {
+                    p class="text-base text-green-700 font-semibold" { "Registration is open. " (left) " places left." }
+                }
+                None => {
+                    p class="text-base text-green-700 font-semibold" { "Registration is open." }
+                }
+            }
+            (form(event))
+        }
+    }
+}
+
+fn form(event: &Event) -> Markup {
+    let action = format!("{}/register", event.url());
+    html! {
+        form method="post" action=(action) hx-post=(action) hx-target="#register" hx-swap="outerHTML" class="space-y-4" {
+            div {
+                label for="name" class="block text-sm font-medium text-gray-700" { "Name" }
+                input type="text" id="name" name="name" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {}
+            }
+            div {
+                label for="email" class="block text-sm font-medium text-gray-700" { "Email" }
+                input type="email" id="email" name="email" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {}
+            }
+            div {
+                label for="phone" class="block text-sm font-medium text-gray-700" { "Phone Number" }
+                input type="tel" id="phone" name="phone" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {}
+            }
+            div class="flex space-x-4" {
+                div class="w-1/2" {
+                    label for="adults" class="block text-sm font-medium text-gray-700" { "Adults" }
+                    input type="number" id="adults" name="adults" min="1" max=(MAX_PARTY) value="1" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {}
+                }
+                div class="w-1/2" {
+                    label for="children" class="block text-sm font-medium text-gray-700" { "Children" }
+                    input type="number" id="children" name="children" min="0" max=(MAX_PARTY) value="0" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {}
+                }
+            }
+            div class="text-center" {
+                button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Register" }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterForm {
+    name: String,
+    email: String,
+    phone: String,
+    adults: u32,
+    children: u32,
+}
+
+/// The form again with a message above it, for a submission that was
+/// turned down.
+fn retry(event: &Event, message: &str) -> Markup {
+    html! {
+        div id="register" class="flex flex-col gap-4" {
+            (error_fragment(message))
+            (form(event))
+        }
+    }
+}
+
+fn confirmation_email(s: &ClientState, event: &Event, registrant: &Registrant) -> String {
+    let mut body = format!(
+        "Dear {},\n\nThank you for registering for {}.\n\nDate: {}\n",
+        registrant.name,
+        event.title,
+        event.dates()
+    );
+    if let Some(times) = event.times() {
+        body.push_str(&format!("Time: {times}\n"));
+    }
+    if let Some(location) = event.location() {
+        body.push_str(&format!("Place: {location}\n"));
+    }
+    body.push_str(&format!(
+        "Adults: {}\nChildren: {}\n\n",
+        registrant.adults, registrant.children
+    ));
+    match registrant.status {
+        RegistrantStatus::Confirmed => {
+            let code = tickets::code(s, Purpose::Ticket, registrant.id);
+            body.push_str(&format!(
+                "Your registration is confirmed. We look forward to seeing you!\n\n\
+                 Please show the attached QR code at the entrance. You can also open your ticket at\n\
+                 {}\n\
+                 or give the volunteers this ticket code: {code}",
+                tickets::url(s, &code)
+            ));
+        }
+        RegistrantStatus::Waitlisted => body.push_str(
+            "The event is full right now, so you are on the waitlist. We will contact you if places open up.",
+        ),
+    }
+    body.push_str(&format!(
+        "\n\nEvent details: {}{}\n\nNJ Thiruvalluvar Tamil Sangam\n",
+        s.config.site_url,
+        event.url()
+    ));
+    body
+}
+
+pub async fn register(
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+    Form(form): Form<RegisterForm>,
+) -> Markup {
+    let events = s.events.get();
+    let Some(event) = events.find(&slug) else {
+        return error_fragment("That event does not exist.");
+    };
+    let Some(native) = event
+        .native_registration()
+        .filter(|_| event.registration_open(crate::events::today()))
+    else {
+        return error_fragment("Registration for this event is closed.");
+    };
+
+    let name = form.name.trim();
+    if name.is_empty() {
+        return retry(event, "Please tell us your name.");
+    }
+    let Ok(to) = form.email.trim().parse::<Mailbox>() else {
+        return retry(
+            event,
+            "That email address does not look right. Please check it and try again.",
+        );
+    };
+    if form.adults == 0 {
+        return retry(event, "At least one adult has to come along.");
+    }
+    // Checked apart before adding so that huge numbers cannot overflow.
+    if form.adults > MAX_PARTY
+        || form.children > MAX_PARTY
+        || form.adults + form.children > MAX_PARTY
+    {
+        return retry(
+            event,
+            &format!("One registration can include at most {MAX_PARTY} people. Please register larger groups separately or call us at {PHONE}."),
+        );
+    }
+
+    let registrant = {
+        // Held from counting to saving so two families cannot both take the
+        // last places.
+        let _guard = s.registration_lock.lock().await;
+        let existing = match registrants(&s, &slug).await {
+            Ok(existing) => existing,
+            Err(e) => {
+                tracing::error!(event = %slug, error = %e, "could not load registrations");
+                return retry(event, &format!("Sorry, we could not complete your registration right now. Please try again later or call us at {PHONE}."));
+            }
+        };
+        let email = to.email.to_string();
+        if existing
+            .iter()
+            .any(|registrant| registrant.email.eq_ignore_ascii_case(&email))
+        {
+            return retry(
+                event,
+                "This email address is already registered for this event. Please check your inbox for the confirmation.",
+            );
+        }
+        let party = (form.adults + form.children) as i32;
+        let fits = Counts::of(&existing)
+            .left(native)
+            .is_none_or(|left| party <= left);
+        let status = match (fits, native.waitlist) {
+            (true, _) => RegistrantStatus::Confirmed,
+            (false, true) => RegistrantStatus::Waitlisted,
+            (false, false) => {
+                return retry(
+                    event,
+                    "Sorry, there are not enough places left for your group.",
+                )
+            }
+        };
+        let registrant = Registrant {
+            id: ObjectId::new(),
+            event: slug.clone(),
+            name: name.to_string(),
+            email,
+            phone: form.phone.trim().to_string(),
+            adults: form.adults as i32,
+            children: form.children as i32,
+            status,
+            created_at: DateTime::now(),
+            checked_in_at: None,
+        };
+        if let Err(e) = s.repo::<Registrant>().insert(&registrant).await {
+            tracing::error!(event = %slug, error = %e, "could not save registration");
+            return retry(event, &format!("Sorry, we could not complete your registration right now. Please try again later or call us at {PHONE}."));
+        }
+        registrant
+    };
+    tracing::info!(event = %slug, registrant_id = %registrant.id, status = ?registrant.status, "registration saved");
+
+    let mut email = QueuedEmail::new(
+        &to,
+        format!("Your registration for {}", event.title),
+        confirmation_email(&s, event, &registrant),
+    );
+    if registrant.status == RegistrantStatus::Confirmed {
+        let ticket = tickets::url(&s, &tickets::code(&s, Purpose::Ticket, registrant.id));
+        match tickets::png(&ticket) {
+            Ok(png) => email = email.attach(Attachment::new("ticket.png", "image/png", png)),
+            Err(e) => {
+                tracing::error!(registrant_id = %registrant.id, error = %e, "could not draw ticket QR code")
+            }
+        }
+    }
+    if let Err(e) = outbox::enqueue(&s, email).await {
+        tracing::error!(registrant_id = %registrant.id, error = %e, "could not queue confirmation email");
+    }
+
+    html! {
+        div id="register" class="flex flex-col gap-2 p-4 rounded-lg bg-green-50 border border-green-200" {
+            @match registrant.status {
+                RegistrantStatus::Confirmed => {
+                    h2 class="text-xl font-semibold text-green-800" { "You are registered, " (registrant.name) "!" }
+                }
+                RegistrantStatus::Waitlisted => {
+                    h2 class="text-xl font-semibold text-orange-800" { "You are on the waitlist, " (registrant.name) "." }
+                    p { "We will contact you if places open up." }
+                }
+            }
+            p { "We have sent the details to " (registrant.email) "." }
+        }
+    }
+}
+
+/// Organizer overview of every event registered on this site.
+pub async fn overview(_: Organizer, State(s): State<ClientState>) -> (Meta, Markup) {
+    let events = s.events.get();
+    let mut rows = Vec::new();
+    for event in events
+        .events
+        .iter()
+        .filter(|event| event.native_registration().is_some())
+    {
+        let counts = match registrants(&s, &event.slug).await {
+            Ok(registrants) => Counts::of(&registrants),
+            Err(e) => {
+                tracing::error!(event = %event.slug, error = %e, "could not count registrations");
+                Counts::default()
+            }
+        };
+        rows.push((event, counts));
+    }
+    rows.sort_by_key(|(event, _)| std::cmp::Reverse(event.date));
+    let meta = Meta::new("Registrations", "Registrations for events.");
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-4xl mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-6 text-center" { "Registrations" }
+                @if rows.is_empty() {
+                    p class="text-center" { "No event takes registrations on this site." }
+                }
+                @for (event, counts) in &rows {
+                    div class="bg-white p-6 rounded-lg shadow-lg mb-4" {
+                        p class="text-xl font-semibold" { (event.title) }
+                        p class="text-gray-700" { (event.dates()) }
+                        p {
+                            (counts.confirmed)
+                            @if let Some(capacity) = event.native_registration().and_then(|native| native.capacity) {
+                                " of " (capacity)
+                            }
+                            " attendees confirmed, " (counts.waitlisted) " on the waitlist, "
+                            (counts.checked_in) " checked in"
+                        }
+                        div class="flex gap-4 mt-2" {
+                            a href=(format!("/admin/registrations/{}", event.slug)) class="text-blue-600 hover:underline" { "Registrants" }
+                            a href=(format!("/admin/checkin/{}", event.slug)) class="text-blue-600 hover:underline" { "Check-in" }
+                        }
+                    }
+                }
+            }
+        }
+    };
+    (meta, content)
+}
+
+/// Organizer list of one event's registrants.
+pub async fn list(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+) -> (Meta, Markup) {
+    let events = s.events.get();
+    let title = events
+        .find(&slug)
+        .map_or(slug.clone(), |event| event.title.clone());
+    let content = match registrants(&s, &slug).await {
+        Ok(registrants) => {
+            let counts = Counts::of(&registrants);
+            html! {
+                p class="mb-4 text-center" {
+                    (registrants.len()) " registrations: " (counts.confirmed) " attendees confirmed, "
+                    (counts.waitlisted) " on the waitlist"
+                }
+                div class="overflow-x-auto bg-white rounded-lg shadow-lg" {
+                    table class="min-w-full text-left text-sm" {
+                        thead class="bg-gray-100" {
+                            tr {
+                                th class="p-2" { "Name" }
+                                th class="p-2" { "Email" }
+                                th class="p-2" { "Phone" }
+                                th class="p-2" { "Adults" }
+                                th class="p-2" { "Children" }
+                                th class="p-2" { "Status" }
+                                th class="p-2" { "Registered" }
+                                th class="p-2" { "Checked in" }
+                            }
+                        }
+                        tbody {
+                            @for registrant in &registrants {
+                                tr class="border-t" {
+                                    td class="p-2" { (registrant.name) }
+                                    td class="p-2" { (registrant.email) }
+                                    td class="p-2" { (registrant.phone) }
+                                    td class="p-2" { (registrant.adults) }
+                                    td class="p-2" { (registrant.children) }
+                                    td class="p-2" {
+                                        @match registrant.status {
+                                            RegistrantStatus::Confirmed => "Confirmed",
+                                            RegistrantStatus::Waitlisted => "Waitlisted",
+                                        }
+                                    }
+                                    td class="p-2" { (registrant.created_at.try_to_rfc3339_string().unwrap_or_default()) }
+                                    td class="p-2" {
+                                        @if let Some(at) = registrant.checked_in_at {
+                                            (at.try_to_rfc3339_string().unwrap_or_default())
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!(event = %slug, error = %e, "could not load registrations");
+            error_fragment("Could not load the registrations.")
+        }
+    };
+    let meta = Meta::new(
+        format!("Registrations: {title}"),
+        "Registrations for one event.",
+    );
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-5xl mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-6 text-center" { (title) }
+                (content)
+            }
+        }
+    };
+    (meta, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use mongodb::bson::doc;
+
+    use super::*;
+    use crate::tests;
+
+    const EVENTS: &str = r#"
+[[event]]
+title = "Pongal"
+slug = "pongal"
+date = "2099-01-15"
+registration = { capacity = 10 }
+"#;
+
+    const WAITLIST_OFF: &str = r#"
+[[event]]
+title = "Pongal"
+slug = "pongal"
+date = "2099-01-15"
+registration = { capacity = 10, waitlist = false }
+"#;
+
+    async fn register(s: &ClientState, email: &str, adults: &str, children: &str) -> String {
+        let form =
+            format!("name=Meena&email={email}&phone=555&adults={adults}&children={children}");
+        let response = tests::post_form(s, "/events/pongal/register", &form, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        tests::text(response).await
+    }
+
+    #[tokio::test]
+    async fn huge_party_sizes_are_turned_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = tests::with_events(tests::test_state(), dir.path(), EVENTS);
+        let max = u32::MAX.to_string();
+        for (adults, children) in [(max.as_str(), "1"), ("1", max.as_str()), (&max, &max)] {
+            let page = register(&s, "meena@example.com", adults, children).await;
+            assert!(
+                page.contains("at most 20 people"),
+                "{adults} adults and {children} children were not turned down"
+            );
+        }
+        let saved = s.repo::<Registrant>().find(doc! {}).await.unwrap();
+        assert!(saved.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_party_that_fits_is_confirmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = tests::with_events(tests::test_state(), dir.path(), EVENTS);
+        register(&s, "meena@example.com", "2", "3").await;
+        let saved = s.repo::<Registrant>().find(doc! {}).await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!((saved[0].adults, saved[0].children), (2, 3));
+        assert_eq!(saved[0].status, RegistrantStatus::Confirmed);
+    }
+
+    #[tokio::test]
+    async fn a_party_larger_than_what_is_left_is_waitlisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = tests::with_events(tests::test_state(), dir.path(), EVENTS);
+        register(&s, "meena@example.com", "2", "3").await;
+        let page = register(&s, "ravi@example.com", "4", "2").await;
+        assert!(page.contains("You are on the waitlist"));
+        let saved = s
+            .repo::<Registrant>()
+            .find(doc! { "email": "ravi@example.com" })
+            .await
+            .unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].status, RegistrantStatus::Waitlisted);
+    }
+
+    #[tokio::test]
+    async fn without_a_waitlist_a_party_that_does_not_fit_is_turned_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = tests::with_events(tests::test_state(), dir.path(), WAITLIST_OFF);
+        register(&s, "meena@example.com", "2", "3").await;
+        let page = register(&s, "ravi@example.com", "4", "2").await;
+        assert!(page.contains("not enough places left"));
+        let saved = s.repo::<Registrant>().find(doc! {}).await.unwrap();
+        assert_eq!(saved.len(), 1);
+    }
+}
@@ -0,0 +1,501 @@
+//! Program schedules: the performance slots of an event, kept in the
+//! `schedule` collection so organizers can edit them from the admin pages
+//! while the event runs. The public timetable at `/events/{slug}/schedule`
+//! polls for changes, so the slot organizers mark as performing shows up on
+//! every phone in the hall within seconds.
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Form,
+};
+use chrono::NaiveTime;
+use maud::{html, Markup};
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::Organizer,
+    events::Event,
+    page::error_fragment,
+    seo::Meta,
+    store::{Record, StoreError},
+    ClientState,
+};
+
+/// Seconds between refreshes of the public timetable.
+const POLL_SECS: u32 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Slot {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    /// Slug of the event.
+    pub event: String,
+    pub start: NaiveTime,
+    pub end: Option<NaiveTime>,
+    /// Name of the performance, e.g. "Bharatanatyam".
+    pub title: String,
+    pub performer: String,
+    /// e.g. "5-8 years" or "Adults"; may be empty.
+    #[serde(default)]
+    pub age_group: String,
+    pub stage: String,
+    /// Set on the one slot on stage right now.
+    #[serde(default)]
+    pub now_performing: bool,
+}
+
+impl Record for Slot {
+    const COLLECTION: &'static str = "schedule";
+}
+
+impl Slot {
+    fn times(&self) -> String {
+        let start = self.start.format("%-I:%M %p");
+        match self.end {
+            Some(end) => format!("{start} – {}", end.format("%-I:%M %p")),
+            None => start.to_string(),
+        }
+    }
+}
+
+/// Slots of one event in running order.
+pub async fn slots(s: &ClientState, slug: &str) -> Result<Vec<Slot>, StoreError> {
+    let mut slots = s.repo::<Slot>().find(doc! { "event": slug }).await?;
+    slots.sort_by(|a, b| (a.start, &a.stage).cmp(&(b.start, &b.stage)));
+    Ok(slots)
+}
+
+/// Whether the event has a program to show.
+pub async fn published(s: &ClientState, slug: &str) -> bool {
+    match s.repo::<Slot>().find_one(doc! { "event": slug }).await {
+        Ok(slot) => slot.is_some(),
+        Err(e) => {
+            tracing::error!(event = %slug, error = %e, "could not load schedule");
+            false
+        }
+    }
+}
+
+fn live_url(event: &Event) -> String {
+    format!("{}/schedule/live", event.url())
+}
+
+/// The timetable, refreshing itself while the page is open.
+fn timetable(event: &Event, slots: &[Slot]) -> Markup {
+    let now = slots.iter().filter(|slot| slot.now_performing);
+    html! {
+        div id="timetable" hx-get=(live_url(event)) hx-trigger={ "every " (POLL_SECS) "s" } hx-swap="outerHTML"
+            class="flex flex-col gap-3" {
+            @for slot in now {
+                div class="p-4 rounded-lg bg-orange-600 text-white shadow-lg" {
+                    p class="text-sm uppercase tracking-wide" { "Now performing · " (slot.stage) }
+                    p class="text-2xl font-bold" { (slot.title) }
+                    p { (slot.performer) }
+                }
+            }
+            @if slots.is_empty() {
+                p class="text-center text-gray-700" { "The program will be published here closer to the event." }
+            }
+            ol class="flex flex-col gap-2" {
+                @for slot in slots {
+                    li class={ "p-3 rounded-lg shadow bg-white flex gap-4 items-start" @if slot.now_performing { " ring-4 ring-orange-500" } } {
+                        div class="w-24 shrink-0 font-semibold text-gray-900" { (slot.times()) }
+                        div class="flex-grow" {
+                            p class="font-semibold text-gray-900" { (slot.title) }
+                            p class="text-gray-700" { (slot.performer) }
+                            p class="text-sm text-gray-500" {
+                                (slot.stage)
+                                @if !slot.age_group.is_empty() { " · " (slot.age_group) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn load(s: &ClientState, event: &Event) -> Markup {
+    match slots(s, &event.slug).await {
+        Ok(slots) => timetable(event, &slots),
+        Err(e) => {
+            tracing::error!(event = %event.slug, error = %e, "could not load schedule");
+            html! {
+                div id="timetable" hx-get=(live_url(event)) hx-trigger={ "every " (POLL_SECS) "s" } hx-swap="outerHTML" {
+                    (error_fragment("The schedule is unavailable right now."))
+                }
+            }
+        }
+    }
+}
+
+pub async fn schedule_page(State(s): State<ClientState>, Path(slug): Path<String>) -> Response {
+    let events = s.events.get();
+    let Some(event) = events.find(&slug) else {
+        return crate::not_found().await.into_response();
+    };
+    let timetable = load(&s, event).await;
+    let meta = Meta::new(
+        format!("Program: {}", event.title),
+        format!("Program schedule of {}.", event.title),
+    );
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen py-8 px-4" {
+            div class="max-w-2xl mx-auto flex flex-col gap-4" {
+                a href=(event.url()) hx-get=(event.url()) hx-target="#page" hx-push-url="true" class="text-blue-600 hover:underline text-sm" {
+                    "← " (event.title)
+                }
+                h1 class="text-3xl font-extrabold text-gray-900" { "Program Schedule" }
+                (timetable)
+            }
+        }
+    };
+    (meta, content).into_response()
+}
+
+pub async fn live(State(s): State<ClientState>, Path(slug): Path<String>) -> Response {
+    let events = s.events.get();
+    let Some(event) = events.find(&slug) else {
+        return crate::not_found().await.into_response();
+    };
+    load(&s, event).await.into_response()
+}
+
+/// Fields of the slot forms. Times come from `<input type="time">`.
+#[derive(Debug, Deserialize)]
+pub struct SlotForm {
+    start: String,
+    #[serde(default)]
+    end: String,
+    title: String,
+    performer: String,
+    #[serde(default)]
+    age_group: String,
+    stage: String,
+}
+
+impl SlotForm {
+    fn into_slot(self, id: ObjectId, event: &str) -> Result<Slot, &'static str> {
+        let time = |text: &str| NaiveTime::parse_from_str(text.trim(), "%H:%M");
+        let start = time(&self.start).map_err(|_| "Please enter a start time.")?;
+        let end = match self.end.trim() {
+            "" => None,
+            end => Some(time(end).map_err(|_| "The end time is not a time.")?),
+        };
+        if end.is_some_and(|end| end <= start) {
+            return Err("The end time has to be after the start time.");
+        }
+        let title = self.title.trim();
+        let performer = self.performer.trim();
+        if title.is_empty() || performer.is_empty() {
+            return Err("Please fill in the performance and the performer.");
+        }
+        let stage = match self.stage.trim() {
+            "" => "Main stage",
+            stage => stage,
+        };
+        Ok(Slot {
+            id,
+            event: event.to_string(),
+            start,
+            end,
+            title: title.to_string(),
+            performer: performer.to_string(),
+            age_group: self.age_group.trim().to_string(),
+            stage: stage.to_string(),
+            now_performing: false,
+        })
+    }
+}
+
+const INPUT: &str = "p-1 border border-gray-300 rounded-md w-full";
+
+/// Inputs of one slot, blank for a new one.
+fn slot_inputs(slot: Option<&Slot>) -> Markup {
+    let time = |time: Option<NaiveTime>| time.map(|time| time.format("%H:%M").to_string());
+    html! {
+        input type="time" name="start" value=[time(slot.map(|slot| slot.start))] required class=(INPUT) aria-label="Start";
+        input type="time" name="end" value=[time(slot.and_then(|slot| slot.end))] class=(INPUT) aria-label="End";
+        input type="text" name="title" value=[slot.map(|slot| &slot.title)] placeholder="Performance" required class=(INPUT);
+        input type="text" name="performer" value=[slot.map(|slot| &slot.performer)] placeholder="Performer" required class=(INPUT);
+        input type="text" name="age_group" value=[slot.map(|slot| &slot.age_group)] placeholder="Age group" class=(INPUT);
+        input type="text" name="stage" value=[slot.map(|slot| &slot.stage)] placeholder="Main stage" class=(INPUT);
+    }
+}
+
+/// The organizer's editor: one form per slot and one to add a slot.
+async fn editor(s: &ClientState, event: &Event, error: Option<&str>) -> Markup {
+    let base = format!("/admin/schedule/{}", event.slug);
+    let slots = match slots(s, &event.slug).await {
+        Ok(slots) => slots,
+        Err(e) => {
+            tracing::error!(event = %event.slug, error = %e, "could not load schedule");
+            return html! {
+                div id="schedule-editor" { (error_fragment("Could not load the schedule.")) }
+            };
+        }
+    };
+    let button = "px-3 py-1 rounded-md text-white";
+    html! {
+        div id="schedule-editor" class="flex flex-col gap-3" {
+            @if let Some(error) = error {
+                (error_fragment(error))
+            }
+            @if slots.iter().any(|slot| slot.now_performing) {
+                form method="post" action={ (base) "/clear" } hx-post={ (base) "/clear" } hx-target="#schedule-editor" hx-swap="outerHTML" {
+                    button type="submit" class={ (button) " bg-gray-600 hover:bg-gray-700" } { "Clear now performing" }
+                }
+            }
+            @for slot in &slots {
+                div class={ "bg-white p-3 rounded-lg shadow flex flex-col gap-2" @if slot.now_performing { " ring-4 ring-orange-500" } } {
+                    form method="post" action={ (base) "/" (slot.id) } hx-post={ (base) "/" (slot.id) } hx-target="#schedule-editor" hx-swap="outerHTML"
+                        class="grid grid-cols-2 md:grid-cols-7 gap-2" {
+                        (slot_inputs(Some(slot)))
+                        button type="submit" class={ (button) " bg-orange-500 hover:bg-orange-600" } { "Save" }
+                    }
+                    div class="flex gap-2" {
+                        @if !slot.now_performing {
+                            form method="post" action={ (base) "/" (slot.id) "/now" } hx-post={ (base) "/" (slot.id) "/now" } hx-target="#schedule-editor" hx-swap="outerHTML" {
+                                button type="submit" class={ (button) " bg-green-600 hover:bg-green-700" } { "Now performing" }
+                            }
+                        }
+                        form method="post" action={ (base) "/" (slot.id) "/delete" } hx-post={ (base) "/" (slot.id) "/delete" } hx-target="#schedule-editor" hx-swap="outerHTML"
+                            hx-confirm="Remove this slot?" {
+                            button type="submit" class={ (button) " bg-red-600 hover:bg-red-700" } { "Remove" }
+                        }
+                    }
+                }
+            }
+            form method="post" action=(base) hx-post=(base) hx-target="#schedule-editor" hx-swap="outerHTML"
+                class="bg-white p-3 rounded-lg shadow grid grid-cols-2 md:grid-cols-7 gap-2" {
+                (slot_inputs(None))
+                button type="submit" class={ (button) " bg-orange-500 hover:bg-orange-600" } { "Add" }
+            }
+        }
+    }
+}
+
+fn editor_meta(event: &Event) -> Meta {
+    Meta::new(
+        format!("Schedule: {}", event.title),
+        "Edit the program schedule.",
+    )
+}
+
+/// Organizer list of events whose schedule can be edited.
+pub async fn overview(_: Organizer, State(s): State<ClientState>) -> (Meta, Markup) {
+    let events = s.events.get();
+    let mut events: Vec<&Event> = events.events.iter().collect();
+    events.sort_by_key(|event| std::cmp::Reverse(event.date));
+    let meta = Meta::new("Program Schedules", "Edit the program schedules of events.");
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-3xl mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-6 text-center" { "Program Schedules" }
+                @for event in events {
+                    a href=(format!("/admin/schedule/{}", event.slug)) class="block bg-white p-4 rounded-lg shadow mb-3 hover:bg-gray-50" {
+                        p class="font-semibold" { (event.title) }
+                        p class="text-gray-600" { (event.dates()) }
+                    }
+                }
+            }
+        }
+    };
+    (meta, content)
+}
+
+pub async fn editor_page(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+) -> Response {
+    let events = s.events.get();
+    let Some(event) = events.find(&slug) else {
+        return crate::not_found().await.into_response();
+    };
+    let editor = editor(&s, event, None).await;
+    let schedule = format!("{}/schedule", event.url());
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen" {
+            div class="max-w-6xl mx-auto p-8 flex flex-col gap-4" {
+                h1 class="text-3xl font-bold text-center" { "Schedule: " (event.title) }
+                p class="text-center" {
+                    a href=(schedule) class="text-blue-600 hover:underline" { "Public timetable" }
+                }
+                (editor)
+            }
+        }
+    };
+    (editor_meta(event), content).into_response()
+}
+
+/// Runs `change` on the event's schedule and answers with the editor.
+async fn edit<F>(s: &ClientState, slug: &str, change: F) -> Response
+where
+    F: std::future::Future<Output = Result<(), String>>,
+{
+    let events = s.events.get();
+    let Some(event) = events.find(slug) else {
+        return crate::not_found().await.into_response();
+    };
+    let error = change.await.err();
+    (editor_meta(event), editor(s, event, error.as_deref()).await).into_response()
+}
+
+fn store_error(e: StoreError) -> String {
+    tracing::error!(error = %e, "could not change schedule");
+    "Could not save the change. Please try again.".to_string()
+}
+
+pub async fn add(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+    Form(form): Form<SlotForm>,
+) -> Response {
+    let change = async {
+        let slot = form.into_slot(ObjectId::new(), &slug)?;
+        s.repo::<Slot>().insert(&slot).await.map_err(store_error)
+    };
+    edit(&s, &slug, change).await
+}
+
+pub async fn save(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path((slug, id)): Path<(String, ObjectId)>,
+    Form(form): Form<SlotForm>,
+) -> Response {
+    let change = async {
+        let slot = form.into_slot(id, &slug)?;
+        let set = doc! {
+            "start": slot.start.to_string(),
+            "end": slot.end.map(|end| end.to_string()),
+            "title": slot.title,
+            "performer": slot.performer,
+            "age_group": slot.age_group,
+            "stage": slot.stage,
+        };
+        s.repo::<Slot>()
+            .update(doc! { "_id": id, "event": &slug }, set)
+            .await
+            .map(drop)
+            .map_err(store_error)
+    };
+    edit(&s, &slug, change).await
+}
+
+pub async fn delete(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path((slug, id)): Path<(String, ObjectId)>,
+) -> Response {
+    let change = async {
+        s.repo::<Slot>()
+            .delete(doc! { "_id": id, "event": &slug })
+            .await
+            .map(drop)
+            .map_err(store_error)
+    };
+    edit(&s, &slug, change).await
+}
+
+pub async fn now_performing(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path((slug, id)): Path<(String, ObjectId)>,
+) -> Response {
+    let change = async {
+        let repo = s.repo::<Slot>();
+        repo.update(doc! { "event": &slug }, doc! { "now_performing": false })
+            .await
+            .map_err(store_error)?;
+        repo.update(
+            doc! { "_id": id, "event": &slug },
+            doc! { "now_performing": true },
+        )
+        .await
+        .map(drop)
+        .map_err(store_error)
+    };
+    edit(&s, &slug, change).await
+}
+
+pub async fn clear(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+) -> Response {
+    let change = async {
+        s.repo::<Slot>()
+            .update(doc! { "event": &slug }, doc! { "now_performing": false })
+            .await
+            .map(drop)
+            .map_err(store_error)
+    };
+    edit(&s, &slug, change).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(start: &str, end: &str) -> SlotForm {
+        SlotForm {
+            start: start.to_string(),
+            end: end.to_string(),
+            title: " Bharatanatyam ".to_string(),
+            performer: "Grade 3".to_string(),
+            age_group: String::new(),
+            stage: String::new(),
+        }
+    }
+
+    #[test]
+    fn the_end_has_to_be_after_the_start() {
+        for end in ["10:00", "09:30"] {
+            assert_eq!(
+                form("10:00", end)
+                    .into_slot(ObjectId::new(), "pongal")
+                    .err(),
+                Some("The end time has to be after the start time.")
+            );
+        }
+        assert_eq!(
+            form("", "10:00").into_slot(ObjectId::new(), "pongal").err(),
+            Some("Please enter a start time.")
+        );
+        assert_eq!(
+            form("10:00", "soon")
+                .into_slot(ObjectId::new(), "pongal")
+                .err(),
+            Some("The end time is not a time.")
+        );
+    }
+
+    #[test]
+    fn optional_fields_may_be_left_blank() {
+        let id = ObjectId::new();
+        let slot = form(" 10:00 ", " ").into_slot(id, "pongal").unwrap();
+        assert_eq!(slot.id, id);
+        assert_eq!(slot.event, "pongal");
+        assert_eq!(slot.start, NaiveTime::from_hms_opt(10, 0, 0).unwrap());
+        assert_eq!(slot.end, None);
+        assert_eq!(slot.title, "Bharatanatyam");
+        assert_eq!(slot.age_group, "");
+        assert_eq!(slot.stage, "Main stage");
+        assert!(!slot.now_performing);
+    }
+
+    #[test]
+    fn the_performance_and_performer_are_required() {
+        let mut blank = form("10:00", "");
+        blank.performer = "  ".to_string();
+        assert_eq!(
+            blank.into_slot(ObjectId::new(), "pongal").err(),
+            Some("Please fill in the performance and the performer.")
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! What search engines and link previews see: the per-page [`Meta`] that
+//! handlers attach to their response, `/sitemap.xml` and `/robots.txt`.
+
+use axum::{
+    extract::State,
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, IntoResponseParts, ResponseParts},
+    routing::get,
+    Router,
+};
+
+use crate::{nav, ClientState};
+
+const SITE_NAME: &str = "NJ Thiruvalluvar Tamil Sangam";
+const DEFAULT_DESCRIPTION: &str = "NJ Thiruvalluvar Tamil Sangam brings the Tamil community of New Jersey together through festivals, Tamil schools, clubs and volunteering.";
+const DEFAULT_IMAGE: &str = "/assets/img/logo.jpg";
+
+/// Public pages that are not in the menu but should still be indexed.
+pub const EXTRA_PAGES: &[&str] = &["/enrollment_guide", "/volunteer-hours", "/photo-consent"];
+/// Menu pages that are still "under construction" placeholders. They stay
+/// out of the sitemap until they have content.
+const PLACEHOLDERS: &[&str] = &["/sponsors", "/library", "/faq"];
+
+/// Title, description and preview image of one page. Returned alongside a
+/// handler's markup; [`page::full_page`](crate::page::full_page) renders it
+/// into the document head.
+#[derive(Debug, Clone)]
+pub struct Meta {
+    pub title: String,
+    pub description: String,
+    /// Site-relative path of the Open Graph image.
+    pub image: String,
+}
+
+impl Meta {
+    pub fn new(title: impl Into<String>, description: impl Into<String>) -> Meta {
+        Meta {
+            title: title.into(),
+            description: description.into(),
+            image: DEFAULT_IMAGE.to_string(),
+        }
+    }
+
+    pub fn image(mut self, image: impl Into<String>) -> Meta {
+        self.image = image.into();
+        self
+    }
+
+    /// The title shown in the browser tab.
+    pub fn full_title(&self) -> String {
+        if self.title.is_empty() {
+            SITE_NAME.to_string()
+        } else {
+            format!("{} | {SITE_NAME}", self.title)
+        }
+    }
+}
+
+impl Default for Meta {
+    fn default() -> Meta {
+        Meta::new("", DEFAULT_DESCRIPTION)
+    }
+}
+
+impl IntoResponseParts for Meta {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+        Ok(res)
+    }
+}
+
+pub fn seo_router() -> Router<ClientState> {
+    Router::new()
+        .route("/sitemap.xml", get(sitemap))
+        .route("/robots.txt", get(robots))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+async fn sitemap(State(s): State<ClientState>) -> impl IntoResponse {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    let events = s.events.get();
+    let pages = nav::targets(false)
+        .into_iter()
+        .chain(EXTRA_PAGES.iter().copied())
+        .filter(|path| !PLACEHOLDERS.contains(path))
+        .map(String::from)
+        .chain(events.events.iter().map(|event| event.url()));
+    for path in pages {
+        let url = xml_escape(&format!("{}{path}", s.config.site_url));
+        xml.push_str(&format!("  <url><loc>{url}</loc></url>\n"));
+    }
+    xml.push_str("</urlset>\n");
+    ([(CONTENT_TYPE, "application/xml")], xml)
+}
+
+async fn robots(State(s): State<ClientState>) -> String {
+    let robots = &s.config.robots;
+    let mut text = String::from("User-agent: *\n");
+    if robots.allow_indexing {
+        for path in &robots.disallow {
+            text.push_str(&format!("Disallow: {path}\n"));
+        }
+        text.push_str(&format!("\nSitemap: {}/sitemap.xml\n", s.config.site_url));
+    } else {
+        text.push_str("Disallow: /\n");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests;
+
+    #[tokio::test]
+    async fn sitemap_lists_pages_with_content_only() {
+        let s = tests::test_state();
+        let xml = tests::text(sitemap(State(s)).await.into_response()).await;
+        assert!(xml.contains("<loc>https://njtts.org/events</loc>"));
+        assert!(xml.contains("<loc>https://njtts.org/enrollment_guide</loc>"));
+        for path in PLACEHOLDERS {
+            assert!(!xml.contains(&format!("{path}<")), "{path} is listed");
+        }
+        assert!(!xml.contains("/admin"));
+    }
+
+    #[tokio::test]
+    async fn placeholders_are_still_under_construction() {
+        let s = tests::test_state();
+        for path in PLACEHOLDERS {
+            let page = tests::text(tests::get(&s, path, None).await).await;
+            assert!(page.contains("under construction"), "{path} has content now");
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, Bson, Document};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{self, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+use super::{Store, StoreError};
+
+/// How often the database is pinged to detect an outage or a recovery.
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// One pending insert, stored as a line of relaxed extended JSON so ids and
+/// dates survive the round trip.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    collection: String,
+    document: serde_json::Value,
+}
+
+/// Wraps a database so the site keeps accepting submissions while it is
+/// unreachable. Inserts that cannot reach the database are appended to a
+/// local journal file and replayed once it is back.
+///
+/// Reads, updates and deletes replay the journal first, so that they never
+/// miss a submission the site has accepted: a capacity count must include
+/// a registration that was journaled a moment ago. While the journal
+/// cannot be replayed they fail, as they would with the database down.
+pub struct JournaledStore {
+    inner: Arc<dyn Store>,
+    path: PathBuf,
+    available: AtomicBool,
+    /// Whether the journal may hold inserts the database has not seen.
+    pending: AtomicBool,
+    /// Serializes appends against replays.
+    file: Mutex<()>,
+}
+
+impl JournaledStore {
+    pub fn new(inner: Arc<dyn Store>, path: PathBuf) -> JournaledStore {
+        JournaledStore {
+            inner,
+            available: AtomicBool::new(false),
+            pending: AtomicBool::new(path.exists()),
+            path,
+            file: Mutex::new(()),
+        }
+    }
+
+    /// Whether the last check found the database reachable.
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+
+    async fn append(&self, collection: &str, doc: Document) -> Result<(), StoreError> {
+        let entry = Entry {
+            collection: collection.to_string(),
+            document: Bson::Document(doc).into_relaxed_extjson(),
+        };
+        let mut line = serde_json::to_string(&entry).map_err(StoreError::Journal)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(StoreError::Io)?;
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(StoreError::Io)?;
+        file.sync_data().await.map_err(StoreError::Io)?;
+        self.pending.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Inserts every journaled document into the database. Entries that
+    /// could not be replayed are written back so nothing is lost.
+    async fn replay(&self) -> Result<usize, StoreError> {
+        let text = match fs::read_to_string(&self.path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.pending.store(false, Ordering::Release);
+                return Ok(0);
+            }
+            Err(e) => return Err(StoreError::Io(e)),
+        };
+        let lines: Vec<&str> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let mut replayed = 0;
+        for (i, line) in lines.iter().enumerate() {
+            let result = match serde_json::from_str::<Entry>(line) {
+                Ok(entry) => match Bson::try_from(entry.document) {
+                    Ok(Bson::Document(doc)) => self.inner.insert(&entry.collection, doc).await,
+                    _ => {
+                        tracing::warn!(entry = %line, "skipping malformed journal entry");
+                        Ok(())
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!(entry = %line, error = %e, "skipping unreadable journal entry");
+                    Ok(())
+                }
+            };
+            match result {
+                Ok(()) => replayed += 1,
+                // Already inserted by an earlier, interrupted replay.
+                Err(e) if e.is_duplicate_key() => replayed += 1,
+                Err(e) => {
+                    let rest: String = lines[i..].iter().map(|l| format!("{l}\n")).collect();
+                    fs::write(&self.path, rest).await.map_err(StoreError::Io)?;
+                    return Err(e);
+                }
+            }
+        }
+        fs::remove_file(&self.path).await.map_err(StoreError::Io)?;
+        self.pending.store(false, Ordering::Release);
+        Ok(replayed)
+    }
+
+    /// Replays the journal if it holds anything, before an operation that
+    /// has to see every insert.
+    async fn catch_up(&self) -> Result<(), StoreError> {
+        if !self.pending.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let _guard = self.file.lock().await;
+        let replayed = self.replay().await?;
+        if replayed > 0 {
+            tracing::info!(replayed, "replayed journaled records into the database");
+        }
+        Ok(())
+    }
+
+    /// Checks the database once, replaying the journal if it is reachable.
+    pub async fn check(&self) {
+        let reachable = self.inner.ping().await;
+        let _guard = self.file.lock().await;
+        match reachable {
+            Ok(()) => match self.replay().await {
+                Ok(replayed) => {
+                    if replayed > 0 {
+                        tracing::info!(replayed, "replayed journaled records into the database");
+                    }
+                    if !self.available.swap(true, Ordering::Relaxed) {
+                        tracing::info!("database is available");
+                    }
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "journal replay stopped");
+                    self.available.store(false, Ordering::Relaxed);
+                }
+            },
+            Err(e) => {
+                if self.available.swap(false, Ordering::Relaxed) {
+                    tracing::warn!(error = %e, "database is unavailable, journaling writes");
+                }
+            }
+        }
+    }
+
+    /// Starts checking the database in the background until the process
+    /// exits.
+    pub fn spawn_monitor(self: &Arc<Self>) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            loop {
+                store.check().await;
+                tokio::time::sleep(CHECK_INTERVAL).await;
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl Store for JournaledStore {
+    async fn insert(&self, collection: &str, mut doc: Document) -> Result<(), StoreError> {
+        // Fix the id now so a replay after a partial failure is idempotent.
+        if !doc.contains_key("_id") {
+            doc.insert("_id", ObjectId::new());
+        }
+        if self.is_available() {
+            match self.inner.insert(collection, doc.clone()).await {
+                Err(e @ StoreError::Mongo(_)) if !e.is_duplicate_key() => {
+                    tracing::warn!(collection, error = %e, "database insert failed, journaling it");
+                    self.available.store(false, Ordering::Relaxed);
+                }
+                result => return result,
+            }
+        }
+        let _guard = self.file.lock().await;
+        self.append(collection, doc).await
+    }
+
+    async fn find(&self, collection: &str, filter: Document) -> Result<Vec<Document>, StoreError> {
+        self.catch_up().await?;
+        self.inner.find(collection, filter).await
+    }
+
+    async fn count(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+        self.catch_up().await?;
+        self.inner.count(collection, filter).await
+    }
+
+    async fn update(
+        &self,
+        collection: &str,
+        filter: Document,
+        set: Document,
+    ) -> Result<u64, StoreError> {
+        self.catch_up().await?;
+        self.inner.update(collection, filter, set).await
+    }
+
+    async fn delete(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+        self.catch_up().await?;
+        self.inner.delete(collection, filter).await
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        self.inner.ping().await
+    }
+
+    async fn check_journal(&self) -> Option<Result<(), StoreError>> {
+        let _guard = self.file.lock().await;
+        let opened = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await;
+        Some(opened.map(drop).map_err(StoreError::Io))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use mongodb::bson::{doc, DateTime};
+
+    use super::*;
+    use crate::store::MemoryStore;
+
+    /// A database that can be taken down, or made to accept only a few
+    /// more inserts.
+    #[derive(Default)]
+    struct FlakyStore {
+        inner: MemoryStore,
+        down: AtomicBool,
+        /// Inserts accepted before failing; `None` for no limit.
+        inserts_left: std::sync::Mutex<Option<usize>>,
+        inserts: AtomicUsize,
+    }
+
+    impl FlakyStore {
+        fn fail(&self) -> Result<(), StoreError> {
+            if self.down.load(Ordering::Relaxed) {
+                return Err(mongodb::error::Error::custom("database is down").into());
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Store for FlakyStore {
+        async fn insert(&self, collection: &str, doc: Document) -> Result<(), StoreError> {
+            self.fail()?;
+            if let Some(left) = self.inserts_left.lock().unwrap().as_mut() {
+                if *left == 0 {
+                    return Err(mongodb::error::Error::custom("insert failed").into());
+                }
+                *left -= 1;
+            }
+            self.inserts.fetch_add(1, Ordering::Relaxed);
+            self.inner.insert(collection, doc).await
+        }
+
+        async fn find(
+            &self,
+            collection: &str,
+            filter: Document,
+        ) -> Result<Vec<Document>, StoreError> {
+            self.fail()?;
+            self.inner.find(collection, filter).await
+        }
+
+        async fn count(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+            self.fail()?;
+            self.inner.count(collection, filter).await
+        }
+
+        async fn update(
+            &self,
+            collection: &str,
+            filter: Document,
+            set: Document,
+        ) -> Result<u64, StoreError> {
+            self.fail()?;
+            self.inner.update(collection, filter, set).await
+        }
+
+        async fn delete(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+            self.fail()?;
+            self.inner.delete(collection, filter).await
+        }
+
+        async fn ping(&self) -> Result<(), StoreError> {
+            self.fail()
+        }
+    }
+
+    /// A journaled store over a database that is down, with its journal in
+    /// a fresh directory.
+    fn journaled() -> (tempfile::TempDir, Arc<FlakyStore>, JournaledStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Arc::new(FlakyStore::default());
+        database.down.store(true, Ordering::Relaxed);
+        let store = JournaledStore::new(database.clone(), dir.path().join("journal.jsonl"));
+        (dir, database, store)
+    }
+
+    fn journal_lines(store: &JournaledStore) -> usize {
+        std::fs::read_to_string(&store.path)
+            .map(|text| text.lines().count())
+            .unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn inserts_are_journaled_while_the_database_is_down() {
+        let (_dir, database, store) = journaled();
+        store.check().await;
+        assert!(!store.is_available());
+
+        let id = ObjectId::new();
+        let at = DateTime::from_millis(1_700_000_000_000);
+        store
+            .insert("users", doc! { "_id": id, "name": "Kavya", "at": at })
+            .await
+            .unwrap();
+        store
+            .insert("users", doc! { "name": "Ravi" })
+            .await
+            .unwrap();
+        assert_eq!(journal_lines(&store), 2);
+        assert!(store.find("users", doc! {}).await.is_err());
+
+        database.down.store(false, Ordering::Relaxed);
+        store.check().await;
+        assert!(store.is_available());
+        assert!(!store.path.exists());
+        let saved = database.inner.find("users", doc! {}).await.unwrap();
+        assert_eq!(saved.len(), 2);
+        assert_eq!(saved[0].get_object_id("_id").unwrap(), id);
+        assert_eq!(saved[0].get_datetime("at").unwrap(), &at);
+        assert!(saved[1].get_object_id("_id").is_ok());
+    }
+
+    #[tokio::test]
+    async fn reads_see_journaled_inserts_before_the_next_check() {
+        let (_dir, database, store) = journaled();
+        store
+            .insert("registrations", doc! { "n": 1 })
+            .await
+            .unwrap();
+
+        // Back, but the monitor has not noticed yet.
+        database.down.store(false, Ordering::Relaxed);
+        let found = store.find("registrations", doc! {}).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(!store.path.exists());
+        assert_eq!(
+            store
+                .update("registrations", doc! { "n": 1 }, doc! { "n": 2 })
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn a_failed_replay_keeps_the_rest_of_the_journal() {
+        let (_dir, database, store) = journaled();
+        for n in 0..3 {
+            store.insert("users", doc! { "n": n }).await.unwrap();
+        }
+
+        database.down.store(false, Ordering::Relaxed);
+        *database.inserts_left.lock().unwrap() = Some(1);
+        store.check().await;
+        assert!(!store.is_available());
+        assert_eq!(
+            database.inner.find("users", doc! {}).await.unwrap().len(),
+            1
+        );
+        assert_eq!(journal_lines(&store), 2);
+        assert!(
+            store.find("users", doc! {}).await.is_err(),
+            "reads must not miss the journaled inserts"
+        );
+
+        *database.inserts_left.lock().unwrap() = None;
+        store.check().await;
+        assert!(store.is_available());
+        assert!(!store.path.exists());
+        let saved = store.find("users", doc! {}).await.unwrap();
+        let numbers: Vec<i32> = saved.iter().map(|doc| doc.get_i32("n").unwrap()).collect();
+        assert_eq!(numbers, [0, 1, 2]);
+        assert_eq!(database.inserts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn a_journal_left_from_before_a_restart_is_replayed() {
+        let (dir, database, store) = journaled();
+        store.insert("users", doc! { "n": 1 }).await.unwrap();
+        drop(store);
+
+        database.down.store(false, Ordering::Relaxed);
+        let store = JournaledStore::new(database.clone(), dir.path().join("journal.jsonl"));
+        assert_eq!(store.find("users", doc! {}).await.unwrap().len(), 1);
+    }
+}
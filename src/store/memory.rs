@@ -0,0 +1,248 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, Bson, Document};
+
+use super::{Store, StoreError};
+
+/// Keeps every collection in process memory. Used for local development and
+/// tests; nothing survives a restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    collections: Mutex<HashMap<String, Vec<Document>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+fn matches(doc: &Document, filter: &Document) -> bool {
+    filter.iter().all(|(key, value)| match doc.get(key) {
+        Some(field) => field == value,
+        None => *value == Bson::Null,
+    })
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn insert(&self, collection: &str, mut doc: Document) -> Result<(), StoreError> {
+        if !doc.contains_key("_id") {
+            doc.insert("_id", ObjectId::new());
+        }
+        let mut collections = self.collections.lock().unwrap();
+        collections
+            .entry(collection.to_string())
+            .or_default()
+            .push(doc);
+        Ok(())
+    }
+
+    async fn find(&self, collection: &str, filter: Document) -> Result<Vec<Document>, StoreError> {
+        let collections = self.collections.lock().unwrap();
+        Ok(collections
+            .get(collection)
+            .map(|docs| {
+                docs.iter()
+                    .filter(|doc| matches(doc, &filter))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn count(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+        let collections = self.collections.lock().unwrap();
+        Ok(collections.get(collection).map_or(0, |docs| {
+            docs.iter().filter(|doc| matches(doc, &filter)).count() as u64
+        }))
+    }
+
+    async fn update(
+        &self,
+        collection: &str,
+        filter: Document,
+        set: Document,
+    ) -> Result<u64, StoreError> {
+        let mut collections = self.collections.lock().unwrap();
+        let mut matched = 0;
+        for doc in collections.entry(collection.to_string()).or_default() {
+            if matches(doc, &filter) {
+                doc.extend(set.clone());
+                matched += 1;
+            }
+        }
+        Ok(matched)
+    }
+
+    async fn delete(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+        let mut collections = self.collections.lock().unwrap();
+        let docs = collections.entry(collection.to_string()).or_default();
+        let before = docs.len();
+        docs.retain(|doc| !matches(doc, &filter));
+        Ok((before - docs.len()) as u64)
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mongodb::bson::doc;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::store::{Record, Repository};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        #[serde(rename = "_id")]
+        id: ObjectId,
+        author: String,
+        pinned: bool,
+    }
+
+    impl Record for Note {
+        const COLLECTION: &'static str = "notes";
+    }
+
+    fn note(author: &str, pinned: bool) -> Note {
+        Note {
+            id: ObjectId::new(),
+            author: author.to_string(),
+            pinned,
+        }
+    }
+
+    #[tokio::test]
+    async fn insert_assigns_an_id_only_when_missing() {
+        let store = MemoryStore::new();
+        let id = ObjectId::new();
+        store.insert("notes", doc! { "_id": id }).await.unwrap();
+        store.insert("notes", doc! { "n": 1 }).await.unwrap();
+        let docs = store.find("notes", doc! {}).await.unwrap();
+        assert_eq!(docs[0].get_object_id("_id").unwrap(), id);
+        assert!(docs[1].get_object_id("_id").is_ok());
+    }
+
+    #[tokio::test]
+    async fn find_matches_every_filter_field_by_equality() {
+        let store = MemoryStore::new();
+        store
+            .insert("notes", doc! { "author": "a", "pinned": true })
+            .await
+            .unwrap();
+        store
+            .insert("notes", doc! { "author": "a", "pinned": false })
+            .await
+            .unwrap();
+        store
+            .insert("notes", doc! { "author": "b", "pinned": true })
+            .await
+            .unwrap();
+
+        let count = |filter| async { store.find("notes", filter).await.unwrap().len() };
+        assert_eq!(count(doc! {}).await, 3);
+        assert_eq!(count(doc! { "author": "a" }).await, 2);
+        assert_eq!(count(doc! { "author": "a", "pinned": true }).await, 1);
+        assert_eq!(count(doc! { "author": "c" }).await, 0);
+        assert_eq!(count(doc! { "missing": 1 }).await, 0);
+        assert!(store.find("other", doc! {}).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn count_agrees_with_find() {
+        let store = MemoryStore::new();
+        store.insert("notes", doc! { "author": "a" }).await.unwrap();
+        store.insert("notes", doc! { "author": "a" }).await.unwrap();
+        store.insert("notes", doc! { "author": "b" }).await.unwrap();
+        assert_eq!(store.count("notes", doc! {}).await.unwrap(), 3);
+        assert_eq!(
+            store.count("notes", doc! { "author": "a" }).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            store.count("notes", doc! { "author": "c" }).await.unwrap(),
+            0
+        );
+        assert_eq!(store.count("other", doc! {}).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn null_matches_null_and_missing_fields() {
+        let store = MemoryStore::new();
+        store.insert("notes", doc! { "at": null }).await.unwrap();
+        store.insert("notes", doc! {}).await.unwrap();
+        store.insert("notes", doc! { "at": 1 }).await.unwrap();
+        let found = store.find("notes", doc! { "at": null }).await.unwrap();
+        assert_eq!(found.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn update_sets_fields_on_matches_and_counts_them() {
+        let store = MemoryStore::new();
+        store.insert("notes", doc! { "author": "a" }).await.unwrap();
+        store.insert("notes", doc! { "author": "a" }).await.unwrap();
+        store.insert("notes", doc! { "author": "b" }).await.unwrap();
+
+        let matched = store
+            .update("notes", doc! { "author": "a" }, doc! { "pinned": true })
+            .await
+            .unwrap();
+        assert_eq!(matched, 2);
+        let pinned = store.find("notes", doc! { "pinned": true }).await.unwrap();
+        assert_eq!(pinned.len(), 2);
+        assert!(pinned.iter().all(|doc| doc.get_str("author") == Ok("a")));
+        let matched = store
+            .update("notes", doc! { "author": "c" }, doc! { "pinned": true })
+            .await
+            .unwrap();
+        assert_eq!(matched, 0);
+    }
+
+    #[tokio::test]
+    async fn delete_removes_only_matches() {
+        let store = MemoryStore::new();
+        store.insert("notes", doc! { "author": "a" }).await.unwrap();
+        store.insert("notes", doc! { "author": "b" }).await.unwrap();
+        assert_eq!(
+            store.delete("notes", doc! { "author": "a" }).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            store.delete("notes", doc! { "author": "a" }).await.unwrap(),
+            0
+        );
+        let left = store.find("notes", doc! {}).await.unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].get_str("author"), Ok("b"));
+    }
+
+    #[tokio::test]
+    async fn repository_round_trips_records() {
+        let repo = Repository::<Note>::new(Arc::new(MemoryStore::new()));
+        let first = note("a", false);
+        let second = note("b", true);
+        repo.insert(&first).await.unwrap();
+        repo.insert(&second).await.unwrap();
+
+        assert_eq!(repo.find(doc! {}).await.unwrap(), [first, second]);
+        let found = repo.find_one(doc! { "pinned": true }).await.unwrap();
+        assert_eq!(found.map(|note| note.author), Some("b".to_string()));
+        assert_eq!(repo.find_one(doc! { "author": "c" }).await.unwrap(), None);
+
+        repo.update(doc! { "author": "a" }, doc! { "pinned": true })
+            .await
+            .unwrap();
+        assert_eq!(repo.find(doc! { "pinned": true }).await.unwrap().len(), 2);
+        repo.delete(doc! { "author": "b" }).await.unwrap();
+        let left = repo.find(doc! {}).await.unwrap();
+        assert_eq!(left.len(), 1);
+        assert!(left[0].pinned);
+    }
+}
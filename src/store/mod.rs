@@ -0,0 +1,139 @@
+//! Persistence for everything the site records: members, contact messages
+//! and the rest. Handlers talk to a typed [`Repository`], which stores BSON
+//! documents in whichever [`Store`] backend the configuration selected.
+
+use std::{fmt, marker::PhantomData, sync::Arc};
+
+use async_trait::async_trait;
+use mongodb::bson::{self, Document};
+use serde::{de::DeserializeOwned, Serialize};
+
+mod journal;
+mod memory;
+mod mongo;
+
+pub use journal::JournaledStore;
+pub use memory::MemoryStore;
+pub use mongo::MongoStore;
+
+#[derive(Debug)]
+pub enum StoreError {
+    Mongo(mongodb::error::Error),
+    Serialize(bson::ser::Error),
+    Deserialize(bson::de::Error),
+    Journal(serde_json::Error),
+    Io(std::io::Error),
+}
+
+impl StoreError {
+    /// Whether an insert failed because a document with the same `_id`
+    /// already exists.
+    pub fn is_duplicate_key(&self) -> bool {
+        use mongodb::error::{ErrorKind, WriteFailure};
+        match self {
+            StoreError::Mongo(e) => matches!(
+                e.kind.as_ref(),
+                ErrorKind::Write(WriteFailure::WriteError(write)) if write.code == 11000
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Mongo(e) => write!(f, "database error: {e}"),
+            StoreError::Serialize(e) => write!(f, "could not encode record: {e}"),
+            StoreError::Deserialize(e) => write!(f, "could not decode record: {e}"),
+            StoreError::Journal(e) => write!(f, "could not encode journal entry: {e}"),
+            StoreError::Io(e) => write!(f, "journal I/O failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<mongodb::error::Error> for StoreError {
+    fn from(e: mongodb::error::Error) -> Self {
+        StoreError::Mongo(e)
+    }
+}
+
+/// A document database. Filters are matched by equality on top-level fields
+/// only, so every backend can evaluate them the same way. As in MongoDB, a
+/// null in a filter also matches a document without that field.
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// Inserts `doc`, assigning an `_id` if it does not have one.
+    async fn insert(&self, collection: &str, doc: Document) -> Result<(), StoreError>;
+    async fn find(&self, collection: &str, filter: Document) -> Result<Vec<Document>, StoreError>;
+    /// Counts the documents matching `filter` without loading them.
+    async fn count(&self, collection: &str, filter: Document) -> Result<u64, StoreError>;
+    /// Sets the fields in `set` on every document matching `filter` and
+    /// returns how many were matched.
+    async fn update(
+        &self,
+        collection: &str,
+        filter: Document,
+        set: Document,
+    ) -> Result<u64, StoreError>;
+    async fn delete(&self, collection: &str, filter: Document) -> Result<u64, StoreError>;
+    /// Checks that the backend is reachable.
+    async fn ping(&self) -> Result<(), StoreError>;
+    /// Checks that inserts can still be kept while the backend is
+    /// unreachable. `None` for backends that have nowhere to keep them.
+    async fn check_journal(&self) -> Option<Result<(), StoreError>> {
+        None
+    }
+}
+
+/// A type that is stored as one document in `COLLECTION`.
+pub trait Record: Serialize + DeserializeOwned + Send + Sync {
+    const COLLECTION: &'static str;
+}
+
+/// Typed access to the collection of one [`Record`] type.
+pub struct Repository<T> {
+    store: Arc<dyn Store>,
+    record: PhantomData<T>,
+}
+
+impl<T: Record> Repository<T> {
+    pub fn new(store: Arc<dyn Store>) -> Self {
+        Repository {
+            store,
+            record: PhantomData,
+        }
+    }
+
+    pub async fn insert(&self, record: &T) -> Result<(), StoreError> {
+        let doc = bson::to_document(record).map_err(StoreError::Serialize)?;
+        self.store.insert(T::COLLECTION, doc).await
+    }
+
+    pub async fn find(&self, filter: Document) -> Result<Vec<T>, StoreError> {
+        self.store
+            .find(T::COLLECTION, filter)
+            .await?
+            .into_iter()
+            .map(|doc| bson::from_document(doc).map_err(StoreError::Deserialize))
+            .collect()
+    }
+
+    pub async fn find_one(&self, filter: Document) -> Result<Option<T>, StoreError> {
+        Ok(self.find(filter).await?.into_iter().next())
+    }
+
+    pub async fn count(&self, filter: Document) -> Result<u64, StoreError> {
+        self.store.count(T::COLLECTION, filter).await
+    }
+
+    pub async fn update(&self, filter: Document, set: Document) -> Result<u64, StoreError> {
+        self.store.update(T::COLLECTION, filter, set).await
+    }
+
+    pub async fn delete(&self, filter: Document) -> Result<u64, StoreError> {
+        self.store.delete(T::COLLECTION, filter).await
+    }
+}
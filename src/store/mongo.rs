@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use mongodb::{
+    bson::{doc, Document},
+    options::ClientOptions,
+    Client, Database,
+};
+use tokio::sync::OnceCell;
+
+use super::{Store, StoreError};
+
+/// How long an operation waits for a reachable server before failing, so an
+/// outage surfaces quickly instead of hanging requests.
+const SERVER_SELECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The client is created on first use: resolving a `mongodb+srv://` URI
+/// needs DNS, and the site has to start even when that is unavailable.
+pub struct MongoStore {
+    uri: String,
+    database: String,
+    db: OnceCell<Database>,
+}
+
+impl MongoStore {
+    pub fn new(uri: &str, database: &str) -> MongoStore {
+        MongoStore {
+            uri: uri.to_string(),
+            database: database.to_string(),
+            db: OnceCell::new(),
+        }
+    }
+
+    async fn db(&self) -> Result<&Database, StoreError> {
+        self.db
+            .get_or_try_init(|| async {
+                let mut client_options = ClientOptions::parse(&self.uri).await?;
+                client_options.app_name = Some("MyApp".to_string());
+                client_options.server_selection_timeout = Some(SERVER_SELECTION_TIMEOUT);
+                let client = Client::with_options(client_options)?;
+                Ok(client.database(&self.database))
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl Store for MongoStore {
+    async fn insert(&self, collection: &str, doc: Document) -> Result<(), StoreError> {
+        self.db()
+            .await?
+            .collection::<Document>(collection)
+            .insert_one(doc)
+            .await?;
+        Ok(())
+    }
+
+    async fn find(&self, collection: &str, filter: Document) -> Result<Vec<Document>, StoreError> {
+        let mut cursor = self
+            .db()
+            .await?
+            .collection::<Document>(collection)
+            .find(filter)
+            .await?;
+        let mut docs = Vec::new();
+        while cursor.advance().await? {
+            docs.push(cursor.deserialize_current()?);
+        }
+        Ok(docs)
+    }
+
+    async fn count(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+        Ok(self
+            .db()
+            .await?
+            .collection::<Document>(collection)
+            .count_documents(filter)
+            .await?)
+    }
+
+    async fn update(
+        &self,
+        collection: &str,
+        filter: Document,
+        set: Document,
+    ) -> Result<u64, StoreError> {
+        let result = self
+            .db()
+            .await?
+            .collection::<Document>(collection)
+            .update_many(filter, doc! { "$set": set })
+            .await?;
+        Ok(result.matched_count)
+    }
+
+    async fn delete(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+        let result = self
+            .db()
+            .await?
+            .collection::<Document>(collection)
+            .delete_many(filter)
+            .await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        self.db().await?.run_command(doc! { "ping": 1 }).await?;
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-/// HTML5 Boilerplate &strs
+//! HTML5 Boilerplate &strs
 
 pub static DESCRIPTION: &str = "description";
 pub static NOT_FOUND_COMMENT: &str = "<!-- IE needs 512+ bytes: https://docs.microsoft.com/archive/blogs/ieinternals/friendly-http-error-pages -->";
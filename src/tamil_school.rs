@@ -1,5 +1,7 @@
 use maud::{html, Markup};
 
+use crate::seo::Meta;
+
 pub struct School {
     name: String,
     venue: String,
@@ -39,7 +41,7 @@ impl School {
         }
     }
 }
-pub async fn tamil_school_page() -> Markup {
+pub async fn tamil_school_page() -> (Meta, Markup) {
     let schools = vec![
             School::new(
                 "Vallalar Tamil School",
@@ -91,7 +93,7 @@ pub async fn tamil_school_page() -> Markup {
             ),
             School::new(
                 "NJ Thiruvalluvar Tamil School",
-                "309 Baldwin Rd, Parsippany, NJ 07054",
+                "309 Baldwin Rd, Parsippany, NJ 07034",
                 "Saturday 9.45 AM – 12PM",
                 "tamilschool.parsippany@gmail.com",
                 "1 973-797-9826",
@@ -139,7 +141,11 @@ pub async fn tamil_school_page() -> Markup {
             )
         ];
 
-    html! {
+    let meta = Meta::new(
+        "NJ Tamil Schools",
+        "Tamil schools across New Jersey where children learn to read, write and speak Tamil.",
+    );
+    let content = html! {
         section class="bg-white py-8 lg:py-16 px-4 mx-auto max-w-screen-md" {
             h2 class="mb-4 text-4xl tracking-tight font-extrabold text-gray-900 text-center" {"NJ Tamil Schools"}
             @for school in schools {
@@ -147,21 +153,27 @@ pub async fn tamil_school_page() -> Markup {
                 (school.as_markup())
             }
         }
-    }
+    };
+    (meta, content)
 }
-pub async fn enrollment_guide() -> Markup {
-    html! {
+pub async fn enrollment_guide() -> (Meta, Markup) {
+    let meta = Meta::new(
+        "Tamil School Enrollment Guide",
+        "How to enroll your child in a New Jersey Tamil school for the new school year.",
+    );
+    let content = html! {
         div class="container mx-auto px-4 py-8 text-center" {
                     h1 class="text-3xl font-bold mb-4" { "Enrollment Guide" }
 
                     // Embedding PDF using iframe
                     div class="w-full h-screen overflow-y-auto border border-gray-300 shadow-lg rounded-lg" {
                         iframe
-                            src="assets/img/Enrollment Guide 2024-25.pdf"
+                            src="/assets/img/Enrollment Guide 2024-25.pdf"
                             class="w-full h-full"
                             title="Embedded PDF Viewer"
                             { "Your browser does not support PDF viewing. You can download the PDF file <a href=\"{pdf_url}\">here</a> instead." }
                     }
                 }
-    }
+    };
+    (meta, content)
 }
@@ -0,0 +1,548 @@
+//! Signed tickets and event-day check-in. A ticket code is the registrant's
+//! id followed by a truncated HMAC of it and the code's [`Purpose`], keyed
+//! with the site's secret key, so codes can be neither guessed nor forged.
+//! The confirmation email carries the ticket as a QR code linking to
+//! `/tickets/{code}`; volunteers scan or type it on the check-in page to
+//! mark the family as arrived.
+
+use std::io::Cursor;
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Form,
+};
+use axum_extra::extract::SignedCookieJar;
+use hmac::{Hmac, Mac};
+use image::{ImageFormat, Luma};
+use maud::{html, Markup, PreEscaped};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use qrcode::{render::svg, QrCode};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::{
+    admin::{self, Organizer},
+    events::TIME_ZONE,
+    page::error_fragment,
+    registration::{self, Counts, Registrant, RegistrantStatus},
+    seo::Meta,
+    ClientState,
+};
+
+/// Bytes of the HMAC kept in a code; enough that forging one is hopeless
+/// while the code stays short enough to type.
+const MAC_LEN: usize = 6;
+
+/// What a signed code grants access to. A code made for one purpose never
+/// verifies for another, even for the same id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Purpose {
+    /// An event ticket, for a registration.
+    Ticket,
+    /// A volunteer's hours ledger.
+    Ledger,
+    /// A volunteer-service certificate.
+    Certificate,
+    /// A family's photo-consent page.
+    Family,
+}
+
+impl Purpose {
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Purpose::Ticket => b"ticket",
+            Purpose::Ledger => b"ledger",
+            Purpose::Certificate => b"certificate",
+            Purpose::Family => b"family",
+        }
+    }
+}
+
+fn mac(s: &ClientState, purpose: Purpose, id: ObjectId) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(s.cookie_key.signing())
+        .expect("HMAC accepts keys of any length");
+    mac.update(purpose.as_bytes());
+    mac.update(&[0]);
+    mac.update(&id.bytes());
+    mac
+}
+
+/// The signed code for `id`, e.g. the ticket code of a registration.
+pub fn code(s: &ClientState, purpose: Purpose, id: ObjectId) -> String {
+    let tag = mac(s, purpose, id).finalize().into_bytes();
+    format!("{}-{}", id.to_hex(), hex::encode(&tag[..MAC_LEN]))
+}
+
+/// The id a genuine code for `purpose` belongs to. Accepts the bare code or
+/// a URL ending in it, such as the ticket URL a scanner read from the QR
+/// code.
+pub fn verify(s: &ClientState, purpose: Purpose, input: &str) -> Option<ObjectId> {
+    let code = input.trim().trim_end_matches('/').rsplit('/').next()?;
+    let (id, tag) = code.split_once('-')?;
+    let id = ObjectId::parse_str(id).ok()?;
+    let tag = hex::decode(tag).ok().filter(|tag| tag.len() == MAC_LEN)?;
+    mac(s, purpose, id).verify_truncated_left(&tag).ok()?;
+    Some(id)
+}
+
+pub fn url(s: &ClientState, code: &str) -> String {
+    format!("{}/tickets/{code}", s.config.site_url)
+}
+
+/// The QR code for `url` as a PNG image, for email attachments.
+pub fn png(url: &str) -> Result<Vec<u8>, String> {
+    let image = QrCode::new(url)
+        .map_err(|e| e.to_string())?
+        .render::<Luma<u8>>()
+        .min_dimensions(300, 300)
+        .build();
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+fn svg(url: &str) -> Option<String> {
+    let svg = QrCode::new(url)
+        .ok()?
+        .render::<svg::Color>()
+        .min_dimensions(240, 240)
+        .build();
+    Some(svg)
+}
+
+fn local_time(at: DateTime) -> String {
+    chrono::DateTime::from_timestamp_millis(at.timestamp_millis())
+        .map(|at| at.with_timezone(&TIME_ZONE).format("%-I:%M %p").to_string())
+        .unwrap_or_default()
+}
+
+async fn registrant(s: &ClientState, id: ObjectId) -> Option<Registrant> {
+    match s.repo::<Registrant>().find_one(doc! { "_id": id }).await {
+        Ok(registrant) => registrant,
+        Err(e) => {
+            tracing::error!(registrant_id = %id, error = %e, "could not load registration");
+            None
+        }
+    }
+}
+
+/// The ticket a family shows at the entrance. Organizers also get the
+/// check-in button.
+pub async fn ticket_page(
+    State(s): State<ClientState>,
+    jar: SignedCookieJar,
+    Path(code): Path<String>,
+) -> Response {
+    let Some(id) = verify(&s, Purpose::Ticket, &code) else {
+        return crate::not_found().await.into_response();
+    };
+    let Some(registrant) = registrant(&s, id).await else {
+        return crate::not_found().await.into_response();
+    };
+    let events = s.events.get();
+    let event = events.find(&registrant.event);
+    let title = event.map_or(registrant.event.as_str(), |event| event.title.as_str());
+    let organizer = admin::is_organizer(&jar, &s);
+
+    let meta = Meta::new(format!("Ticket: {title}"), "Your ticket for the event.");
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen py-10 px-4" {
+            div class="max-w-md mx-auto bg-white p-8 rounded-lg shadow-lg flex flex-col items-center gap-3 text-center" {
+                h1 class="text-2xl font-bold" { (title) }
+                @if let Some(event) = event {
+                    p class="text-gray-700" {
+                        (event.dates())
+                        @if let Some(times) = event.times() { ", " (times) }
+                    }
+                }
+                @if let Some(svg) = svg(&url(&s, &code)) {
+                    div class="w-60" { (PreEscaped(svg)) }
+                }
+                p class="text-xl font-semibold" { (registrant.name) }
+                p { (registrant.adults) " adults, " (registrant.children) " children" }
+                p class="font-mono text-sm break-all text-gray-600" { (code) }
+                @if organizer {
+                    (checkin_box(&registrant.event, None, Some(&code)))
+                }
+            }
+        }
+    };
+    (meta, content).into_response()
+}
+
+/// What happened to a scanned ticket.
+enum Outcome {
+    CheckedIn(Registrant),
+    AlreadyIn(Registrant),
+    Rejected(String),
+}
+
+async fn check_in(s: &ClientState, slug: &str, input: &str) -> Outcome {
+    let Some(id) = verify(s, Purpose::Ticket, input) else {
+        return Outcome::Rejected("This is not a valid ticket.".to_string());
+    };
+    let Some(mut registrant) = registrant(s, id).await else {
+        return Outcome::Rejected("No registration matches this ticket.".to_string());
+    };
+    if registrant.event != slug {
+        let events = s.events.get();
+        let other = events
+            .find(&registrant.event)
+            .map_or(registrant.event.clone(), |event| event.title.clone());
+        return Outcome::Rejected(format!("This ticket is for {other}."));
+    }
+    if registrant.status == RegistrantStatus::Waitlisted {
+        return Outcome::Rejected(format!(
+            "{} is on the waitlist, not confirmed.",
+            registrant.name
+        ));
+    }
+    if registrant.checked_in_at.is_some() {
+        return Outcome::AlreadyIn(registrant);
+    }
+    let now = DateTime::now();
+    // Only sets the time if nobody else has, so that two volunteers
+    // scanning the same ticket at once do not both let the family in.
+    let result = s
+        .repo::<Registrant>()
+        .update(
+            doc! { "_id": id, "checked_in_at": null },
+            doc! { "checked_in_at": now },
+        )
+        .await;
+    match result {
+        Ok(0) => match self::registrant(s, id).await {
+            Some(registrant) => Outcome::AlreadyIn(registrant),
+            None => Outcome::Rejected("No registration matches this ticket.".to_string()),
+        },
+        Ok(_) => {
+            tracing::info!(event = %slug, registrant_id = %id, "checked in");
+            registrant.checked_in_at = Some(now);
+            Outcome::CheckedIn(registrant)
+        }
+        Err(e) => {
+            tracing::error!(registrant_id = %id, error = %e, "could not record check-in");
+            Outcome::Rejected("Could not record the check-in. Please try again.".to_string())
+        }
+    }
+}
+
+/// The result of the last scan, if any, above a form for the next one.
+/// `code` fills the form in, for the button on a ticket page.
+fn checkin_box(slug: &str, outcome: Option<&Outcome>, code: Option<&str>) -> Markup {
+    let action = format!("/admin/checkin/{slug}");
+    html! {
+        div id="checkin" class="w-full flex flex-col gap-4" {
+            @match outcome {
+                Some(Outcome::CheckedIn(registrant)) => {
+                    div class="p-4 rounded-lg bg-green-100 border border-green-300 text-green-900" {
+                        p class="text-xl font-semibold" { "Welcome, " (registrant.name) "!" }
+                        p { (registrant.adults) " adults, " (registrant.children) " children" }
+                    }
+                }
+                Some(Outcome::AlreadyIn(registrant)) => {
+                    div class="p-4 rounded-lg bg-yellow-100 border border-yellow-300 text-yellow-900" {
+                        p class="text-xl font-semibold" { (registrant.name) " is already checked in" }
+                        @if let Some(at) = registrant.checked_in_at {
+                            p { "at " (local_time(at)) }
+                        }
+                    }
+                }
+                Some(Outcome::Rejected(message)) => (error_fragment(message)),
+                None => {}
+            }
+            form method="post" action=(action) hx-post=(action) hx-target="#checkin" hx-swap="outerHTML" class="flex gap-2" {
+                @if let Some(code) = code {
+                    input type="hidden" name="code" value=(code);
+                    button type="submit" class="w-full bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Check in" }
+                } @else {
+                    input type="text" name="code" placeholder="Scan or type the ticket code" autocomplete="off" autofocus required
+                        class="flex-grow p-2 border border-gray-300 rounded-md font-mono" {}
+                    button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Check in" }
+                }
+            }
+        }
+    }
+}
+
+/// Registered and arrived head counts; refreshes itself every few seconds.
+fn counts_markup(slug: &str, counts: Option<Counts>) -> Markup {
+    html! {
+        div hx-get=(format!("/admin/checkin/{slug}/counts")) hx-trigger="every 5s" hx-swap="outerHTML"
+            class="grid grid-cols-2 gap-4 text-center" {
+            @if let Some(counts) = counts {
+                div class="bg-white p-4 rounded-lg shadow" {
+                    p class="text-3xl font-bold" { (counts.checked_in) " / " (counts.confirmed) }
+                    p class="text-gray-600" { "people checked in" }
+                }
+                div class="bg-white p-4 rounded-lg shadow" {
+                    p class="text-3xl font-bold" { (counts.families_checked_in) " / " (counts.families) }
+                    p class="text-gray-600" { "families checked in" }
+                }
+            } @else {
+                p class="col-span-2" { "Counts are unavailable right now." }
+            }
+        }
+    }
+}
+
+async fn counts(s: &ClientState, slug: &str) -> Option<Counts> {
+    match registration::registrants(s, slug).await {
+        Ok(registrants) => Some(Counts::of(&registrants)),
+        Err(e) => {
+            tracing::error!(event = %slug, error = %e, "could not count registrations");
+            None
+        }
+    }
+}
+
+pub async fn checkin_page(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+) -> Response {
+    let events = s.events.get();
+    let Some(event) = events.find(&slug) else {
+        return crate::not_found().await.into_response();
+    };
+    let counts = counts(&s, &slug).await;
+    let meta = Meta::new(format!("Check-in: {}", event.title), "Event check-in.");
+    let content = html! {
+        div class="bg-vertical-to-pink min-h-screen" {
+            div class="max-w-xl mx-auto p-8 flex flex-col gap-6" {
+                h1 class="text-3xl font-bold text-center" { "Check-in: " (event.title) }
+                (counts_markup(&slug, counts))
+                (checkin_box(&slug, None, None))
+            }
+        }
+    };
+    (meta, content).into_response()
+}
+
+pub async fn checkin_counts(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+) -> Markup {
+    counts_markup(&slug, counts(&s, &slug).await)
+}
+
+#[derive(Deserialize)]
+pub struct CheckinForm {
+    code: String,
+}
+
+pub async fn checkin(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+    Form(form): Form<CheckinForm>,
+) -> (Meta, Markup) {
+    let outcome = check_in(&s, &slug, &form.code).await;
+    let meta = Meta::new("Check-in", "Event check-in.");
+    (meta, checkin_box(&slug, Some(&outcome), None))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mongodb::bson::Document;
+
+    use super::*;
+    use crate::{
+        store::{MemoryStore, Store, StoreError},
+        tests,
+    };
+
+    fn registrant(status: RegistrantStatus) -> Registrant {
+        Registrant {
+            id: ObjectId::new(),
+            event: "pongal".to_string(),
+            name: "Meena".to_string(),
+            email: "meena@example.com".to_string(),
+            phone: String::new(),
+            adults: 2,
+            children: 1,
+            status,
+            created_at: DateTime::now(),
+            checked_in_at: None,
+        }
+    }
+
+    #[test]
+    fn codes_verify_back_to_their_id() {
+        let s = tests::test_state();
+        let id = ObjectId::new();
+        let code = code(&s, Purpose::Ticket, id);
+        assert_eq!(verify(&s, Purpose::Ticket, &code), Some(id));
+        assert_eq!(
+            verify(&s, Purpose::Ticket, &format!("  {code}\n")),
+            Some(id)
+        );
+    }
+
+    #[test]
+    fn ticket_urls_verify_like_bare_codes() {
+        let s = tests::test_state();
+        let id = ObjectId::new();
+        let ticket = url(&s, &code(&s, Purpose::Ticket, id));
+        assert_eq!(verify(&s, Purpose::Ticket, &ticket), Some(id));
+        assert_eq!(verify(&s, Purpose::Ticket, &format!("{ticket}/")), Some(id));
+    }
+
+    #[test]
+    fn tampered_codes_are_rejected() {
+        let s = tests::test_state();
+        let id = ObjectId::new();
+        let code = code(&s, Purpose::Ticket, id);
+        let (hex_id, tag) = code.split_once('-').unwrap();
+
+        let mut flipped = tag.to_string();
+        let last = if flipped.ends_with('0') { "1" } else { "0" };
+        flipped.replace_range(flipped.len() - 1.., last);
+        assert_eq!(
+            verify(&s, Purpose::Ticket, &format!("{hex_id}-{flipped}")),
+            None
+        );
+
+        let other = ObjectId::new().to_hex();
+        assert_eq!(verify(&s, Purpose::Ticket, &format!("{other}-{tag}")), None);
+        assert_eq!(verify(&s, Purpose::Ticket, hex_id), None);
+        assert_eq!(verify(&s, Purpose::Ticket, ""), None);
+
+        let other_site = tests::test_state();
+        assert_eq!(verify(&other_site, Purpose::Ticket, &code), None);
+    }
+
+    #[test]
+    fn codes_only_verify_for_their_purpose() {
+        let s = tests::test_state();
+        let id = ObjectId::new();
+        let purposes = [
+            Purpose::Ticket,
+            Purpose::Ledger,
+            Purpose::Certificate,
+            Purpose::Family,
+        ];
+        for made in purposes {
+            let code = code(&s, made, id);
+            for checked in purposes {
+                let expected = (made == checked).then_some(id);
+                assert_eq!(
+                    verify(&s, checked, &code),
+                    expected,
+                    "{made:?} code as {checked:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tags_of_the_wrong_length_are_rejected() {
+        let s = tests::test_state();
+        let code = code(&s, Purpose::Ticket, ObjectId::new());
+        assert_eq!(verify(&s, Purpose::Ticket, &code[..code.len() - 2]), None);
+        assert_eq!(verify(&s, Purpose::Ticket, &format!("{code}00")), None);
+        assert_eq!(verify(&s, Purpose::Ticket, &format!("{code}0")), None);
+    }
+
+    #[tokio::test]
+    async fn a_ticket_checks_in_once() {
+        let s = tests::test_state();
+        let family = registrant(RegistrantStatus::Confirmed);
+        s.repo::<Registrant>().insert(&family).await.unwrap();
+        let code = code(&s, Purpose::Ticket, family.id);
+
+        assert!(matches!(
+            check_in(&s, "pongal", &code).await,
+            Outcome::CheckedIn(_)
+        ));
+        match check_in(&s, "pongal", &code).await {
+            Outcome::AlreadyIn(registrant) => assert!(registrant.checked_in_at.is_some()),
+            _ => panic!("a second scan should find the family already in"),
+        }
+        assert!(matches!(
+            check_in(&s, "other", &code).await,
+            Outcome::Rejected(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn waitlisted_families_are_not_checked_in() {
+        let s = tests::test_state();
+        let family = registrant(RegistrantStatus::Waitlisted);
+        s.repo::<Registrant>().insert(&family).await.unwrap();
+        assert!(matches!(
+            check_in(&s, "pongal", &code(&s, Purpose::Ticket, family.id)).await,
+            Outcome::Rejected(_)
+        ));
+    }
+
+    /// Yields before every operation, so that concurrent requests
+    /// interleave between reading and writing as they can in production.
+    struct YieldingStore(MemoryStore);
+
+    #[async_trait::async_trait]
+    impl Store for YieldingStore {
+        async fn insert(&self, collection: &str, doc: Document) -> Result<(), StoreError> {
+            tokio::task::yield_now().await;
+            self.0.insert(collection, doc).await
+        }
+
+        async fn find(
+            &self,
+            collection: &str,
+            filter: Document,
+        ) -> Result<Vec<Document>, StoreError> {
+            tokio::task::yield_now().await;
+            self.0.find(collection, filter).await
+        }
+
+        async fn count(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+            tokio::task::yield_now().await;
+            self.0.count(collection, filter).await
+        }
+
+        async fn update(
+            &self,
+            collection: &str,
+            filter: Document,
+            set: Document,
+        ) -> Result<u64, StoreError> {
+            tokio::task::yield_now().await;
+            self.0.update(collection, filter, set).await
+        }
+
+        async fn delete(&self, collection: &str, filter: Document) -> Result<u64, StoreError> {
+            tokio::task::yield_now().await;
+            self.0.delete(collection, filter).await
+        }
+
+        async fn ping(&self) -> Result<(), StoreError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn simultaneous_scans_let_the_family_in_once() {
+        let s = ClientState {
+            store: Arc::new(YieldingStore(MemoryStore::new())),
+            ..tests::test_state()
+        };
+        let family = registrant(RegistrantStatus::Confirmed);
+        s.repo::<Registrant>().insert(&family).await.unwrap();
+        let code = code(&s, Purpose::Ticket, family.id);
+
+        let (first, second) =
+            tokio::join!(check_in(&s, "pongal", &code), check_in(&s, "pongal", &code));
+        let welcomed = [first, second]
+            .iter()
+            .filter(|outcome| matches!(outcome, Outcome::CheckedIn(_)))
+            .count();
+        assert_eq!(welcomed, 1);
+    }
+}
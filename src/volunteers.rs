@@ -0,0 +1,527 @@
+//! Volunteer sign-up. Events list their volunteer shifts in `events.toml`;
+//! members pick one on the event page and are kept in the `volunteers`
+//! collection, next to the members who joined through the join form. A
+//! background task emails everyone the day before their shift.
+
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    Form,
+};
+use chrono::{Days, NaiveDate};
+use lettre::message::Mailbox;
+use maud::{html, Markup};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::Organizer,
+    events::{today, Event, Shift},
+    links::PHONE,
+    outbox::{self, QueuedEmail},
+    page::error_fragment,
+    seo::Meta,
+    store::{Record, StoreError},
+    ClientState,
+};
+
+/// How often the reminder task looks for shifts happening tomorrow.
+const REMINDER_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Volunteer {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    /// Slug of the event.
+    pub event: String,
+    /// [`Shift::id`] of the shift.
+    pub shift: String,
+    pub name: String,
+    pub email: String,
+    pub phone: String,
+    pub created_at: DateTime,
+    /// When the day-before reminder was queued.
+    #[serde(default)]
+    pub reminded_at: Option<DateTime>,
+}
+
+impl Record for Volunteer {
+    const COLLECTION: &'static str = "volunteers";
+}
+
+/// Volunteers of one event, oldest sign-up first.
+pub async fn volunteers(s: &ClientState, slug: &str) -> Result<Vec<Volunteer>, StoreError> {
+    let mut volunteers = s.repo::<Volunteer>().find(doc! { "event": slug }).await?;
+    volunteers.sort_by_key(|volunteer| volunteer.created_at);
+    Ok(volunteers)
+}
+
+/// Volunteers signed up for `shift`.
+fn on_shift<'a>(
+    volunteers: &'a [Volunteer],
+    shift: &'a Shift,
+) -> impl Iterator<Item = &'a Volunteer> {
+    volunteers
+        .iter()
+        .filter(move |volunteer| volunteer.shift == shift.id)
+}
+
+fn filled(volunteers: &[Volunteer], shift: &Shift) -> u32 {
+    on_shift(volunteers, shift).count() as u32
+}
+
+/// Shifts that can still be signed up for on `today`, with the places left.
+fn open_shifts<'a>(
+    event: &'a Event,
+    volunteers: &[Volunteer],
+    today: NaiveDate,
+) -> Vec<(&'a Shift, u32)> {
+    event
+        .shifts
+        .iter()
+        .filter(|shift| shift.day(event) >= today)
+        .map(|shift| {
+            let left = shift.needed.saturating_sub(filled(volunteers, shift));
+            (shift, left)
+        })
+        .filter(|(_, left)| *left > 0)
+        .collect()
+}
+
+/// The volunteer section of the event page; empty for events without
+/// shifts.
+pub async fn section(s: &ClientState, event: &Event, today: NaiveDate) -> Markup {
+    if event.shifts.is_empty() || !event.is_upcoming(today) {
+        return html! {};
+    }
+    let volunteers = match volunteers(s, &event.slug).await {
+        Ok(volunteers) => volunteers,
+        Err(e) => {
+            tracing::error!(event = %event.slug, error = %e, "could not load volunteers");
+            return html! {};
+        }
+    };
+    html! {
+        section id="volunteer" class="flex flex-col gap-4" {
+            h2 class="text-xl font-semibold text-gray-900" { "Volunteer" }
+            (form(event, &open_shifts(event, &volunteers, today)))
+        }
+    }
+}
+
+fn form(event: &Event, shifts: &[(&Shift, u32)]) -> Markup {
+    if shifts.is_empty() {
+        return html! {
+            p class="text-base text-gray-600" { "Every volunteer shift is filled. Thank you!" }
+        };
+    }
+    let action = format!("{}/volunteer", event.url());
+    html! {
+        p class="text-base text-gray-700" { "We need helping hands. Pick a shift that suits you:" }
+        form method="post" action=(action) hx-post=(action) hx-target="#volunteer" hx-swap="outerHTML" class="space-y-4" {
+            fieldset class="space-y-2" {
+                legend class="sr-only" { "Shift" }
+                @for (i, (shift, left)) in shifts.iter().enumerate() {
+                    label class="flex items-start gap-2 p-2 border border-gray-200 rounded-md" {
+                        input type="radio" name="shift" value=(shift.id) required checked[i == 0] class="mt-1";
+                        span {
+                            span class="font-semibold" { (shift.role) }
+                            " · " (shift.when(event))
+                            span class="block text-sm text-gray-600" {
+                                @if !shift.description.is_empty() { (shift.description) " · " }
+                                (left) " of " (shift.needed) " places left"
+                            }
+                        }
+                    }
+                }
+            }
+            div {
+                label for="volunteer_name" class="block text-sm font-medium text-gray-700" { "Name" }
+                input type="text" id="volunteer_name" name="name" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {}
+            }
+            div {
+                label for="volunteer_email" class="block text-sm font-medium text-gray-700" { "Email" }
+                input type="email" id="volunteer_email" name="email" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {}
+            }
+            div {
+                label for="volunteer_phone" class="block text-sm font-medium text-gray-700" { "Phone Number" }
+                input type="tel" id="volunteer_phone" name="phone" class="mt-1 block w-full p-2 border border-gray-300 rounded-md" required {}
+            }
+            div class="text-center" {
+                button type="submit" class="bg-orange-500 text-white px-4 py-2 rounded-md hover:bg-orange-600" { "Sign up" }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignupForm {
+    shift: String,
+    name: String,
+    email: String,
+    phone: String,
+}
+
+/// The section again with a message above the form.
+async fn retry(s: &ClientState, event: &Event, message: &str) -> Markup {
+    let volunteers = volunteers(s, &event.slug).await.unwrap_or_default();
+    html! {
+        section id="volunteer" class="flex flex-col gap-4" {
+            h2 class="text-xl font-semibold text-gray-900" { "Volunteer" }
+            (error_fragment(message))
+            (form(event, &open_shifts(event, &volunteers, today())))
+        }
+    }
+}
+
+fn shift_details(event: &Event, shift: &Shift) -> String {
+    let mut details = format!("Shift: {}\nWhen: {}\n", shift.role, shift.when(event));
+    if let Some(location) = event.location() {
+        details.push_str(&format!("Place: {location}\n"));
+    }
+    if !shift.description.is_empty() {
+        details.push_str(&format!("What to do: {}\n", shift.description));
+    }
+    details
+}
+
+pub async fn signup(
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+    Form(form): Form<SignupForm>,
+) -> Markup {
+    let events = s.events.get();
+    let Some(event) = events.find(&slug) else {
+        return error_fragment("That event does not exist.");
+    };
+    let Some(shift) = event
+        .find_shift(&form.shift)
+        .filter(|shift| shift.day(event) >= today())
+    else {
+        return retry(
+            &s,
+            event,
+            "That shift is no longer available. Please pick another one.",
+        )
+        .await;
+    };
+
+    let name = form.name.trim();
+    if name.is_empty() {
+        return retry(&s, event, "Please tell us your name.").await;
+    }
+    let Ok(to) = form.email.trim().parse::<Mailbox>() else {
+        return retry(
+            &s,
+            event,
+            "That email address does not look right. Please check it and try again.",
+        )
+        .await;
+    };
+
+    let volunteer = {
+        // Held from counting to saving so two people cannot both take the
+        // last place.
+        let _guard = s.registration_lock.lock().await;
+        let existing = match volunteers(&s, &slug).await {
+            Ok(existing) => existing,
+            Err(e) => {
+                tracing::error!(event = %slug, error = %e, "could not load volunteers");
+                return retry(&s, event, &format!("Sorry, we could not sign you up right now. Please try again later or call us at {PHONE}.")).await;
+            }
+        };
+        let email = to.email.to_string();
+        if on_shift(&existing, shift).any(|volunteer| volunteer.email.eq_ignore_ascii_case(&email))
+        {
+            return retry(&s, event, "You are already signed up for this shift.").await;
+        }
+        if filled(&existing, shift) >= shift.needed {
+            return retry(
+                &s,
+                event,
+                "Sorry, that shift has just filled up. Please pick another one.",
+            )
+            .await;
+        }
+        let volunteer = Volunteer {
+            id: ObjectId::new(),
+            event: slug.clone(),
+            shift: shift.id.clone(),
+            name: name.to_string(),
+            email,
+            phone: form.phone.trim().to_string(),
+            created_at: DateTime::now(),
+            reminded_at: None,
+        };
+        if let Err(e) = s.repo::<Volunteer>().insert(&volunteer).await {
+            tracing::error!(event = %slug, error = %e, "could not save volunteer");
+            return retry(&s, event, &format!("Sorry, we could not sign you up right now. Please try again later or call us at {PHONE}.")).await;
+        }
+        volunteer
+    };
+    tracing::info!(event = %slug, volunteer_id = %volunteer.id, shift = %volunteer.shift, "volunteer signed up");
+
+    let body = format!(
+        "Dear {},\n\nThank you for volunteering at {}!\n\n{}\nWe will send you a reminder the day before. \
+         Afterwards you can report your hours at {site}/volunteer-hours.\n\n\
+         Event details: {site}{}\n\nNJ Thiruvalluvar Tamil Sangam\n",
+        volunteer.name,
+        event.title,
+        shift_details(event, shift),
+        event.url(),
+        site = s.config.site_url,
+    );
+    let email = QueuedEmail::new(&to, format!("Volunteering at {}", event.title), body);
+    if let Err(e) = outbox::enqueue(&s, email).await {
+        tracing::error!(volunteer_id = %volunteer.id, error = %e, "could not queue volunteer confirmation");
+    }
+
+    html! {
+        section id="volunteer" class="flex flex-col gap-2 p-4 rounded-lg bg-green-50 border border-green-200" {
+            h2 class="text-xl font-semibold text-green-800" { "Thank you for volunteering, " (volunteer.name) "!" }
+            p { (shift.role) ", " (shift.when(event)) }
+            p { "We have sent the details to " (volunteer.email) "." }
+        }
+    }
+}
+
+/// Queues a reminder for everyone whose shift is on `day` and has not been
+/// reminded yet.
+async fn send_reminders(s: &ClientState, day: NaiveDate) -> Result<(), StoreError> {
+    let events = s.events.get();
+    for event in &events.events {
+        if !event.shifts.iter().any(|shift| shift.day(event) == day) {
+            continue;
+        }
+        for volunteer in volunteers(s, &event.slug).await? {
+            if volunteer.reminded_at.is_some() {
+                continue;
+            }
+            let Some(shift) = event
+                .find_shift(&volunteer.shift)
+                .filter(|shift| shift.day(event) == day)
+            else {
+                continue;
+            };
+            let Ok(to) = volunteer.email.parse::<Mailbox>() else {
+                continue;
+            };
+            let body = format!(
+                "Dear {},\n\nA reminder that you are volunteering at {} tomorrow.\n\n{}\n\
+                 If you can no longer make it, please let us know at {PHONE}.\n\nThank you!\n\nNJ Thiruvalluvar Tamil Sangam\n",
+                volunteer.name,
+                event.title,
+                shift_details(event, shift),
+            );
+            let email = QueuedEmail::new(
+                &to,
+                format!("Tomorrow: volunteering at {}", event.title),
+                body,
+            );
+            outbox::enqueue(s, email).await?;
+            s.repo::<Volunteer>()
+                .update(
+                    doc! { "_id": volunteer.id },
+                    doc! { "reminded_at": DateTime::now() },
+                )
+                .await?;
+            tracing::info!(volunteer_id = %volunteer.id, "volunteer reminder queued");
+        }
+    }
+    Ok(())
+}
+
+/// Starts the reminder task. It runs until the process exits.
+pub fn spawn_reminders(s: ClientState) {
+    tokio::spawn(async move {
+        loop {
+            let today = today();
+            if let Some(tomorrow) = today.checked_add_days(Days::new(1)) {
+                if let Err(e) = send_reminders(&s, tomorrow).await {
+                    tracing::error!(error = %e, "could not send volunteer reminders");
+                }
+            }
+            tokio::time::sleep(REMINDER_INTERVAL).await;
+        }
+    });
+}
+
+/// Organizer overview of the events that need volunteers.
+pub async fn overview(_: Organizer, State(s): State<ClientState>) -> (Meta, Markup) {
+    let events = s.events.get();
+    let mut rows = Vec::new();
+    for event in events
+        .events
+        .iter()
+        .filter(|event| !event.shifts.is_empty())
+    {
+        let volunteers = match volunteers(&s, &event.slug).await {
+            Ok(volunteers) => volunteers,
+            Err(e) => {
+                tracing::error!(event = %event.slug, error = %e, "could not load volunteers");
+                Vec::new()
+            }
+        };
+        let needed: u32 = event.shifts.iter().map(|shift| shift.needed).sum();
+        let filled: u32 = event
+            .shifts
+            .iter()
+            .map(|shift| filled(&volunteers, shift).min(shift.needed))
+            .sum();
+        rows.push((event, filled, needed));
+    }
+    rows.sort_by_key(|(event, _, _)| std::cmp::Reverse(event.date));
+    let meta = Meta::new("Volunteers", "Volunteer shifts of events.");
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-4xl mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-6 text-center" { "Volunteers" }
+                @if rows.is_empty() {
+                    p class="text-center" { "No event has volunteer shifts." }
+                }
+                @for (event, filled, needed) in &rows {
+                    a href=(format!("/admin/volunteers/{}", event.slug)) class="block bg-white p-6 rounded-lg shadow-lg mb-4 hover:bg-gray-50" {
+                        p class="text-xl font-semibold" { (event.title) }
+                        p class="text-gray-700" { (event.dates()) }
+                        p { (filled) " of " (needed) " volunteer places filled" }
+                    }
+                }
+            }
+        }
+    };
+    (meta, content)
+}
+
+/// Organizer view of one event's shifts, filled and unfilled.
+pub async fn list(
+    _: Organizer,
+    State(s): State<ClientState>,
+    Path(slug): Path<String>,
+) -> (Meta, Markup) {
+    let events = s.events.get();
+    let Some(event) = events.find(&slug) else {
+        return (
+            Meta::new("Volunteers", "Volunteer shifts of events."),
+            error_fragment("That event does not exist."),
+        );
+    };
+    let mut shifts: Vec<&Shift> = event.shifts.iter().collect();
+    shifts.sort_by_key(|shift| (shift.day(event), shift.start));
+    let content = match volunteers(&s, &slug).await {
+        Ok(volunteers) => html! {
+            @for shift in &shifts {
+                @let filled = filled(&volunteers, shift);
+                div class="bg-white p-6 rounded-lg shadow-lg mb-4" {
+                    div class="flex justify-between items-baseline gap-4" {
+                        p class="text-xl font-semibold" { (shift.role) }
+                        @if filled < shift.needed {
+                            p class="text-orange-700 font-semibold" { (filled) " of " (shift.needed) " · " (shift.needed - filled) " open" }
+                        } @else {
+                            p class="text-green-700 font-semibold" { (filled) " of " (shift.needed) " · filled" }
+                        }
+                    }
+                    p class="text-gray-700 mb-2" { (shift.when(event)) }
+                    @if filled > 0 {
+                        table class="min-w-full text-left text-sm" {
+                            tbody {
+                                @for volunteer in on_shift(&volunteers, shift) {
+                                    tr class="border-t" {
+                                        td class="p-2" { (volunteer.name) }
+                                        td class="p-2" { (volunteer.email) }
+                                        td class="p-2" { (volunteer.phone) }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        Err(e) => {
+            tracing::error!(event = %slug, error = %e, "could not load volunteers");
+            error_fragment("Could not load the volunteers.")
+        }
+    };
+    let meta = Meta::new(
+        format!("Volunteers: {}", event.title),
+        "Volunteer shifts of one event.",
+    );
+    let content = html! {
+        div class="bg-vertical-to-pink" {
+            div class="max-w-4xl mx-auto p-8" {
+                h1 class="text-3xl font-bold mb-6 text-center" { "Volunteers: " (event.title) }
+                (content)
+            }
+        }
+    };
+    (meta, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::doc;
+
+    use super::*;
+    use crate::{content::Content, events::Events, tests};
+
+    fn events(role: &str, start: &str) -> String {
+        format!(
+            r#"
+            [[event]]
+            title = "Pongal"
+            slug = "pongal"
+            date = "2099-01-15"
+
+            [[event.volunteer]]
+            id = "setup"
+            role = "{role}"
+            start = "{start}"
+            end = "15:00"
+            needed = 2
+            "#
+        )
+    }
+
+    #[tokio::test]
+    async fn sign_ups_survive_edits_to_the_shift() {
+        let dir = tempfile::tempdir().unwrap();
+        let s = tests::with_events(tests::test_state(), dir.path(), &events("Setup", "13:00"));
+        tests::post_form(
+            &s,
+            "/events/pongal/volunteer",
+            "shift=setup&name=Vel&email=vel%40example.com&phone=555",
+            None,
+        )
+        .await;
+        let signed_up = s.repo::<Volunteer>().find(doc! {}).await.unwrap();
+        assert_eq!(signed_up.len(), 1);
+        assert_eq!(signed_up[0].shift, "setup");
+
+        let s = tests::with_events(s, dir.path(), &events("Setup and decoration", "12:30"));
+        let events = s.events.get();
+        let event = events.find("pongal").unwrap();
+        let shift = event.find_shift("setup").unwrap();
+        assert_eq!(shift.role, "Setup and decoration");
+        assert_eq!(filled(&signed_up, shift), 1);
+    }
+
+    #[test]
+    fn shift_ids_must_be_unique_slugs() {
+        let duplicate = format!(
+            "{}\n[[event.volunteer]]\nid = \"setup\"\nrole = \"Food\"\nstart = \"16:00\"\nend = \"18:00\"\nneeded = 1\n",
+            events("Setup", "13:00")
+        );
+        let events: Events = toml::from_str(&duplicate).unwrap();
+        assert!(events.validate().is_err());
+
+        let spaced = events_with_id("Set up");
+        assert!(spaced.validate().is_err());
+        assert!(events_with_id("setup-1").validate().is_ok());
+    }
+
+    fn events_with_id(id: &str) -> Events {
+        toml::from_str(&events("Setup", "13:00").replace("id = \"setup\"", &format!("id = {id:?}")))
+            .unwrap()
+    }
+}